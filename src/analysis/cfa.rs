@@ -40,10 +40,31 @@ impl Display for SectionAddress {
 impl SectionAddress {
     pub fn new(section: usize, address: u32) -> Self { Self { section, address } }
 
-    pub fn offset(self, offset: i32) -> Self {
+    pub fn wrapping_offset(self, offset: i32) -> Self {
         Self { section: self.section, address: self.address.wrapping_add_signed(offset) }
     }
 
+    /// Offsets the address within the same section index, returning `None` if the result
+    /// doesn't fit in 32 bits. A [SectionAddress] doesn't know its section's bounds; use
+    /// [checked_offset](Self::checked_offset) to also reject results outside of the section.
+    pub fn offset(self, delta: i64) -> Option<Self> {
+        let address = u32::try_from(self.address as i64 + delta).ok()?;
+        Some(Self { section: self.section, address })
+    }
+
+    /// Same as [offset](Self::offset), but also returns `None` if the result falls outside
+    /// of the section, looked up in `obj`. The section end address is considered in bounds,
+    /// so the result may be used as an exclusive end.
+    pub fn checked_offset(self, delta: i64, obj: &ObjInfo) -> Option<Self> {
+        let section = obj.sections.get(self.section)?;
+        let result = self.offset(delta)?;
+        let address = result.address as u64;
+        if address < section.address || address > section.address + section.size {
+            return None;
+        }
+        Some(result)
+    }
+
     pub fn align_up(self, align: u32) -> Self {
         Self { section: self.section, address: (self.address + align - 1) & !(align - 1) }
     }
@@ -625,3 +646,36 @@ pub fn locate_bss_memsets(obj: &mut ObjInfo) -> Result<Vec<(u32, u32)>> {
     )?;
     Ok(bss_sections)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::{test_section, ObjArchitecture, ObjKind};
+
+    #[test]
+    fn test_offset() {
+        let section =
+            |name: &str, address| test_section(name, ObjSectionKind::Code, address, vec![0; 0x100]);
+        let obj = ObjInfo::new(
+            ObjKind::Executable,
+            ObjArchitecture::PowerPc,
+            "test".to_string(),
+            vec![],
+            vec![section(".init", 0x80003000), section(".text", 0x80003100)],
+        );
+        let addr = SectionAddress::new(1, 0x80003180);
+        assert_eq!(addr.offset(-0x84), Some(SectionAddress::new(1, 0x800030FC)));
+        assert_eq!(SectionAddress::new(0, 0xFFFFFFFC).offset(4), None);
+        assert_eq!(SectionAddress::new(0, 4).offset(-8), None);
+        assert_eq!(addr.checked_offset(0x40, &obj), Some(SectionAddress::new(1, 0x800031C0)));
+        assert_eq!(addr.checked_offset(-0x80, &obj), Some(SectionAddress::new(1, 0x80003100)));
+        assert_eq!(addr.checked_offset(0x80, &obj), Some(SectionAddress::new(1, 0x80003200)));
+        // Offsets into the neighboring section are rejected
+        assert_eq!(addr.checked_offset(-0x84, &obj), None);
+        assert_eq!(addr.checked_offset(0x84, &obj), None);
+        assert_eq!(SectionAddress::new(2, 0x80003180).checked_offset(0, &obj), None);
+
+        assert_eq!(addr.align_up(0x100), SectionAddress::new(1, 0x80003200));
+        assert!(SectionAddress::new(0, 0x80003200) < SectionAddress::new(1, 0x80003100));
+    }
+}
@@ -23,11 +23,7 @@ pub fn disassemble(section: &ObjSection, address: u32) -> Option<Ins> {
 }
 
 pub fn read_u32(section: &ObjSection, address: u32) -> Option<u32> {
-    let offset = (address as u64 - section.address) as usize;
-    if section.data.len() < offset + 4 {
-        return None;
-    }
-    Some(u32::from_be_bytes(*array_ref!(section.data, offset, 4)))
+    section.read_u32(address).ok()
 }
 
 fn read_unresolved_relocation_address(
@@ -104,8 +100,7 @@ pub fn read_address(obj: &ObjInfo, section: &ObjSection, address: u32) -> Result
             format!("Failed to find relocation for {:#010X} in section {}", address, section.name)
         })
     } else {
-        let offset = (address as u64 - section.address) as usize;
-        let address = u32::from_be_bytes(*array_ref!(section.data, offset, 4));
+        let address = section.read_word(address, obj.architecture.arch())?;
         let (section_index, _) = obj.sections.at_address(address)?;
         Ok(SectionAddress::new(section_index, address))
     }
@@ -267,9 +267,11 @@ impl VM {
                         GprValue::Constant(value) => {
                             GprValue::Constant(value.wrapping_add(ins.field_simm() as u32))
                         }
-                        GprValue::Address(RelocationTarget::Address(address)) => GprValue::Address(
-                            RelocationTarget::Address(address.offset(ins.field_simm() as i32)),
-                        ),
+                        GprValue::Address(RelocationTarget::Address(address)) => {
+                            GprValue::Address(RelocationTarget::Address(
+                                address.wrapping_offset(ins.field_simm() as i32),
+                            ))
+                        }
                         _ => GprValue::Unknown,
                     };
                     if load_zero {
@@ -33,8 +33,9 @@ use crate::{
     },
     cmd::shasum::file_sha1_string,
     obj::{
-        best_match_for_reloc, ObjInfo, ObjKind, ObjReloc, ObjRelocKind, ObjSectionKind, ObjSymbol,
-        ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind, ObjSymbolScope, SymbolIndex,
+        best_match_for_reloc, AutoUnitNaming, ObjInfo, ObjKind, ObjReloc, ObjRelocKind,
+        ObjSectionKind, ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind, ObjSymbolScope,
+        SymbolIndex,
     },
     util::{
         asm::write_asm,
@@ -236,6 +237,9 @@ pub struct ProjectConfig {
     /// Marks all emitted symbols as "exported" to prevent the linker from removing them.
     #[serde(default = "bool_true", skip_serializing_if = "is_true")]
     pub export_all: bool,
+    /// Naming scheme for autogenerated units: `symbol` (default) or `address`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub auto_unit_naming: AutoUnitNaming,
 }
 
 impl Default for ProjectConfig {
@@ -254,6 +258,7 @@ impl Default for ProjectConfig {
             symbols_known: false,
             fill_gaps: true,
             export_all: true,
+            auto_unit_naming: AutoUnitNaming::Symbol,
         }
     }
 }
@@ -801,6 +806,7 @@ fn load_analyze_dol(config: &ProjectConfig) -> Result<AnalyzeResult> {
     if let Some(comment_version) = config.mw_comment_version {
         obj.mw_comment = Some(MWComment::new(comment_version)?);
     }
+    obj.auto_unit_naming = config.auto_unit_naming;
 
     if let Some(map_path) = &config.base.map {
         apply_map_file(map_path, &mut obj, config.common_start, config.mw_comment_version)?;
@@ -1030,6 +1036,7 @@ fn load_analyze_rel(config: &ProjectConfig, module_config: &ModuleConfig) -> Res
     if let Some(comment_version) = config.mw_comment_version {
         module_obj.mw_comment = Some(MWComment::new(comment_version)?);
     }
+    module_obj.auto_unit_naming = config.auto_unit_naming;
 
     let mut dep = vec![module_config.object.clone()];
     if let Some(map_path) = &module_config.map {
@@ -96,6 +96,9 @@ pub struct ConfigArgs {
     #[argp(positional)]
     /// output directory
     out_dir: PathBuf,
+    #[argp(option, short = 'u', long = "unit")]
+    /// (optional) unit object(s), used to record each unit's comment version
+    units: Vec<PathBuf>,
 }
 
 #[derive(FromArgs, PartialEq, Eq, Debug)]
@@ -135,7 +138,19 @@ pub fn run(args: Args) -> Result<()> {
 
 fn config(args: ConfigArgs) -> Result<()> {
     log::info!("Loading {}", args.in_file.display());
-    let obj = process_elf(&args.in_file)?;
+    let mut obj = process_elf(&args.in_file)?;
+    for path in process_rsp(&args.units)? {
+        let unit_obj = process_elf(&path)?;
+        ensure!(
+            unit_obj.kind == ObjKind::Relocatable,
+            "Unit object {} is not relocatable",
+            path.display()
+        );
+        if !obj.link_order.iter().any(|u| u.name == unit_obj.name) {
+            log::warn!("Unit {} ({}) not found in link order", unit_obj.name, path.display());
+        }
+        obj.import_unit(&unit_obj);
+    }
 
     DirBuilder::new().recursive(true).create(&args.out_dir)?;
     write_symbols_file(args.out_dir.join("symbols.txt"), &obj, None)?;
@@ -7,7 +7,10 @@ use anyhow::{anyhow, bail, ensure, Result};
 use argp::FromArgs;
 use object::{Architecture, Endianness, Object, ObjectKind, ObjectSection, SectionKind};
 
-use crate::util::file::{buf_writer, map_file};
+use crate::util::{
+    elf::{PaddingPolicy, SectionPadding},
+    file::{buf_writer, map_file},
+};
 
 #[derive(FromArgs, PartialEq, Eq, Debug)]
 /// Converts an ELF file to a DOL file.
@@ -22,6 +25,12 @@ pub struct Args {
     /// sections (by name) to ignore
     #[argp(option, long = "ignore")]
     deny_sections: Vec<String>,
+    /// padding for code sections: nop (default), zero or ff
+    #[argp(option)]
+    code_padding: Option<PaddingPolicy>,
+    /// padding for data sections: zero (default), nop or ff
+    #[argp(option)]
+    data_padding: Option<PaddingPolicy>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -58,6 +67,9 @@ pub fn run(args: Args) -> Result<()> {
         kind => bail!("Unexpected ELF type: {kind:?}"),
     }
 
+    let default_padding = SectionPadding::default();
+    let code_padding = args.code_padding.unwrap_or(default_padding.code);
+    let data_padding = args.data_padding.unwrap_or(default_padding.data);
     let mut header = DolHeader { entry_point: obj_file.entry() as u32, ..Default::default() };
     let mut offset = 0x100u32;
     let mut out = buf_writer(&args.dol_file)?;
@@ -79,7 +91,7 @@ pub fn run(args: Args) -> Result<()> {
             )
         })? = DolSection { offset, address, size };
         header.text_section_count += 1;
-        write_aligned(&mut out, section.data()?, size)?;
+        write_aligned(&mut out, section.data()?, size, code_padding)?;
         offset += size;
     }
 
@@ -99,7 +111,7 @@ pub fn run(args: Args) -> Result<()> {
             )
         })? = DolSection { offset, address, size };
         header.data_section_count += 1;
-        write_aligned(&mut out, section.data()?, size)?;
+        write_aligned(&mut out, section.data()?, size, data_padding)?;
         offset += size;
     }
 
@@ -155,15 +167,20 @@ pub fn run(args: Args) -> Result<()> {
 #[inline]
 const fn align32(x: u32) -> u32 { (x + 31) & !31 }
 
-const ZERO_BUF: [u8; 32] = [0u8; 32];
-
 #[inline]
-fn write_aligned<T>(out: &mut T, bytes: &[u8], aligned_size: u32) -> std::io::Result<()>
-where T: Write + ?Sized {
+fn write_aligned<T>(
+    out: &mut T,
+    bytes: &[u8],
+    aligned_size: u32,
+    padding: PaddingPolicy,
+) -> std::io::Result<()>
+where
+    T: Write + ?Sized,
+{
     out.write_all(bytes)?;
-    let padding = aligned_size - bytes.len() as u32;
-    if padding > 0 {
-        out.write_all(&ZERO_BUF[0..padding as usize])?;
+    let fill = (bytes.len()..aligned_size as usize).map(|i| padding.byte_at(i)).collect::<Vec<_>>();
+    if !fill.is_empty() {
+        out.write_all(&fill)?;
     }
     Ok(())
 }
@@ -197,3 +214,35 @@ fn is_alloc(flags: object::SectionFlags) -> bool {
 fn is_name_allowed(s: &object::Section, denied: &[String]) -> bool {
     !denied.contains(&s.name().unwrap_or("[error]").to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_write_aligned_padding() {
+        let code = [0x4E, 0x80, 0x00, 0x20, 0x38, 0x60, 0x00, 0x00];
+        let mut out = vec![];
+        write_aligned(&mut out, &code, align32(code.len() as u32), PaddingPolicy::Nop).unwrap();
+        assert_eq!(out.len(), 32);
+        assert_eq!(&out[..8], &code);
+        assert!(out[8..].chunks(4).all(|c| c == [0x60, 0x00, 0x00, 0x00]));
+
+        let data = [1, 2, 3, 4, 5];
+        let mut out = vec![];
+        write_aligned(&mut out, &data, align32(data.len() as u32), PaddingPolicy::Zero).unwrap();
+        assert_eq!(out.len(), 32);
+        assert_eq!(&out[..5], &data);
+        assert!(out[5..].iter().all(|&b| b == 0));
+
+        // Unaligned code padding stays in phase with the section start
+        let mut out = vec![];
+        write_aligned(&mut out, &data, 12, PaddingPolicy::Nop).unwrap();
+        assert_eq!(&out[5..], &[0, 0, 0, 0x60, 0, 0, 0]);
+
+        assert_eq!(PaddingPolicy::from_str("ff"), Ok(PaddingPolicy::Ones));
+        assert!(PaddingPolicy::from_str("nope").is_err());
+    }
+}
@@ -28,7 +28,10 @@ use crate::{
     },
     array_ref_mut,
     cmd::dol::{ModuleConfig, ProjectConfig},
-    obj::{ObjInfo, ObjReloc, ObjRelocKind, ObjSection, ObjSectionKind, ObjSymbol},
+    obj::{
+        split_ha_lo, Architecture as _, ObjInfo, ObjReloc, ObjRelocKind, ObjSection,
+        ObjSectionKind, ObjSymbol,
+    },
     util::{
         config::{is_auto_symbol, read_splits_sections, SectionDef},
         dol::process_dol,
@@ -511,6 +514,7 @@ fn merge(args: MergeArgs) -> Result<()> {
                 file_offset: mod_section.file_offset,
                 section_known: mod_section.section_known,
                 splits: mod_section.splits.clone(),
+                compressed_size_cache: Default::default(),
             });
             section_map.nested_insert(module.module_id, mod_section.elf_index as u32, offset)?;
             for (_, mod_symbol) in module.symbols.for_section(mod_section_index) {
@@ -606,13 +610,15 @@ fn merge(args: MergeArgs) -> Result<()> {
 }
 
 fn link_relocations(obj: &mut ObjInfo) -> Result<()> {
+    let arch = obj.architecture.arch();
     for (_, section) in obj.sections.iter_mut() {
         for (source_address, reloc) in section.relocations.iter() {
-            let target_address =
-                (obj.symbols[reloc.target_symbol].address as i64 + reloc.addend) as u32;
+            let target_address = reloc
+                .target_address(obj.symbols[reloc.target_symbol].address)
+                .with_context(|| format!("Relocation @ {:#010X}", source_address))?;
             let ins_ref =
                 array_ref_mut!(section.data, (source_address as u64 - section.address) as usize, 4);
-            let mut ins = u32::from_be_bytes(*ins_ref);
+            let mut ins = arch.read_u32(*ins_ref);
             match reloc.kind {
                 ObjRelocKind::Absolute => {
                     ins = target_address;
@@ -621,10 +627,10 @@ fn link_relocations(obj: &mut ObjInfo) -> Result<()> {
                     ins = (ins & 0xffff0000) | ((target_address >> 16) & 0xffff);
                 }
                 ObjRelocKind::PpcAddr16Ha => {
-                    ins = (ins & 0xffff0000) | (((target_address + 0x8000) >> 16) & 0xffff);
+                    ins = (ins & 0xffff0000) | split_ha_lo(target_address, 0).0 as u32;
                 }
                 ObjRelocKind::PpcAddr16Lo => {
-                    ins = (ins & 0xffff0000) | (target_address & 0xffff);
+                    ins = (ins & 0xffff0000) | split_ha_lo(target_address, 0).1 as u32;
                 }
                 ObjRelocKind::PpcRel24 => {
                     let diff = target_address as i32 - source_address as i32;
@@ -646,7 +652,7 @@ fn link_relocations(obj: &mut ObjInfo) -> Result<()> {
                     // Unused in RELs
                 }
             };
-            *ins_ref = ins.to_be_bytes();
+            *ins_ref = arch.write_u32(ins);
         }
     }
     Ok(())
@@ -5,7 +5,7 @@ use argp::FromArgs;
 
 use crate::util::{
     file::{map_file_basic, process_rsp},
-    ncompress::{compress_yaz0, decompress_yaz0},
+    ncompress::{compress_yaz0, compress_yaz0_parallel, decompress_yaz0, YAZ0_PARALLEL_WINDOW},
     IntoCow, ToCow,
 };
 
@@ -35,6 +35,9 @@ pub struct CompressArgs {
     /// Output file (or directory, if multiple files are specified).
     /// If not specified, compresses in-place.
     output: Option<PathBuf>,
+    #[argp(switch)]
+    /// Compress using multiple threads, for large files.
+    parallel: bool,
 }
 
 #[derive(FromArgs, PartialEq, Eq, Debug)]
@@ -63,7 +66,11 @@ fn compress(args: CompressArgs) -> Result<()> {
     for path in files {
         let data = {
             let file = map_file_basic(&path)?;
-            compress_yaz0(file.as_slice())
+            if args.parallel {
+                compress_yaz0_parallel(file.as_slice(), YAZ0_PARALLEL_WINDOW)
+            } else {
+                compress_yaz0(file.as_slice())
+            }
         };
         let out_path = if let Some(output) = &args.output {
             if single_file {
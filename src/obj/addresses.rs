@@ -31,6 +31,11 @@ impl AddressRanges {
         }
     }
 
+    /// Iterate over (start, end address) pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (SectionAddress, u32)> + '_ {
+        self.inner.iter().copied()
+    }
+
     pub fn contains(&self, address: SectionAddress) -> bool {
         let pos = match self.inner.binary_search_by_key(&address, |&(start, _)| start) {
             Ok(_) => return true,
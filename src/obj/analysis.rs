@@ -0,0 +1,962 @@
+use std::{
+    cmp::max,
+    collections::{BTreeMap, HashSet},
+    fmt,
+};
+
+use anyhow::{anyhow, ensure, Result};
+use itertools::Itertools;
+#[cfg(feature = "parallel-analysis")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    analysis::cfa::SectionAddress,
+    array_ref,
+    obj::{
+        ObjDataKind, ObjInfo, ObjRelocKind, ObjSectionKind, ObjSplit, ObjSymbol, ObjSymbolFlagSet,
+        ObjSymbolFlags, ObjSymbolKind, RelocMismatch,
+    },
+    util::{align_up, extab},
+};
+
+/// Which checks [ObjInfo::validate] runs.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Default)]
+pub enum ValidationLevel {
+    /// Structural checks on splits, symbols and relocation targets.
+    #[default]
+    Basic,
+    /// Also decodes instructions at relocation sites and exception table records.
+    Full,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DiagnosticCategory {
+    Split,
+    Symbol,
+    Relocation,
+    ExceptionTable,
+}
+
+/// A problem found by [ObjInfo::validate].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub category: DiagnosticCategory,
+    pub section: Option<usize>,
+    pub address: Option<u32>,
+    /// Description, including the formatted location.
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{} [{:?}]: {}", severity, self.category, self.message)
+    }
+}
+
+/// Results of [ObjInfo::analyze_sections].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SectionAnalysis {
+    /// Number of pool symbols created.
+    pub symbols_added: usize,
+    /// Relocation mismatches, in section and address order.
+    pub mismatches: Vec<RelocMismatch>,
+}
+
+impl ObjInfo {
+    /// Looks for undetected functions in gaps between consecutive sized
+    /// [known_functions](Self::known_functions) entries within a code section. To avoid false
+    /// positives, the gap must be free of relocations and, after any zero or `nop` padding,
+    /// begin with a prologue: `stwu r1, -N(r1)` and `mflr r0` within the first three
+    /// instructions. Found functions are registered without a size. Returns the number of
+    /// functions added.
+    pub fn detect_gap_functions(&mut self) -> usize {
+        const MFLR_R0: u32 = 0x7C0802A6;
+        const NOP: u32 = 0x60000000;
+        let is_stwu_r1 = |ins: u32| ins & 0xFFFF8000 == 0x94218000;
+
+        let arch = self.architecture.arch();
+        let mut found = vec![];
+        for ((start, size), (next, _)) in self.known_functions.iter().tuple_windows() {
+            let (Some(size), true) = (*size, start.section == next.section) else {
+                continue;
+            };
+            let section = &self.sections[start.section];
+            if section.kind != ObjSectionKind::Code {
+                continue;
+            }
+            let gap = align_up(start.address + size, 4)..next.address;
+            if gap.start >= gap.end || section.relocations.range(gap.clone()).next().is_some() {
+                continue;
+            }
+            let Ok(data) = section.data_range(gap.start, gap.end) else {
+                continue;
+            };
+            let words = data
+                .chunks_exact(4)
+                .map(|w| arch.read_u32(w.try_into().unwrap()))
+                .enumerate()
+                .skip_while(|&(_, ins)| ins == 0 || ins == NOP)
+                .take(3)
+                .collect_vec();
+            let Some(&(offset, _)) = words.first() else {
+                continue;
+            };
+            if words.iter().any(|&(_, ins)| is_stwu_r1(ins))
+                && words.iter().any(|&(_, ins)| ins == MFLR_R0)
+            {
+                found.push(SectionAddress::new(start.section, gap.start + offset as u32 * 4));
+            }
+        }
+        for address in &found {
+            log::debug!("Found function in gap at {:#010X}", address.address);
+            self.known_functions.insert(*address, None);
+        }
+        found.len()
+    }
+
+    /// Creates auto-named function symbols for [known_functions](Self::known_functions)
+    /// entries that no function symbol covers, sized by the recorded size when available.
+    /// Returns the number of symbols created.
+    pub fn materialize_known_functions(&mut self) -> Result<usize> {
+        let mut count = 0;
+        for (&address, &size) in &self.known_functions {
+            let covered = self
+                .symbols
+                .for_section_range(address.section, ..=address.address)
+                .rev()
+                .find(|(_, s)| s.kind == ObjSymbolKind::Function)
+                .is_some_and(|(_, s)| {
+                    s.address == address.address as u64
+                        || s.address + s.size > address.address as u64
+                });
+            if covered {
+                continue;
+            }
+            let name = self.auto_symbol_name(address, ObjSymbolKind::Function);
+            self.symbols.add(
+                ObjSymbol {
+                    name,
+                    address: address.address as u64,
+                    section: Some(address.section),
+                    size: size.unwrap_or(0) as u64,
+                    size_known: size.is_some(),
+                    kind: ObjSymbolKind::Function,
+                    ..Default::default()
+                },
+                false,
+            )?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Sets the size of zero-size symbols to the gap up to the next symbol, or the section end.
+    /// Functions and objects extend past labels (`ObjSymbolKind::Unknown`), but a label
+    /// never extends past the next symbol of any kind.
+    pub fn infer_symbol_sizes(&mut self) -> Result<()> {
+        let mut updates = vec![];
+        for (section_index, section) in self.sections.iter() {
+            let section_end = section.address + section.size;
+            let symbols = self
+                .symbols
+                .for_section(section_index)
+                .filter(|(_, s)| s.kind != ObjSymbolKind::Section)
+                .map(|(idx, s)| (idx, s.address, s.size, s.kind))
+                .collect_vec();
+            for (i, &(symbol_idx, address, size, kind)) in symbols.iter().enumerate() {
+                if size != 0 {
+                    continue;
+                }
+                let next_address = symbols[i + 1..]
+                    .iter()
+                    .find(|&&(_, next_address, _, next_kind)| {
+                        next_address > address
+                            && (kind == ObjSymbolKind::Unknown
+                                || next_kind != ObjSymbolKind::Unknown)
+                    })
+                    .map_or(section_end, |&(_, next_address, _, _)| next_address);
+                if next_address > address {
+                    updates.push((symbol_idx, next_address - address));
+                }
+            }
+        }
+        for (symbol_idx, size) in updates {
+            let mut symbol = self.symbols[symbol_idx].clone();
+            symbol.size = size;
+            symbol.size_known = true;
+            self.symbols.replace(symbol_idx, symbol)?;
+        }
+        Ok(())
+    }
+
+    /// Creates a string symbol for each literal in a string pool, starting at the beginning
+    /// of the section. Literals must be printable and zero-terminated. Zero padding up to the
+    /// next 4-byte boundary is skipped, and scanning stops at the first non-string data.
+    /// Returns the number of string symbols created.
+    pub fn split_string_pool(&mut self, section_index: usize) -> Result<usize> {
+        let symbols = self.string_pool_symbols(section_index)?;
+        self.add_analyzed_symbols(symbols)
+    }
+
+    fn string_pool_symbols(&self, section_index: usize) -> Result<Vec<ObjSymbol>> {
+        let section = self
+            .sections
+            .get(section_index)
+            .ok_or_else(|| anyhow!("Invalid section index {}", section_index))?;
+        ensure!(
+            matches!(section.kind, ObjSectionKind::Data | ObjSectionKind::ReadOnlyData),
+            "Section {} is not a data section",
+            section.name
+        );
+        let mut strings = vec![];
+        let mut offset = 0;
+        while offset < section.data.len() {
+            let data = &section.data[offset..];
+            let Some(len) = data.iter().position(|&c| c == 0) else {
+                break;
+            };
+            if len == 0
+                || !data[..len].iter().all(|c| c.is_ascii_graphic() || c.is_ascii_whitespace())
+            {
+                break;
+            }
+            strings.push((section.address + offset as u64, len as u64 + 1));
+            offset += len + 1;
+            while offset % 4 != 0 && section.data.get(offset) == Some(&0) {
+                offset += 1;
+            }
+        }
+
+        Ok(strings
+            .into_iter()
+            .enumerate()
+            .map(|(index, (address, size))| ObjSymbol {
+                name: format!("@stringPool{}_{}", section_index, index),
+                address,
+                section: Some(section_index),
+                size,
+                size_known: true,
+                flags: ObjSymbolFlagSet(ObjSymbolFlags::Local.into()),
+                kind: ObjSymbolKind::Object,
+                data_kind: ObjDataKind::String,
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    /// Creates `Float` and `Double` symbols for constants in a pool section, skipping any
+    /// data already covered by a symbol. Doubles are only considered at 8-byte aligned
+    /// addresses, and are preferred when the value has a shorter decimal representation
+    /// than the two floats occupying the same bytes. If `dedup` is set, only the first
+    /// occurrence of each value receives a symbol. Returns the number of symbols created.
+    pub fn split_float_pool(&mut self, section_index: usize, dedup: bool) -> Result<usize> {
+        let symbols = self.float_pool_symbols(section_index, dedup)?;
+        self.add_analyzed_symbols(symbols)
+    }
+
+    fn float_pool_symbols(&self, section_index: usize, dedup: bool) -> Result<Vec<ObjSymbol>> {
+        let section = self
+            .sections
+            .get(section_index)
+            .ok_or_else(|| anyhow!("Invalid section index {}", section_index))?;
+        ensure!(
+            matches!(section.kind, ObjSectionKind::Data | ObjSectionKind::ReadOnlyData),
+            "Section {} is not a data section",
+            section.name
+        );
+        let covered = self
+            .symbols
+            .for_section(section_index)
+            .filter(|(_, s)| s.kind != ObjSymbolKind::Section)
+            .map(|(_, s)| s.address..s.address + max(s.size, 1))
+            .collect_vec();
+        let is_covered = |address: u64, size: u64| {
+            covered.iter().any(|r| r.start < address + size && address < r.end)
+        };
+
+        let mut seen = HashSet::new();
+        let mut constants = vec![];
+        let mut offset = (align_up(section.address as u32, 4) as u64 - section.address) as usize;
+        while offset + 4 <= section.data.len() {
+            let address = section.address + offset as u64;
+            if is_covered(address, 4) {
+                offset += 4;
+                continue;
+            }
+            let data = &section.data[offset..];
+            let (data_kind, size) = if address % 8 == 0
+                && data.len() >= 8
+                && !is_covered(address, 8)
+                && prefer_double(array_ref!(data, 0, 8))
+            {
+                (ObjDataKind::Double, 8)
+            } else {
+                (ObjDataKind::Float, 4)
+            };
+            if !dedup || seen.insert(&data[..size]) {
+                constants.push((address, data_kind, size as u64));
+            }
+            offset += size;
+        }
+
+        Ok(constants
+            .into_iter()
+            .enumerate()
+            .map(|(index, (address, data_kind, size))| ObjSymbol {
+                name: format!("@floatPool{}_{}", section_index, index),
+                address,
+                section: Some(section_index),
+                size,
+                size_known: true,
+                flags: ObjSymbolFlagSet(ObjSymbolFlags::Local.into()),
+                kind: ObjSymbolKind::Object,
+                data_kind,
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    fn add_analyzed_symbols(&mut self, symbols: Vec<ObjSymbol>) -> Result<usize> {
+        let count = symbols.len();
+        for symbol in symbols {
+            self.add_symbol(symbol, false)?;
+        }
+        Ok(count)
+    }
+
+    /// Runs the independent per-section analysis passes over every section:
+    /// - `.sdata2` is split as a float pool.
+    /// - Other data sections without any symbols are split as string pools.
+    /// - Relocations are verified, as in [verify_relocations](Self::verify_relocations).
+    ///
+    /// Symbols are added in section order once all sections are analyzed.
+    pub fn analyze_sections(&mut self) -> Result<SectionAnalysis> {
+        let results = (0..self.sections.len())
+            .map(|section_index| self.analyze_section(section_index))
+            .collect::<Result<Vec<_>>>()?;
+        self.merge_section_analysis(results)
+    }
+
+    /// Same as [analyze_sections](Self::analyze_sections), but analyzes sections concurrently.
+    /// Results are merged in section order, so the outcome matches the sequential path.
+    #[cfg(feature = "parallel-analysis")]
+    pub fn analyze_sections_parallel(&mut self) -> Result<SectionAnalysis> {
+        let results = (0..self.sections.len())
+            .into_par_iter()
+            .map(|section_index| self.analyze_section(section_index))
+            .collect::<Result<Vec<_>>>()?;
+        self.merge_section_analysis(results)
+    }
+
+    fn analyze_section(
+        &self,
+        section_index: usize,
+    ) -> Result<(Vec<ObjSymbol>, Vec<RelocMismatch>)> {
+        let section = &self.sections[section_index];
+        let symbols =
+            if !matches!(section.kind, ObjSectionKind::Data | ObjSectionKind::ReadOnlyData) {
+                vec![]
+            } else if section.name == ".sdata2" {
+                self.float_pool_symbols(section_index, false)?
+            } else if self
+                .symbols
+                .for_section(section_index)
+                .all(|(_, s)| s.kind == ObjSymbolKind::Section)
+            {
+                self.string_pool_symbols(section_index)?
+            } else {
+                vec![]
+            };
+        Ok((symbols, self.section_reloc_mismatches(section_index, section)))
+    }
+
+    fn merge_section_analysis(
+        &mut self,
+        results: Vec<(Vec<ObjSymbol>, Vec<RelocMismatch>)>,
+    ) -> Result<SectionAnalysis> {
+        let mut analysis = SectionAnalysis::default();
+        for (symbols, mismatches) in results {
+            analysis.symbols_added += self.add_analyzed_symbols(symbols)?;
+            analysis.mismatches.extend(mismatches);
+        }
+        Ok(analysis)
+    }
+
+    /// Runs all consistency checks and returns their findings, ordered by location:
+    /// - Splits overlapping each other or extending past their section.
+    /// - Symbols outside of their section, and overlapping sized symbols.
+    /// - Relocations against nonexistent symbols.
+    /// - With [ValidationLevel::Full], relocations not matching their site (see
+    ///   [verify_relocations](Self::verify_relocations)) and undecodable `extab` records.
+    pub fn validate(&self, level: ValidationLevel) -> Vec<Diagnostic> {
+        let fmt = self.address_format;
+        let mut diagnostics = vec![];
+        for (section_index, section) in self.sections.iter() {
+            let section_end = section.address + section.size;
+            let mut push = |severity, category, address, message: String| {
+                diagnostics.push(Diagnostic {
+                    severity,
+                    category,
+                    section: Some(section_index),
+                    address: Some(address),
+                    message: format!("{}: {}", fmt.address(section, address), message),
+                });
+            };
+
+            let mut last_split: Option<(u32, &ObjSplit)> = None;
+            for (addr, split) in section.splits.iter() {
+                // An end of 0 runs to the end of the section
+                let end = if split.end == 0 { section_end as u32 } else { split.end };
+                if end < addr {
+                    let message =
+                        format!("Split {} has invalid end {:#010X}", split.unit, split.end);
+                    push(Severity::Error, DiagnosticCategory::Split, addr, message);
+                } else if end as u64 > section_end {
+                    let message =
+                        format!("Split {} extends past the end of the section", split.unit);
+                    push(Severity::Error, DiagnosticCategory::Split, addr, message);
+                }
+                if let Some((_, last)) = last_split.filter(|&(last_end, _)| last_end > addr) {
+                    let message = format!("Split {} overlaps split {}", split.unit, last.unit);
+                    push(Severity::Error, DiagnosticCategory::Split, addr, message);
+                }
+                last_split = Some((end, split));
+            }
+
+            let mut last_symbol: Option<&ObjSymbol> = None;
+            for (_, symbol) in self.symbols.for_section(section_index) {
+                if symbol.kind == ObjSymbolKind::Section || symbol.flags.is_stripped() {
+                    continue;
+                }
+                let address = symbol.address as u32;
+                if symbol.address < section.address || symbol.address + symbol.size > section_end {
+                    let message = format!("Symbol {} lies outside of the section", symbol.name);
+                    push(Severity::Error, DiagnosticCategory::Symbol, address, message);
+                }
+                if !symbol.size_known || symbol.size == 0 {
+                    continue;
+                }
+                if let Some(last) =
+                    last_symbol.filter(|last| last.address + last.size > symbol.address)
+                {
+                    let message = format!("Symbol {} overlaps symbol {}", symbol.name, last.name);
+                    push(Severity::Warning, DiagnosticCategory::Symbol, address, message);
+                }
+                if last_symbol
+                    .map_or(true, |last| symbol.address + symbol.size > last.address + last.size)
+                {
+                    last_symbol = Some(symbol);
+                }
+            }
+
+            for (address, reloc) in section.relocations.iter() {
+                if reloc.target_symbol >= self.symbols.count() {
+                    let message = format!(
+                        "{:?} relocation targets nonexistent symbol {}",
+                        reloc.kind, reloc.target_symbol
+                    );
+                    push(Severity::Error, DiagnosticCategory::Relocation, address, message);
+                }
+            }
+
+            if level < ValidationLevel::Full {
+                continue;
+            }
+            for mismatch in self.section_reloc_mismatches(section_index, section) {
+                let message = if mismatch.misaligned {
+                    format!("Misaligned {:?} relocation", mismatch.kind)
+                } else {
+                    format!(
+                        "{:?} relocation doesn't match instruction {:#010X}",
+                        mismatch.kind,
+                        mismatch.instruction.unwrap_or_default()
+                    )
+                };
+                push(Severity::Warning, DiagnosticCategory::Relocation, mismatch.address, message);
+            }
+            if section.name == "extab" {
+                for (_, symbol) in self.symbols.for_section(section_index) {
+                    if symbol.kind != ObjSymbolKind::Object || symbol.size == 0 {
+                        continue;
+                    }
+                    let address = symbol.address as u32;
+                    let result = section
+                        .symbol_data(symbol)
+                        .and_then(|data| extab::decode(data).map(|_| ()));
+                    if let Err(e) = result {
+                        let message = format!("Failed to decode {}: {}", symbol.name, e);
+                        push(
+                            Severity::Warning,
+                            DiagnosticCategory::ExceptionTable,
+                            address,
+                            message,
+                        );
+                    }
+                }
+            }
+        }
+        diagnostics.sort_by_key(|d| (d.section, d.address));
+        diagnostics
+    }
+
+    /// Creates symbols for relocation targets that no symbol covers. Targets within 0x10
+    /// bytes of each other (with no symbol between them) are clustered, and an auto-named
+    /// symbol is created at the head of each cluster with at least two references: a
+    /// `hi`/`ha` and `l` pair counts as a single reference, as does each other relocation.
+    /// Symbols are created as a function when branched to from a code section, a label for
+    /// other code targets, and an object otherwise. Targets in
+    /// [blocked_relocation_targets](Self::blocked_relocation_targets) are ignored.
+    /// Returns the number of symbols created; follow with
+    /// [reresolve_relocations](Self::reresolve_relocations) to re-bind relocations to them.
+    pub fn discover_symbols_from_relocations(&mut self) -> Result<usize> {
+        const CLUSTER_DISTANCE: u32 = 0x10;
+        const MIN_REFERENCES: usize = 2;
+
+        let mut targets = BTreeMap::<SectionAddress, Vec<ObjRelocKind>>::new();
+        for (_, section) in self.sections.iter() {
+            for (_, reloc) in section.relocations.iter() {
+                if matches!(reloc.module, Some(module_id) if module_id != self.module_id) {
+                    continue;
+                }
+                let symbol = &self.symbols[reloc.target_symbol];
+                let target = symbol.address as i64 + reloc.addend;
+                let (Some(target_section), Ok(target)) = (symbol.section, u32::try_from(target))
+                else {
+                    continue;
+                };
+                let target = SectionAddress::new(target_section, target);
+                if self.blocked_relocation_targets.contains(target)
+                    || !self
+                        .sections
+                        .get(target_section)
+                        .is_some_and(|s| s.contains(target.address))
+                    || self.symbols.for_relocation(target, reloc.kind)?.is_some()
+                {
+                    continue;
+                }
+                targets.entry(target).or_default().push(reloc.kind);
+            }
+        }
+
+        let mut clusters: Vec<(SectionAddress, Vec<ObjRelocKind>)> = vec![];
+        let mut last_target: Option<SectionAddress> = None;
+        for (target, kinds) in targets {
+            let extends = last_target.is_some_and(|last| {
+                last.section == target.section
+                    && target.address - last.address <= CLUSTER_DISTANCE
+                    && self
+                        .symbols
+                        .for_section_range(target.section, last.address + 1..=target.address)
+                        .next()
+                        .is_none()
+            });
+            match clusters.last_mut() {
+                Some((_, cluster_kinds)) if extends => cluster_kinds.extend(kinds),
+                _ => clusters.push((target, kinds)),
+            }
+            last_target = Some(target);
+        }
+
+        // Each `hi`/`ha` is normally paired with one or more `l` relocations
+        let references = |kinds: &[ObjRelocKind]| {
+            let high = kinds
+                .iter()
+                .filter(|k| matches!(k, ObjRelocKind::PpcAddr16Hi | ObjRelocKind::PpcAddr16Ha))
+                .count();
+            let low = kinds.iter().filter(|&&k| k == ObjRelocKind::PpcAddr16Lo).count();
+            kinds.iter().filter(|k| !k.is_halfword()).count() + high.max(low)
+        };
+        let mut count = 0;
+        for (head, kinds) in clusters {
+            let references = references(&kinds);
+            if references < MIN_REFERENCES {
+                continue;
+            }
+            let kind = match self.sections[head.section].kind {
+                ObjSectionKind::Code if kinds.iter().any(|k| k.is_branch()) => {
+                    ObjSymbolKind::Function
+                }
+                ObjSectionKind::Code => ObjSymbolKind::Unknown,
+                _ => ObjSymbolKind::Object,
+            };
+            let index = self.ensure_symbol_at(head, kind)?;
+            log::debug!(
+                "Discovered symbol {} from {} references",
+                self.symbols[index].name,
+                references
+            );
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// Whether 8 bytes of big-endian data look more like a double than a pair of floats.
+fn prefer_double(data: &[u8; 8]) -> bool {
+    fn digits(repr: String) -> usize {
+        repr.split('e').next().unwrap_or_default().chars().filter(char::is_ascii_digit).count()
+    }
+    let double = f64::from_be_bytes(*data);
+    let (hi, lo) =
+        (f32::from_be_bytes(*array_ref!(data, 0, 4)), f32::from_be_bytes(*array_ref!(data, 4, 4)));
+    if !double.is_finite() {
+        return false;
+    }
+    if !hi.is_finite() || !lo.is_finite() {
+        return true;
+    }
+    digits(format!("{:e}", double)) < digits(format!("{:e}", hi)) + digits(format!("{:e}", lo))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::{test_obj, test_section, test_split, ObjReloc};
+
+    #[test]
+    fn test_detect_gap_functions() {
+        let words = [
+            // fn_80003100
+            0x4E800020u32,
+            // Padding, then an unreferenced function
+            0x00000000,
+            0x9421FFF0,
+            0x7C0802A6,
+            0x4E800020,
+            // fn_80003114
+            0x4E800020,
+            // Gap without a prologue
+            0x38600000,
+            0x4E800020,
+            // fn_80003120
+            0x4E800020,
+        ];
+        let data = words.iter().flat_map(|w| w.to_be_bytes()).collect_vec();
+        let mut obj = test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, data)]);
+        for (address, size) in [(0x80003100, 4), (0x80003114, 4), (0x80003120, 4)] {
+            obj.known_functions.insert(SectionAddress::new(0, address), Some(size));
+        }
+
+        assert_eq!(obj.detect_gap_functions(), 1);
+        assert_eq!(obj.known_functions.get(&SectionAddress::new(0, 0x80003108)), Some(&None));
+        assert_eq!(obj.known_functions.len(), 4);
+        // The new entry has no size, so no further gaps are considered
+        assert_eq!(obj.detect_gap_functions(), 0);
+    }
+
+    #[test]
+    fn test_materialize_known_functions() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x40])]);
+        obj.add_symbol(
+            ObjSymbol {
+                name: "existing".to_string(),
+                address: 0x80003100,
+                section: Some(0),
+                size: 0x20,
+                size_known: true,
+                kind: ObjSymbolKind::Function,
+                ..Default::default()
+            },
+            false,
+        )
+        .unwrap();
+        for (address, size) in
+            [(0x80003100, Some(0x20)), (0x80003110, None), (0x80003120, Some(0x18))]
+        {
+            obj.known_functions.insert(SectionAddress::new(0, address), size);
+        }
+        assert_eq!(obj.materialize_known_functions().unwrap(), 1);
+        assert_eq!(obj.symbols.count(), 2);
+        let (_, symbol) = obj.symbols.by_name("fn_80003120").unwrap().unwrap();
+        assert_eq!((symbol.address, symbol.size, symbol.size_known), (0x80003120, 0x18, true));
+        assert_eq!(symbol.kind, ObjSymbolKind::Function);
+        assert_eq!(obj.materialize_known_functions().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_infer_symbol_sizes() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x40])]);
+        for (name, address, size, kind) in [
+            ("fn_a", 0x80003100, 0, ObjSymbolKind::Function),
+            ("fn_b", 0x80003110, 0, ObjSymbolKind::Function),
+            ("lbl_80003118", 0x80003118, 0, ObjSymbolKind::Unknown),
+            ("fn_c", 0x80003120, 0, ObjSymbolKind::Function),
+            ("fn_d", 0x80003130, 4, ObjSymbolKind::Function),
+        ] {
+            let symbol = ObjSymbol {
+                name: name.to_string(),
+                address,
+                section: Some(0),
+                size,
+                kind,
+                ..Default::default()
+            };
+            obj.add_symbol(symbol, false).unwrap();
+        }
+        obj.infer_symbol_sizes().unwrap();
+
+        let size = |name| obj.symbols.by_name(name).unwrap().unwrap().1.size;
+        assert_eq!(size("fn_a"), 0x10);
+        assert_eq!(size("fn_b"), 0x10);
+        assert_eq!(size("lbl_80003118"), 0x8);
+        assert_eq!(size("fn_c"), 0x10);
+        assert_eq!(size("fn_d"), 4);
+    }
+
+    #[test]
+    fn test_split_string_pool() {
+        let data = b"Hello\0\0\0world!\0\0%s: %d\n\0\x12\x34\x56\x78".to_vec();
+        let mut obj =
+            test_obj(vec![test_section(".rodata", ObjSectionKind::ReadOnlyData, 0x80004000, data)]);
+        assert_eq!(obj.split_string_pool(0).unwrap(), 3);
+
+        let strings =
+            obj.symbols.for_section(0).map(|(_, s)| (s.address, s.size, s.data_kind)).collect_vec();
+        assert_eq!(strings, vec![
+            (0x80004000, 6, ObjDataKind::String),
+            (0x80004008, 7, ObjDataKind::String),
+            (0x80004010, 8, ObjDataKind::String),
+        ]);
+    }
+
+    fn test_analysis_obj(copies: usize) -> ObjInfo {
+        let mut sections = vec![];
+        for i in 0..copies as u64 {
+            let base = 0x80003000 + i * 0x1000;
+            let code = [0x48000001u32, 0x60000000].iter().flat_map(|w| w.to_be_bytes()).collect();
+            sections.push(test_section(".text", ObjSectionKind::Code, base, code));
+            let strings = b"Hello\0\0\0world!\0\0\0\0".to_vec();
+            sections.push(test_section(
+                ".rodata",
+                ObjSectionKind::ReadOnlyData,
+                base + 0x100,
+                strings,
+            ));
+            let mut floats = vec![];
+            floats.extend_from_slice(&1.0f32.to_be_bytes());
+            floats.extend_from_slice(&0.5f32.to_be_bytes());
+            floats.extend_from_slice(&3.14159f64.to_be_bytes());
+            sections.push(test_section(
+                ".sdata2",
+                ObjSectionKind::ReadOnlyData,
+                base + 0x200,
+                floats,
+            ));
+        }
+        let mut obj = test_obj(sections);
+        for (section_index, section) in obj.sections.iter_mut() {
+            if section.kind == ObjSectionKind::Code {
+                let reloc_kind = if section_index % 2 == 0 {
+                    ObjRelocKind::PpcRel24
+                } else {
+                    ObjRelocKind::PpcRel14
+                };
+                for address in [section.address as u32, section.address as u32 + 4] {
+                    section
+                        .relocations
+                        .insert(address, ObjReloc {
+                            kind: reloc_kind,
+                            target_symbol: 0,
+                            addend: 0,
+                            module: None,
+                        })
+                        .unwrap();
+                }
+            }
+        }
+        obj
+    }
+
+    #[test]
+    fn test_discover_symbols_from_relocations() {
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x100]),
+            test_section(".data", ObjSectionKind::Data, 0x80003200, vec![0; 0x100]),
+        ]);
+        let anchor = obj
+            .symbols
+            .add_direct(ObjSymbol {
+                name: "lbl_80003200".to_string(),
+                address: 0x80003200,
+                section: Some(1),
+                size: 0x10,
+                size_known: true,
+                kind: ObjSymbolKind::Object,
+                ..Default::default()
+            })
+            .unwrap();
+        obj.blocked_relocation_targets
+            .insert(SectionAddress::new(1, 0x800032C0), SectionAddress::new(1, 0x800032D0));
+        for (address, kind, addend) in [
+            // Cluster at 0x80003240
+            (0x80003100, ObjRelocKind::PpcAddr16Ha, 0x40),
+            (0x80003104, ObjRelocKind::PpcAddr16Lo, 0x40),
+            (0x80003108, ObjRelocKind::PpcAddr16Lo, 0x48),
+            // Single reference
+            (0x8000310C, ObjRelocKind::PpcAddr16Lo, 0x80),
+            // Single ha/l pair
+            (0x80003118, ObjRelocKind::PpcAddr16Ha, 0xA0),
+            (0x8000311C, ObjRelocKind::PpcAddr16Lo, 0xA0),
+            // Blocked target
+            (0x80003110, ObjRelocKind::PpcAddr16Ha, 0xC0),
+            (0x80003114, ObjRelocKind::PpcAddr16Lo, 0xC0),
+        ] {
+            obj.sections[0]
+                .relocations
+                .insert(address, ObjReloc { kind, target_symbol: anchor, addend, module: None })
+                .unwrap();
+        }
+
+        assert_eq!(obj.discover_symbols_from_relocations().unwrap(), 1);
+        let (_, symbol) = obj.symbols.by_name("lbl_80003240").unwrap().unwrap();
+        assert_eq!((symbol.section, symbol.kind), (Some(1), ObjSymbolKind::Object));
+        assert_eq!(obj.symbols.count(), 2);
+        assert_eq!(obj.discover_symbols_from_relocations().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x20])]);
+        obj.add_split(0, 0x80003100, test_split("a.cpp", 0x80003110)).unwrap();
+        obj.add_split(0, 0x80003110, test_split("b.cpp", 0x80003120)).unwrap();
+        obj.symbols
+            .add_direct(ObjSymbol {
+                name: "fn_80003100".to_string(),
+                address: 0x80003100,
+                section: Some(0),
+                size: 0x10,
+                size_known: true,
+                kind: ObjSymbolKind::Function,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(obj.validate(ValidationLevel::Full).is_empty());
+
+        // Relocation against a symbol that doesn't exist
+        obj.sections[0]
+            .relocations
+            .insert(0x80003108, ObjReloc {
+                kind: ObjRelocKind::Absolute,
+                target_symbol: 5,
+                addend: 0,
+                module: None,
+            })
+            .unwrap();
+        let diagnostics = obj.validate(ValidationLevel::Basic);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].category, DiagnosticCategory::Relocation);
+        assert_eq!((diagnostics[0].section, diagnostics[0].address), (Some(0), Some(0x80003108)));
+        assert!(diagnostics[0].to_string().starts_with("error [Relocation]: .text 0x80003108"));
+    }
+
+    #[test]
+    fn test_validate_open_ended_split() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x20])]);
+        obj.add_split(0, 0x80003100, test_split("a.cpp", 0x80003110)).unwrap();
+        obj.add_split(0, 0x80003110, test_split("b.cpp", 0)).unwrap();
+        assert!(obj.validate(ValidationLevel::Full).is_empty());
+
+        // b.cpp runs to the end of the section
+        obj.sections[0].splits.push(0x80003118, test_split("c.cpp", 0x80003120));
+        let diagnostics = obj.validate(ValidationLevel::Basic);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].address, Some(0x80003118));
+    }
+
+    #[test]
+    fn test_analyze_sections() {
+        let mut obj = test_analysis_obj(4);
+        let analysis = obj.analyze_sections().unwrap();
+        // 2 strings and 3 floats per copy
+        assert_eq!(analysis.symbols_added, 20);
+        // Relocations on each `nop`, and on each `bl` labeled `rel14`
+        assert_eq!(analysis.mismatches.len(), 6);
+        assert_eq!(obj.symbols[0].name, "@stringPool1_0");
+    }
+
+    #[test]
+    #[cfg(feature = "parallel-analysis")]
+    fn test_analyze_sections_parallel() {
+        let mut sequential = test_analysis_obj(4);
+        let mut parallel = test_analysis_obj(4);
+        let analysis = sequential.analyze_sections().unwrap();
+        assert_eq!(analysis, parallel.analyze_sections_parallel().unwrap());
+
+        let symbols = |obj: &ObjInfo| {
+            obj.symbols
+                .iter()
+                .map(|s| (s.name.clone(), s.section, s.address, s.size, s.data_kind))
+                .collect_vec()
+        };
+        assert_eq!(symbols(&sequential), symbols(&parallel));
+    }
+
+    #[test]
+    #[ignore = "benchmark"]
+    #[cfg(feature = "parallel-analysis")]
+    fn bench_analyze_sections_parallel() {
+        let time = |analyze: fn(&mut ObjInfo) -> Result<SectionAnalysis>| {
+            let mut obj = test_analysis_obj(0x1000);
+            let start = std::time::Instant::now();
+            let analysis = analyze(&mut obj).unwrap();
+            (start.elapsed(), analysis)
+        };
+        let (sequential_time, sequential) = time(ObjInfo::analyze_sections);
+        let (parallel_time, parallel) = time(ObjInfo::analyze_sections_parallel);
+        assert_eq!(sequential, parallel);
+        println!(
+            "analyze_sections: {:?}, analyze_sections_parallel: {:?} ({} threads)",
+            sequential_time,
+            parallel_time,
+            rayon::current_num_threads()
+        );
+    }
+
+    #[test]
+    fn test_split_float_pool() {
+        let mut data = vec![];
+        data.extend_from_slice(&1.0f32.to_be_bytes());
+        data.extend_from_slice(&0.5f32.to_be_bytes());
+        data.extend_from_slice(&3.14159f64.to_be_bytes());
+        data.extend_from_slice(&1.0f32.to_be_bytes());
+        let pool = || {
+            test_obj(vec![test_section(
+                ".sdata2",
+                ObjSectionKind::ReadOnlyData,
+                0x80005000,
+                data.clone(),
+            )])
+        };
+
+        let mut obj = pool();
+        assert_eq!(obj.split_float_pool(0, false).unwrap(), 4);
+        let constants =
+            obj.symbols.for_section(0).map(|(_, s)| (s.address, s.size, s.data_kind)).collect_vec();
+        assert_eq!(constants, vec![
+            (0x80005000, 4, ObjDataKind::Float),
+            (0x80005004, 4, ObjDataKind::Float),
+            (0x80005008, 8, ObjDataKind::Double),
+            (0x80005010, 4, ObjDataKind::Float),
+        ]);
+
+        let mut obj = pool();
+        assert_eq!(obj.split_float_pool(0, true).unwrap(), 3);
+        assert!(obj.symbols.at_section_address(0, 0x80005010).next().is_none());
+    }
+}
@@ -0,0 +1,159 @@
+use object::elf;
+
+use crate::{
+    obj::{ObjReloc, ObjRelocKind},
+    util::reader::Endian,
+};
+
+/// Target-specific behavior for an [ObjArchitecture](crate::obj::ObjArchitecture).
+pub trait Architecture {
+    fn endian(&self) -> Endian;
+
+    /// Instruction width in bytes.
+    fn instruction_size(&self) -> usize;
+
+    /// Relocation kinds supported by this architecture.
+    fn reloc_kinds(&self) -> &'static [ObjRelocKind];
+
+    /// Calculates the ELF r_offset and r_type for a relocation.
+    fn reloc_to_elf(&self, reloc: &ObjReloc, addr: u32) -> (u64, u32);
+
+    /// Mask of the bits written by a relocation kind,
+    /// within the 4-byte word at the relocation address.
+    fn reloc_field_mask(&self, kind: ObjRelocKind) -> u32;
+
+    /// Whether the word at a relocation site has an encoding compatible with the relocation kind.
+    fn reloc_matches_instruction(&self, kind: ObjRelocKind, word: u32) -> bool;
+
+    /// Required alignment of a relocation site within a data section.
+    fn reloc_alignment(&self, kind: ObjRelocKind) -> u32;
+
+    /// Reads a 32-bit word in the architecture's byte order.
+    fn read_u32(&self, bytes: [u8; 4]) -> u32 {
+        match self.endian() {
+            Endian::Big => u32::from_be_bytes(bytes),
+            Endian::Little => u32::from_le_bytes(bytes),
+        }
+    }
+
+    /// Writes a 32-bit word in the architecture's byte order.
+    fn write_u32(&self, value: u32) -> [u8; 4] {
+        match self.endian() {
+            Endian::Big => value.to_be_bytes(),
+            Endian::Little => value.to_le_bytes(),
+        }
+    }
+}
+
+pub struct PowerPc;
+
+impl Architecture for PowerPc {
+    fn endian(&self) -> Endian { Endian::Big }
+
+    fn instruction_size(&self) -> usize { 4 }
+
+    fn reloc_kinds(&self) -> &'static [ObjRelocKind] {
+        &[
+            ObjRelocKind::Absolute,
+            ObjRelocKind::PpcAddr16Hi,
+            ObjRelocKind::PpcAddr16Ha,
+            ObjRelocKind::PpcAddr16Lo,
+            ObjRelocKind::PpcRel24,
+            ObjRelocKind::PpcRel14,
+            ObjRelocKind::PpcEmbSda21,
+        ]
+    }
+
+    fn reloc_to_elf(&self, reloc: &ObjReloc, addr: u32) -> (u64, u32) {
+        let mut r_offset = addr as u64;
+        let r_type = match reloc.kind {
+            ObjRelocKind::Absolute => {
+                if r_offset & 3 == 0 {
+                    elf::R_PPC_ADDR32
+                } else {
+                    elf::R_PPC_UADDR32
+                }
+            }
+            ObjRelocKind::PpcAddr16Hi => {
+                r_offset = (r_offset & !3) + 2;
+                elf::R_PPC_ADDR16_HI
+            }
+            ObjRelocKind::PpcAddr16Ha => {
+                r_offset = (r_offset & !3) + 2;
+                elf::R_PPC_ADDR16_HA
+            }
+            ObjRelocKind::PpcAddr16Lo => {
+                r_offset = (r_offset & !3) + 2;
+                elf::R_PPC_ADDR16_LO
+            }
+            ObjRelocKind::PpcRel24 => {
+                r_offset &= !3;
+                elf::R_PPC_REL24
+            }
+            ObjRelocKind::PpcRel14 => {
+                r_offset &= !3;
+                elf::R_PPC_REL14
+            }
+            ObjRelocKind::PpcEmbSda21 => {
+                r_offset &= !3;
+                elf::R_PPC_EMB_SDA21
+            }
+        };
+        (r_offset, r_type)
+    }
+
+    fn reloc_field_mask(&self, kind: ObjRelocKind) -> u32 {
+        match kind {
+            ObjRelocKind::Absolute => 0xFFFFFFFF,
+            ObjRelocKind::PpcAddr16Hi | ObjRelocKind::PpcAddr16Ha | ObjRelocKind::PpcAddr16Lo => {
+                0x0000FFFF
+            }
+            ObjRelocKind::PpcRel24 => 0x03FFFFFC,
+            ObjRelocKind::PpcRel14 => 0x0000FFFC,
+            // rA and 16-bit displacement
+            ObjRelocKind::PpcEmbSda21 => 0x001FFFFF,
+        }
+    }
+
+    fn reloc_matches_instruction(&self, kind: ObjRelocKind, word: u32) -> bool {
+        let opcode = word >> 26;
+        match kind {
+            ObjRelocKind::Absolute => true,
+            // lis / addis
+            ObjRelocKind::PpcAddr16Hi | ObjRelocKind::PpcAddr16Ha => opcode == 15,
+            // addi, ori, integer & float loads/stores
+            ObjRelocKind::PpcAddr16Lo => matches!(opcode, 14 | 24 | 32..=55),
+            // b, bl
+            ObjRelocKind::PpcRel24 => opcode == 18,
+            // bc
+            ObjRelocKind::PpcRel14 => opcode == 16,
+            // addi, integer & float loads/stores
+            ObjRelocKind::PpcEmbSda21 => matches!(opcode, 14 | 32..=55),
+        }
+    }
+
+    fn reloc_alignment(&self, kind: ObjRelocKind) -> u32 {
+        match kind {
+            // Emitted as R_PPC_UADDR32 when unaligned
+            ObjRelocKind::Absolute => 1,
+            // Instruction fields
+            _ => 4,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_powerpc() {
+        let arch = PowerPc;
+        assert_eq!(arch.instruction_size(), 4);
+        assert_eq!(arch.endian(), Endian::Big);
+        assert_eq!(arch.read_u32([0x48, 0x00, 0x00, 0x01]), 0x48000001);
+        assert_eq!(arch.reloc_field_mask(ObjRelocKind::PpcRel24), 0x03FFFFFC);
+        assert!(arch.reloc_matches_instruction(ObjRelocKind::PpcRel24, 0x48000001));
+        assert!(!arch.reloc_matches_instruction(ObjRelocKind::PpcAddr16Ha, 0x48000001));
+    }
+}
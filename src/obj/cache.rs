@@ -0,0 +1,627 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use flagset::FlagSet;
+use objdiff_core::obj::split_meta::SplitMeta;
+
+use crate::{
+    analysis::cfa::SectionAddress,
+    obj::{
+        AddressFormat, AutoUnitNaming, ObjArchitecture, ObjDataKind, ObjInfo, ObjKind, ObjReloc,
+        ObjRelocKind, ObjSection, ObjSectionKind, ObjSections, ObjSplit, ObjSymbol,
+        ObjSymbolFlagSet, ObjSymbolKind, ObjSymbols, ObjUnit,
+    },
+    util::{
+        comment::MWComment,
+        reader::{read_bytes, Endian, FromReader, ToWriter},
+        rel::RelReloc,
+    },
+};
+
+pub const CACHE_MAGIC: [u8; 4] = *b"DTKC";
+/// Bump whenever the layout below changes.
+pub const CACHE_VERSION: u32 = 3;
+
+const E: Endian = Endian::Big;
+
+const OBJ_KINDS: &[ObjKind] = &[ObjKind::Executable, ObjKind::Relocatable];
+const ARCHITECTURES: &[ObjArchitecture] = &[ObjArchitecture::PowerPc];
+const ADDRESS_FORMATS: &[AddressFormat] =
+    &[AddressFormat::Absolute, AddressFormat::SectionRelative];
+const AUTO_UNIT_NAMINGS: &[AutoUnitNaming] = &[AutoUnitNaming::Symbol, AutoUnitNaming::Address];
+const SECTION_KINDS: &[ObjSectionKind] = &[
+    ObjSectionKind::Code,
+    ObjSectionKind::Data,
+    ObjSectionKind::ReadOnlyData,
+    ObjSectionKind::Bss,
+];
+const SYMBOL_KINDS: &[ObjSymbolKind] = &[
+    ObjSymbolKind::Unknown,
+    ObjSymbolKind::Function,
+    ObjSymbolKind::Object,
+    ObjSymbolKind::Section,
+];
+const DATA_KINDS: &[ObjDataKind] = &[
+    ObjDataKind::Unknown,
+    ObjDataKind::Byte,
+    ObjDataKind::Byte2,
+    ObjDataKind::Byte4,
+    ObjDataKind::Byte8,
+    ObjDataKind::Float,
+    ObjDataKind::Double,
+    ObjDataKind::String,
+    ObjDataKind::String16,
+    ObjDataKind::StringTable,
+    ObjDataKind::String16Table,
+    ObjDataKind::Int,
+    ObjDataKind::Short,
+];
+
+/// Serializes the full analyzed state of an [ObjInfo].
+pub fn write_cache<W>(obj: &ObjInfo, w: &mut W) -> Result<()>
+where W: Write + ?Sized {
+    let reloc_kinds = obj.architecture.arch().reloc_kinds();
+    CACHE_MAGIC.to_writer(w, E)?;
+    CACHE_VERSION.to_writer(w, E)?;
+    write_enum(w, OBJ_KINDS, obj.kind)?;
+    write_enum(w, ARCHITECTURES, obj.architecture)?;
+    write_str(w, &obj.name)?;
+    write_opt(w, obj.entry.as_ref(), |w, v| Ok(v.to_writer(w, E)?))?;
+    write_opt(w, obj.mw_comment.as_ref(), |w, v| Ok(v.to_writer(w, E)?))?;
+    write_opt(w, obj.split_meta.as_ref(), |w, v| {
+        write_opt(w, v.generator.as_deref(), write_str)?;
+        write_opt(w, v.module_name.as_deref(), write_str)?;
+        write_opt(w, v.module_id.as_ref(), |w, v| Ok(v.to_writer(w, E)?))?;
+        write_opt(w, v.virtual_addresses.as_ref(), |w, v| {
+            write_len(w, v.len())?;
+            for address in v {
+                address.to_writer(w, E)?;
+            }
+            Ok(())
+        })
+    })?;
+    for value in [
+        obj.sda2_base,
+        obj.sda_base,
+        obj.stack_address,
+        obj.stack_end,
+        obj.db_stack_addr,
+        obj.arena_lo,
+        obj.arena_hi,
+    ] {
+        write_opt(w, value.as_ref(), |w, v| Ok(v.to_writer(w, E)?))?;
+    }
+
+    write_len(w, obj.sections.len())?;
+    for (_, section) in obj.sections.iter() {
+        write_section(w, section, reloc_kinds)?;
+    }
+    write_len(w, obj.symbols.count())?;
+    for symbol in obj.symbols.iter() {
+        write_symbol(w, symbol)?;
+    }
+
+    write_len(w, obj.link_order.len())?;
+    for unit in &obj.link_order {
+        write_str(w, &unit.name)?;
+        write_bool(w, unit.autogenerated)?;
+        write_opt(w, unit.comment_version.as_ref(), |w, v| Ok(v.to_writer(w, E)?))?;
+    }
+    for ranges in [&obj.blocked_relocation_sources, &obj.blocked_relocation_targets] {
+        let ranges = ranges.iter().collect::<Vec<_>>();
+        write_len(w, ranges.len())?;
+        for (start, end) in ranges {
+            write_section_address(w, start)?;
+            end.to_writer(w, E)?;
+        }
+    }
+    write_len(w, obj.known_functions.len())?;
+    for (&address, size) in &obj.known_functions {
+        write_section_address(w, address)?;
+        write_opt(w, size.as_ref(), |w, v| Ok(v.to_writer(w, E)?))?;
+    }
+    obj.module_id.to_writer(w, E)?;
+    write_len(w, obj.unresolved_relocations.len())?;
+    for reloc in &obj.unresolved_relocations {
+        write_enum(w, reloc_kinds, reloc.kind)?;
+        reloc.section.to_writer(w, E)?;
+        reloc.address.to_writer(w, E)?;
+        reloc.module_id.to_writer(w, E)?;
+        reloc.target_section.to_writer(w, E)?;
+        reloc.addend.to_writer(w, E)?;
+        reloc.original_section.to_writer(w, E)?;
+        reloc.original_target_section.to_writer(w, E)?;
+    }
+    write_enum(w, ADDRESS_FORMATS, obj.address_format)?;
+    write_enum(w, AUTO_UNIT_NAMINGS, obj.auto_unit_naming)?;
+    Ok(())
+}
+
+/// Deserializes an [ObjInfo] written by [write_cache].
+/// Caches written by a different format version are rejected.
+pub fn read_cache<R>(r: &mut R) -> Result<ObjInfo>
+where R: Read + Seek + ?Sized {
+    let magic = <[u8; 4]>::from_reader(r, E)?;
+    ensure!(magic == CACHE_MAGIC, "Invalid cache magic: {:?}", magic);
+    let version = u32::from_reader(r, E)?;
+    ensure!(
+        version == CACHE_VERSION,
+        "Unsupported cache version {} (expected {})",
+        version,
+        CACHE_VERSION
+    );
+    let kind = read_enum(r, OBJ_KINDS)?;
+    let architecture = read_enum(r, ARCHITECTURES)?;
+    let reloc_kinds = architecture.arch().reloc_kinds();
+    let name = read_str(r)?;
+    let mut obj = ObjInfo::new(kind, architecture, name, vec![], vec![]);
+    obj.entry = read_opt(r, |r| Ok(u64::from_reader(r, E)?))?;
+    obj.mw_comment = read_opt(r, |r| Ok(MWComment::from_reader(r, E)?))?;
+    obj.split_meta = read_opt(r, |r| {
+        Ok(SplitMeta {
+            generator: read_opt(r, read_str)?,
+            module_name: read_opt(r, read_str)?,
+            module_id: read_opt(r, |r| Ok(u32::from_reader(r, E)?))?,
+            virtual_addresses: read_opt(r, |r| {
+                let len = read_len(r)?;
+                (0..len).map(|_| Ok(u64::from_reader(r, E)?)).collect::<Result<Vec<_>>>()
+            })?,
+        })
+    })?;
+    for value in [
+        &mut obj.sda2_base,
+        &mut obj.sda_base,
+        &mut obj.stack_address,
+        &mut obj.stack_end,
+        &mut obj.db_stack_addr,
+        &mut obj.arena_lo,
+        &mut obj.arena_hi,
+    ] {
+        *value = read_opt(r, |r| Ok(u32::from_reader(r, E)?))?;
+    }
+
+    let len = read_len(r)?;
+    let mut sections = Vec::with_capacity(len);
+    let mut relocations = vec![];
+    for section_index in 0..len {
+        let (section, section_relocations) = read_section(r, reloc_kinds)?;
+        sections.push(section);
+        relocations.extend(
+            section_relocations.into_iter().map(|(address, reloc)| (section_index, address, reloc)),
+        );
+    }
+    obj.sections = ObjSections::new(kind, sections);
+    let len = read_len(r)?;
+    let symbols = (0..len).map(|_| read_symbol(r)).collect::<Result<Vec<_>>>()?;
+    obj.symbols = ObjSymbols::new(kind, symbols);
+
+    let len = read_len(r)?;
+    for _ in 0..len {
+        obj.link_order.push(ObjUnit {
+            name: read_str(r)?,
+            autogenerated: read_bool(r)?,
+            comment_version: read_opt(r, |r| Ok(u8::from_reader(r, E)?))?,
+        });
+    }
+    for ranges in [&mut obj.blocked_relocation_sources, &mut obj.blocked_relocation_targets] {
+        let len = read_len(r)?;
+        for _ in 0..len {
+            let start = read_section_address(r)?;
+            let end = u32::from_reader(r, E)?;
+            ranges.insert(start, SectionAddress::new(start.section, end));
+        }
+    }
+    let len = read_len(r)?;
+    for _ in 0..len {
+        let address = read_section_address(r)?;
+        let size = read_opt(r, |r| Ok(u32::from_reader(r, E)?))?;
+        obj.known_functions.insert(address, size);
+    }
+    obj.module_id = u32::from_reader(r, E)?;
+    for (section_index, address, reloc) in relocations {
+        obj.insert_relocation(section_index, address, reloc)?;
+    }
+    let len = read_len(r)?;
+    for _ in 0..len {
+        obj.unresolved_relocations.push(RelReloc {
+            kind: read_enum(r, reloc_kinds)?,
+            section: u8::from_reader(r, E)?,
+            address: u32::from_reader(r, E)?,
+            module_id: u32::from_reader(r, E)?,
+            target_section: u8::from_reader(r, E)?,
+            addend: u32::from_reader(r, E)?,
+            original_section: u8::from_reader(r, E)?,
+            original_target_section: u8::from_reader(r, E)?,
+        });
+    }
+    obj.address_format = read_enum(r, ADDRESS_FORMATS)?;
+    obj.auto_unit_naming = read_enum(r, AUTO_UNIT_NAMINGS)?;
+    Ok(obj)
+}
+
+fn write_section<W>(w: &mut W, section: &ObjSection, reloc_kinds: &[ObjRelocKind]) -> Result<()>
+where W: Write + ?Sized {
+    write_str(w, &section.name)?;
+    write_enum(w, SECTION_KINDS, section.kind)?;
+    section.address.to_writer(w, E)?;
+    section.size.to_writer(w, E)?;
+    write_len(w, section.data.len())?;
+    section.data.to_writer(w, E)?;
+    section.align.to_writer(w, E)?;
+    (section.elf_index as u64).to_writer(w, E)?;
+    write_len(w, section.relocations.len())?;
+    for (address, reloc) in section.relocations.iter() {
+        address.to_writer(w, E)?;
+        write_enum(w, reloc_kinds, reloc.kind)?;
+        (reloc.target_symbol as u64).to_writer(w, E)?;
+        reloc.addend.to_writer(w, E)?;
+        write_opt(w, reloc.module.as_ref(), |w, v| Ok(v.to_writer(w, E)?))?;
+    }
+    write_opt(w, section.virtual_address.as_ref(), |w, v| Ok(v.to_writer(w, E)?))?;
+    section.file_offset.to_writer(w, E)?;
+    write_bool(w, section.section_known)?;
+    let splits = section.splits.iter().collect::<Vec<_>>();
+    write_len(w, splits.len())?;
+    for (address, split) in splits {
+        address.to_writer(w, E)?;
+        write_str(w, &split.unit)?;
+        split.end.to_writer(w, E)?;
+        write_opt(w, split.align.as_ref(), |w, v| Ok(v.to_writer(w, E)?))?;
+        write_bool(w, split.common)?;
+        write_bool(w, split.autogenerated)?;
+        write_bool(w, split.skip)?;
+        write_opt(w, split.rename.as_deref(), write_str)?;
+    }
+    Ok(())
+}
+
+/// Reads a section, returning its relocations separately so they can be inserted once the
+/// module ID is known.
+fn read_section<R>(
+    r: &mut R,
+    reloc_kinds: &[ObjRelocKind],
+) -> Result<(ObjSection, Vec<(u32, ObjReloc)>)>
+where
+    R: Read + Seek + ?Sized,
+{
+    let name = read_str(r)?;
+    let kind = read_enum(r, SECTION_KINDS)?;
+    let address = u64::from_reader(r, E)?;
+    let size = u64::from_reader(r, E)?;
+    let len = read_len(r)?;
+    let data = read_bytes(r, len)?;
+    let align = u64::from_reader(r, E)?;
+    let elf_index = u64::from_reader(r, E)? as usize;
+    let mut section = ObjSection {
+        name,
+        kind,
+        address,
+        size,
+        data,
+        align,
+        elf_index,
+        relocations: Default::default(),
+        virtual_address: None,
+        file_offset: 0,
+        section_known: false,
+        splits: Default::default(),
+        compressed_size_cache: Default::default(),
+    };
+    let len = read_len(r)?;
+    let mut relocations = Vec::with_capacity(len);
+    for _ in 0..len {
+        let address = u32::from_reader(r, E)?;
+        let reloc = ObjReloc {
+            kind: read_enum(r, reloc_kinds)?,
+            target_symbol: u64::from_reader(r, E)? as usize,
+            addend: i64::from_reader(r, E)?,
+            module: read_opt(r, |r| Ok(u32::from_reader(r, E)?))?,
+        };
+        relocations.push((address, reloc));
+    }
+    section.virtual_address = read_opt(r, |r| Ok(u64::from_reader(r, E)?))?;
+    section.file_offset = u64::from_reader(r, E)?;
+    section.section_known = read_bool(r)?;
+    let len = read_len(r)?;
+    for _ in 0..len {
+        let address = u32::from_reader(r, E)?;
+        let split = ObjSplit {
+            unit: read_str(r)?,
+            end: u32::from_reader(r, E)?,
+            align: read_opt(r, |r| Ok(u32::from_reader(r, E)?))?,
+            common: read_bool(r)?,
+            autogenerated: read_bool(r)?,
+            skip: read_bool(r)?,
+            rename: read_opt(r, read_str)?,
+        };
+        section.splits.push(address, split);
+    }
+    Ok((section, relocations))
+}
+
+fn write_symbol<W>(w: &mut W, symbol: &ObjSymbol) -> Result<()>
+where W: Write + ?Sized {
+    write_str(w, &symbol.name)?;
+    write_opt(w, symbol.demangled_name.as_deref(), write_str)?;
+    symbol.address.to_writer(w, E)?;
+    write_opt(w, symbol.section.map(|v| v as u64).as_ref(), |w, v| Ok(v.to_writer(w, E)?))?;
+    symbol.size.to_writer(w, E)?;
+    write_bool(w, symbol.size_known)?;
+    symbol.flags.0.bits().to_writer(w, E)?;
+    write_enum(w, SYMBOL_KINDS, symbol.kind)?;
+    write_opt(w, symbol.align.as_ref(), |w, v| Ok(v.to_writer(w, E)?))?;
+    write_enum(w, DATA_KINDS, symbol.data_kind)?;
+    write_opt(w, symbol.name_hash.as_ref(), |w, v| Ok(v.to_writer(w, E)?))?;
+    write_opt(w, symbol.demangled_name_hash.as_ref(), |w, v| Ok(v.to_writer(w, E)?))?;
+    Ok(())
+}
+
+fn read_symbol<R>(r: &mut R) -> Result<ObjSymbol>
+where R: Read + Seek + ?Sized {
+    Ok(ObjSymbol {
+        name: read_str(r)?,
+        demangled_name: read_opt(r, read_str)?,
+        address: u64::from_reader(r, E)?,
+        section: read_opt(r, |r| Ok(u64::from_reader(r, E)? as usize))?,
+        size: u64::from_reader(r, E)?,
+        size_known: read_bool(r)?,
+        flags: {
+            let bits = u32::from_reader(r, E)?;
+            ObjSymbolFlagSet(
+                FlagSet::new(bits).map_err(|_| anyhow!("Invalid symbol flags {:#X}", bits))?,
+            )
+        },
+        kind: read_enum(r, SYMBOL_KINDS)?,
+        align: read_opt(r, |r| Ok(u32::from_reader(r, E)?))?,
+        data_kind: read_enum(r, DATA_KINDS)?,
+        name_hash: read_opt(r, |r| Ok(u32::from_reader(r, E)?))?,
+        demangled_name_hash: read_opt(r, |r| Ok(u32::from_reader(r, E)?))?,
+    })
+}
+
+fn write_section_address<W>(w: &mut W, address: SectionAddress) -> Result<()>
+where W: Write + ?Sized {
+    (address.section as u32).to_writer(w, E)?;
+    address.address.to_writer(w, E)?;
+    Ok(())
+}
+
+fn read_section_address<R>(r: &mut R) -> Result<SectionAddress>
+where R: Read + Seek + ?Sized {
+    let section = u32::from_reader(r, E)? as usize;
+    Ok(SectionAddress::new(section, u32::from_reader(r, E)?))
+}
+
+fn write_enum<T, W>(w: &mut W, table: &[T], value: T) -> Result<()>
+where
+    T: PartialEq + std::fmt::Debug,
+    W: Write + ?Sized,
+{
+    let Some(index) = table.iter().position(|v| *v == value) else {
+        bail!("Unsupported value {:?}", value);
+    };
+    (index as u8).to_writer(w, E)?;
+    Ok(())
+}
+
+fn read_enum<T, R>(r: &mut R, table: &[T]) -> Result<T>
+where
+    T: Copy,
+    R: Read + Seek + ?Sized,
+{
+    let index = u8::from_reader(r, E)?;
+    table.get(index as usize).copied().ok_or_else(|| anyhow!("Invalid enum value {}", index))
+}
+
+fn write_opt<T, W, F>(w: &mut W, value: Option<T>, f: F) -> Result<()>
+where
+    W: Write + ?Sized,
+    F: FnOnce(&mut W, T) -> Result<()>,
+{
+    write_bool(w, value.is_some())?;
+    match value {
+        Some(value) => f(w, value),
+        None => Ok(()),
+    }
+}
+
+fn read_opt<T, R, F>(r: &mut R, f: F) -> Result<Option<T>>
+where
+    R: Read + Seek + ?Sized,
+    F: FnOnce(&mut R) -> Result<T>,
+{
+    if read_bool(r)? {
+        Ok(Some(f(r)?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn write_bool<W>(w: &mut W, value: bool) -> Result<()>
+where W: Write + ?Sized {
+    (value as u8).to_writer(w, E)?;
+    Ok(())
+}
+
+fn read_bool<R>(r: &mut R) -> Result<bool>
+where R: Read + Seek + ?Sized {
+    match u8::from_reader(r, E)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        v => bail!("Invalid bool value {}", v),
+    }
+}
+
+fn write_len<W>(w: &mut W, len: usize) -> Result<()>
+where W: Write + ?Sized {
+    u32::try_from(len)?.to_writer(w, E)?;
+    Ok(())
+}
+
+/// Every element takes at least one byte, so lengths beyond the end of the stream are
+/// rejected before anything is allocated for them.
+fn read_len<R>(r: &mut R) -> Result<usize>
+where R: Read + Seek + ?Sized {
+    let len = u32::from_reader(r, E)?;
+    let position = r.stream_position()?;
+    let end = r.seek(SeekFrom::End(0))?;
+    r.seek(SeekFrom::Start(position))?;
+    ensure!(
+        len as u64 <= end.saturating_sub(position),
+        "Invalid length {:#X} at offset {:#X}",
+        len,
+        position
+    );
+    Ok(len as usize)
+}
+
+fn write_str<W>(w: &mut W, value: &str) -> Result<()>
+where W: Write + ?Sized {
+    write_len(w, value.len())?;
+    w.write_all(value.as_bytes())?;
+    Ok(())
+}
+
+fn read_str<R>(r: &mut R) -> Result<String>
+where R: Read + Seek + ?Sized {
+    let len = read_len(r)?;
+    Ok(String::from_utf8(read_bytes(r, len)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::obj::{test_section, ObjSymbolFlags};
+
+    fn test_obj() -> ObjInfo {
+        let mut obj = ObjInfo::new(
+            ObjKind::Executable,
+            ObjArchitecture::PowerPc,
+            "main".to_string(),
+            vec![],
+            vec![ObjSection {
+                align: 32,
+                elf_index: 1,
+                virtual_address: Some(0x80003100),
+                file_offset: 0x100,
+                ..test_section(".text", ObjSectionKind::Code, 0x80003100, vec![
+                    0x48, 0, 0, 0x5, 0x4E, 0x80, 0, 0x20, 0x4E, 0x80, 0, 0x20, 0, 0, 0, 0,
+                ])
+            }],
+        );
+        let symbol = obj
+            .add_symbol(
+                ObjSymbol {
+                    name: "fn_80003104".to_string(),
+                    demangled_name: Some("foo()".to_string()),
+                    address: 0x80003104,
+                    section: Some(0),
+                    size: 4,
+                    size_known: true,
+                    flags: ObjSymbolFlagSet(ObjSymbolFlags::Global | ObjSymbolFlags::NoReloc),
+                    kind: ObjSymbolKind::Function,
+                    align: Some(4),
+                    data_kind: ObjDataKind::Unknown,
+                    name_hash: Some(0x1234),
+                    demangled_name_hash: None,
+                },
+                false,
+            )
+            .unwrap();
+        obj.sections[0]
+            .relocations
+            .insert(0x80003100, ObjReloc {
+                kind: ObjRelocKind::PpcRel24,
+                target_symbol: symbol,
+                addend: -4,
+                module: Some(0),
+            })
+            .unwrap();
+        obj.sections[0].splits.push(0x80003100, ObjSplit {
+            unit: "main.c".to_string(),
+            end: 0x80003110,
+            align: Some(16),
+            common: false,
+            autogenerated: true,
+            skip: false,
+            rename: Some(".text$a".to_string()),
+        });
+        obj.entry = Some(0x80003100);
+        obj.mw_comment = Some(MWComment::new(8).unwrap());
+        obj.split_meta = Some(SplitMeta {
+            generator: Some("dtk".to_string()),
+            module_name: None,
+            module_id: Some(0),
+            virtual_addresses: Some(vec![0x80003100, 0x80003104]),
+        });
+        obj.sda_base = Some(0x80400000);
+        obj.arena_hi = Some(0x81700000);
+        obj.link_order.push(ObjUnit {
+            name: "main.c".to_string(),
+            autogenerated: false,
+            comment_version: Some(8),
+        });
+        obj.blocked_relocation_sources
+            .insert(SectionAddress::new(0, 0x80003100), SectionAddress::new(0, 0x80003108));
+        obj.known_functions.insert(SectionAddress::new(0, 0x80003104), Some(4));
+        obj.module_id = 1;
+        obj.unresolved_relocations.push(RelReloc {
+            kind: ObjRelocKind::Absolute,
+            section: 1,
+            address: 0x10,
+            module_id: 0,
+            target_section: 5,
+            addend: 0x80003104,
+            original_section: 1,
+            original_target_section: 5,
+        });
+        obj.address_format = AddressFormat::SectionRelative;
+        obj.auto_unit_naming = AutoUnitNaming::Address;
+        obj
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let obj = test_obj();
+        let mut buf = vec![];
+        write_cache(&obj, &mut buf).unwrap();
+        let read = read_cache(&mut Cursor::new(buf.as_slice())).unwrap();
+        // ObjSymbols contains a HashMap, so its Debug output isn't stable
+        assert_eq!(read.symbols.iter().collect::<Vec<_>>(), obj.symbols.iter().collect::<Vec<_>>());
+        let fields = |obj: &ObjInfo| {
+            format!(
+                "{:?}",
+                (
+                    (obj.kind, obj.architecture, &obj.name, obj.entry, &obj.mw_comment),
+                    (&obj.split_meta, obj.sda2_base, obj.sda_base, obj.stack_address),
+                    (obj.stack_end, obj.db_stack_addr, obj.arena_lo, obj.arena_hi),
+                    (&obj.sections, &obj.link_order, &obj.blocked_relocation_sources),
+                    (&obj.blocked_relocation_targets, &obj.known_functions, obj.module_id),
+                    (&obj.unresolved_relocations, obj.address_format, obj.auto_unit_naming),
+                )
+            )
+        };
+        assert_eq!(fields(&read), fields(&obj));
+    }
+
+    #[test]
+    fn test_version_mismatch() {
+        let mut buf = vec![];
+        write_cache(&test_obj(), &mut buf).unwrap();
+        buf[4..8].copy_from_slice(&(CACHE_VERSION + 1).to_be_bytes());
+        let err = read_cache(&mut Cursor::new(buf.as_slice())).unwrap_err();
+        assert!(err.to_string().contains("Unsupported cache version"));
+    }
+
+    #[test]
+    fn test_invalid_length() {
+        let mut buf = vec![];
+        write_cache(&test_obj(), &mut buf).unwrap();
+        // Name length, after the magic, version, kind and architecture
+        buf[10..14].copy_from_slice(&u32::MAX.to_be_bytes());
+        let err = read_cache(&mut Cursor::new(buf.as_slice())).unwrap_err();
+        assert!(err.to_string().contains("Invalid length"));
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    cmp::{max, min},
+    collections::BTreeMap,
+    ops::Range,
+};
+
+use crate::obj::{ObjInfo, ObjReloc, ObjSection, ObjSymbol, ObjSymbolKind};
+
+/// Differences between two builds of the same object.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ObjDiff {
+    /// Symbols only present in the other object.
+    pub added_symbols: Vec<String>,
+    /// Symbols only present in this object.
+    pub removed_symbols: Vec<String>,
+    /// Symbols present in both objects, with a different address, size, kind or data.
+    pub changed_symbols: Vec<String>,
+    /// Sections only present in the other object.
+    pub added_sections: Vec<String>,
+    /// Sections only present in this object.
+    pub removed_sections: Vec<String>,
+    /// Sections present in both objects with differing data or relocations.
+    pub sections: Vec<ObjSectionDiff>,
+}
+
+/// Differences between two sections with the same name.
+/// Addresses are relative to the section address in this object.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ObjSectionDiff {
+    pub name: String,
+    /// Address ranges with differing data, in 4-byte granularity.
+    pub changed_ranges: Vec<Range<u32>>,
+    pub added_relocations: Vec<u32>,
+    pub removed_relocations: Vec<u32>,
+    /// Relocations with a different kind, addend, module or target symbol name.
+    pub changed_relocations: Vec<u32>,
+}
+
+impl ObjDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_symbols.is_empty()
+            && self.removed_symbols.is_empty()
+            && self.changed_symbols.is_empty()
+            && self.added_sections.is_empty()
+            && self.removed_sections.is_empty()
+            && self.sections.is_empty()
+    }
+}
+
+impl ObjSectionDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changed_ranges.is_empty()
+            && self.added_relocations.is_empty()
+            && self.removed_relocations.is_empty()
+            && self.changed_relocations.is_empty()
+    }
+}
+
+pub fn diff_objs(left: &ObjInfo, right: &ObjInfo) -> ObjDiff {
+    let mut diff = ObjDiff::default();
+
+    let left_symbols = symbols_by_name(left);
+    let right_symbols = symbols_by_name(right);
+    for (&name, &left_symbol) in &left_symbols {
+        match right_symbols.get(name) {
+            Some(&right_symbol) => {
+                if symbol_changed(left, left_symbol, right, right_symbol) {
+                    diff.changed_symbols.push(name.to_string());
+                }
+            }
+            None => diff.removed_symbols.push(name.to_string()),
+        }
+    }
+    for &name in right_symbols.keys() {
+        if !left_symbols.contains_key(name) {
+            diff.added_symbols.push(name.to_string());
+        }
+    }
+
+    for (_, left_section) in left.sections.iter() {
+        match right.sections.first_by_name(&left_section.name) {
+            Some((_, right_section)) => {
+                let section_diff = diff_section(left, left_section, right, right_section);
+                if !section_diff.is_empty() {
+                    diff.sections.push(section_diff);
+                }
+            }
+            None => diff.removed_sections.push(left_section.name.clone()),
+        }
+    }
+    for (_, right_section) in right.sections.iter() {
+        if !left.sections.iter().any(|(_, s)| s.name == right_section.name) {
+            diff.added_sections.push(right_section.name.clone());
+        }
+    }
+    diff
+}
+
+/// Named symbols, keeping the first symbol for duplicate (local) names.
+fn symbols_by_name(obj: &ObjInfo) -> BTreeMap<&str, &ObjSymbol> {
+    let mut map = BTreeMap::new();
+    for symbol in obj.symbols.iter() {
+        if symbol.name.is_empty() || symbol.kind == ObjSymbolKind::Section {
+            continue;
+        }
+        map.entry(symbol.name.as_str()).or_insert(symbol);
+    }
+    map
+}
+
+fn symbol_changed(left: &ObjInfo, l: &ObjSymbol, right: &ObjInfo, r: &ObjSymbol) -> bool {
+    let left_section = l.section.and_then(|idx| left.sections.get(idx));
+    let right_section = r.section.and_then(|idx| right.sections.get(idx));
+    if l.address != r.address
+        || l.size != r.size
+        || l.kind != r.kind
+        || left_section.map(|s| &s.name) != right_section.map(|s| &s.name)
+    {
+        return true;
+    }
+    match (left_section, right_section) {
+        (Some(ls), Some(rs)) => ls.symbol_data(l).ok() != rs.symbol_data(r).ok(),
+        _ => false,
+    }
+}
+
+fn diff_section(
+    left: &ObjInfo,
+    left_section: &ObjSection,
+    right: &ObjInfo,
+    right_section: &ObjSection,
+) -> ObjSectionDiff {
+    let base = left_section.address as u32;
+    let mut diff = ObjSectionDiff { name: left_section.name.clone(), ..Default::default() };
+
+    let len = max(left_section.data.len(), right_section.data.len());
+    let mut offset = 0;
+    while offset < len {
+        let end = min(offset + 4, len);
+        if left_section.data.get(offset..end) != right_section.data.get(offset..end) {
+            let (start, end) = (base + offset as u32, base + end as u32);
+            match diff.changed_ranges.last_mut() {
+                Some(last) if last.end == start => last.end = end,
+                _ => diff.changed_ranges.push(start..end),
+            }
+        }
+        offset = end;
+    }
+
+    let left_relocs = relocations_by_offset(left_section);
+    let right_relocs = relocations_by_offset(right_section);
+    for (&offset, &left_reloc) in &left_relocs {
+        match right_relocs.get(&offset) {
+            Some(&right_reloc) => {
+                if reloc_changed(left, left_reloc, right, right_reloc) {
+                    diff.changed_relocations.push(base + offset);
+                }
+            }
+            None => diff.removed_relocations.push(base + offset),
+        }
+    }
+    for &offset in right_relocs.keys() {
+        if !left_relocs.contains_key(&offset) {
+            diff.added_relocations.push(base + offset);
+        }
+    }
+    diff
+}
+
+fn relocations_by_offset(section: &ObjSection) -> BTreeMap<u32, &ObjReloc> {
+    section
+        .relocations
+        .iter()
+        .map(|(address, reloc)| (address - section.address as u32, reloc))
+        .collect()
+}
+
+fn reloc_changed(left: &ObjInfo, l: &ObjReloc, right: &ObjInfo, r: &ObjReloc) -> bool {
+    l.kind != r.kind
+        || l.addend != r.addend
+        || l.module != r.module
+        || left.symbols[l.target_symbol].name != right.symbols[r.target_symbol].name
+}
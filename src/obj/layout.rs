@@ -0,0 +1,1335 @@
+use std::{
+    cmp::{max, min},
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Range,
+};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use itertools::Itertools;
+
+use crate::{
+    analysis::cfa::SectionAddress,
+    obj::{
+        addresses::AddressRanges, ObjInfo, ObjKind, ObjReloc, ObjRelocations, ObjSection,
+        ObjSectionKind, ObjSections, ObjSplit, ObjSplits, ObjSymbol, ObjSymbolFlags, ObjSymbolKind,
+        ObjSymbols,
+    },
+    util::{
+        align_up,
+        split::{is_linker_generated_label, is_linker_generated_object},
+    },
+};
+
+/// Bytes attributed to a unit by its splits. See [ObjInfo::unit_size_breakdown].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct UnitSizes {
+    pub code: u32,
+    /// Initialized data, including read-only data.
+    pub data: u32,
+    /// Uninitialized data, including common splits.
+    pub bss: u32,
+    /// The unit is autogenerated, rather than defined by the user.
+    pub autogenerated: bool,
+}
+
+/// How [ObjInfo::snap_split_boundaries] moves a boundary that bisects a symbol.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum SnapDirection {
+    /// Move the boundary to the end of the symbol.
+    #[default]
+    Grow,
+    /// Move the boundary to the start of the symbol.
+    Shrink,
+}
+
+/// What [ObjInfo::strip] removes.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct StripOptions {
+    /// Local symbols not referenced by any relocation.
+    pub local_symbols: bool,
+    /// `.comment` and DWARF sections.
+    pub debug_sections: bool,
+    /// Analysis metadata: `split_meta` and `known_functions`.
+    pub metadata: bool,
+}
+
+impl Default for StripOptions {
+    fn default() -> Self { Self { local_symbols: true, debug_sections: true, metadata: true } }
+}
+
+impl ObjInfo {
+    /// Moves split boundaries that fall inside a sized function or object to the symbol's
+    /// end or start (per `direction`), so no split bisects a symbol. Starts and ends are
+    /// snapped alike, keeping adjacent splits contiguous. Splits left empty are removed.
+    /// Returns the number of splits adjusted.
+    pub fn snap_split_boundaries(&mut self, direction: SnapDirection) -> Result<usize> {
+        let fmt = self.address_format;
+        let mut count = 0;
+        for section_index in 0..self.sections.len() {
+            let snap = |address: u32| {
+                let mut result = address;
+                for (_, symbol) in self.symbols.for_section_range(section_index, ..address) {
+                    if !matches!(symbol.kind, ObjSymbolKind::Function | ObjSymbolKind::Object)
+                        || symbol.size == 0
+                    {
+                        continue;
+                    }
+                    let end = (symbol.address + symbol.size) as u32;
+                    if end <= address {
+                        continue;
+                    }
+                    result = match direction {
+                        SnapDirection::Grow => max(result, end),
+                        SnapDirection::Shrink => min(result, symbol.address as u32),
+                    };
+                }
+                result
+            };
+            let section = &self.sections[section_index];
+            let mut splits = vec![];
+            let mut changed = false;
+            for (addr, split) in section.splits.iter() {
+                let start = snap(addr);
+                let end = if split.end == 0 { 0 } else { snap(split.end) };
+                if start != addr || end != split.end {
+                    log::info!(
+                        "Snapped split {} {} to {}",
+                        split.unit,
+                        fmt.range(section, addr, split.end),
+                        fmt.range(section, start, end)
+                    );
+                    changed = true;
+                    count += 1;
+                }
+                if end != 0 && start >= end {
+                    log::warn!(
+                        "Removing split {} in {}: empty after snapping",
+                        split.unit,
+                        section.name
+                    );
+                    continue;
+                }
+                splits.push((start, ObjSplit { end, ..split.clone() }));
+            }
+            if changed {
+                let section = &mut self.sections[section_index];
+                section.splits = ObjSplits::default();
+                for (addr, split) in splits {
+                    section.splits.push(addr, split);
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Reclassifies a section, e.g. data misdetected as code. Known functions in a section
+    /// that's no longer code are dropped. A section can only become bss if its data is all
+    /// zero, and a bss section has no data to become anything else. Symbols whose kind is
+    /// inconsistent with the new section kind are reported as warnings.
+    pub fn set_section_kind(&mut self, section_index: usize, kind: ObjSectionKind) -> Result<()> {
+        let section = self
+            .sections
+            .get_mut(section_index)
+            .ok_or_else(|| anyhow!("Invalid section index {}", section_index))?;
+        if section.kind == kind {
+            return Ok(());
+        }
+        if kind == ObjSectionKind::Bss {
+            ensure!(
+                section.data.iter().all(|&b| b == 0),
+                "Can't reclassify section {} as bss: data is not zero",
+                section.name
+            );
+            section.data.clear();
+        } else if section.kind == ObjSectionKind::Bss {
+            ensure!(
+                section.size == 0,
+                "Can't reclassify bss section {} as {:?}: no data",
+                section.name,
+                kind
+            );
+        }
+        log::debug!("Reclassifying section {} from {:?} to {:?}", section.name, section.kind, kind);
+        section.kind = kind;
+
+        if kind != ObjSectionKind::Code {
+            self.known_functions.retain(|addr, _| addr.section != section_index);
+        }
+        let section = &self.sections[section_index];
+        for (_, symbol) in self.symbols.for_section(section_index) {
+            let consistent = match symbol.kind {
+                ObjSymbolKind::Function => kind == ObjSectionKind::Code,
+                ObjSymbolKind::Object => kind != ObjSectionKind::Code,
+                ObjSymbolKind::Unknown | ObjSymbolKind::Section => true,
+            };
+            if !consistent {
+                log::warn!(
+                    "{:?} symbol {} at {} is inconsistent with {:?} section",
+                    symbol.kind,
+                    symbol.name,
+                    self.address_format.address(section, symbol.address as u32),
+                    kind
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Section sizes attributed to each unit via its splits, excluding skipped splits.
+    /// Units in the link order are autogenerated according to their [ObjUnit] entry;
+    /// otherwise, a unit is autogenerated if all of its splits are.
+    pub fn unit_size_breakdown(&self) -> BTreeMap<String, UnitSizes> {
+        let mut result = BTreeMap::<String, UnitSizes>::new();
+        let mut explicit = HashSet::<&str>::new();
+        for (_, section) in self.sections.iter() {
+            let section_end = (section.address + section.size) as u32;
+            for (addr, split) in section.splits.iter() {
+                if split.skip {
+                    continue;
+                }
+                let end = if split.end == 0 { section_end } else { split.end };
+                let sizes = result
+                    .entry(split.unit.clone())
+                    .or_insert_with(|| UnitSizes { autogenerated: true, ..Default::default() });
+                let size = end.saturating_sub(addr);
+                match section.kind {
+                    ObjSectionKind::Code => sizes.code += size,
+                    ObjSectionKind::Data | ObjSectionKind::ReadOnlyData => sizes.data += size,
+                    ObjSectionKind::Bss => sizes.bss += size,
+                }
+                if !split.autogenerated {
+                    explicit.insert(split.unit.as_str());
+                }
+            }
+        }
+        for (name, sizes) in result.iter_mut() {
+            sizes.autogenerated = match self.link_order.iter().find(|unit| &unit.name == name) {
+                Some(unit) => unit.autogenerated,
+                None => !explicit.contains(name.as_str()),
+            };
+        }
+        result
+    }
+
+    /// Allocates common symbols into `.bss`, extending the section as needed.
+    /// Symbols are placed in name order, each aligned to its own alignment (stored
+    /// in the address of a common symbol when `align` is unset). A `.bss` section is
+    /// created for relocatable objects that don't have one.
+    ///
+    /// Allocation starts after the end of the last split in `.bss`. Objects produced by
+    /// [split_obj](crate::util::split::split_obj) for a common split hold only common symbols,
+    /// so allocating them yields that unit's own common section.
+    pub fn allocate_common_symbols(&mut self) -> Result<()> {
+        let mut common_symbols = self
+            .symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, symbol)| symbol.flags.is_common() && symbol.section.is_none())
+            .map(|(idx, symbol)| {
+                let align = symbol.align.unwrap_or(match symbol.address {
+                    0 => 4,
+                    address => address as u32,
+                });
+                (idx, symbol.name.clone(), symbol.size as u32, align)
+            })
+            .collect_vec();
+        if common_symbols.is_empty() {
+            return Ok(());
+        }
+        common_symbols.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let section_index = match self.sections.by_name(".bss")? {
+            Some((idx, _)) => idx,
+            None => {
+                ensure!(
+                    self.kind == ObjKind::Relocatable,
+                    "No .bss section to allocate common symbols into"
+                );
+                let elf_index = self.sections.next_section_index() + 1;
+                self.sections.push(ObjSection {
+                    name: ".bss".to_string(),
+                    kind: ObjSectionKind::Bss,
+                    address: 0,
+                    size: 0,
+                    data: vec![],
+                    align: 4,
+                    elf_index,
+                    relocations: Default::default(),
+                    virtual_address: None,
+                    file_offset: 0,
+                    section_known: true,
+                    splits: Default::default(),
+                    compressed_size_cache: Default::default(),
+                })
+            }
+        };
+
+        let section = &self.sections[section_index];
+        let mut section_align = section.align;
+        // Never allocate into an existing split, so that split units keep their ranges.
+        let mut end = max(
+            (section.address + section.size) as u32,
+            section.splits.iter().map(|(_, split)| split.end).max().unwrap_or(0),
+        );
+        for (symbol_idx, name, size, align) in common_symbols {
+            ensure!(
+                align.is_power_of_two(),
+                "Invalid alignment {:#X} for common symbol {}",
+                align,
+                name
+            );
+            let address = align_up(end, align);
+            log::debug!("Allocating common symbol {} @ {:#010X} size {:#X}", name, address, size);
+            self.symbols.set_address(symbol_idx, Some(section_index), address as u64)?;
+            let mut symbol = self.symbols[symbol_idx].clone();
+            symbol.flags.0 &= !ObjSymbolFlags::Common;
+            symbol.align = Some(align);
+            symbol.size_known = true;
+            self.symbols.replace(symbol_idx, symbol)?;
+            section_align = max(section_align, align as u64);
+            end = address + size;
+        }
+
+        let section = &mut self.sections[section_index];
+        section.size = end as u64 - section.address;
+        section.align = section_align;
+        Ok(())
+    }
+
+    /// Overwrites `range` in a code section with `nop` instructions, removing (and returning)
+    /// the relocations within it. The range must be 4-byte aligned.
+    pub fn nop_out_range(
+        &mut self,
+        section_index: usize,
+        range: Range<u32>,
+    ) -> Result<Vec<(u32, ObjReloc)>> {
+        let arch = self.architecture.arch();
+        let fmt = self.address_format;
+        let section = self
+            .sections
+            .get_mut(section_index)
+            .ok_or_else(|| anyhow!("Invalid section index {}", section_index))?;
+        ensure!(
+            section.kind == ObjSectionKind::Code,
+            "Can't nop out {} in non-code section",
+            fmt.range(section, range.start, range.end)
+        );
+        ensure!(
+            range.start % 4 == 0 && range.end % 4 == 0,
+            "Range {} is not 4-byte aligned",
+            fmt.range(section, range.start, range.end)
+        );
+        ensure!(
+            range.start <= range.end && section.contains_range(range.clone()),
+            "Range {} outside of section",
+            fmt.range(section, range.start, range.end)
+        );
+        let start = (range.start as u64 - section.address) as usize;
+        let end = (range.end as u64 - section.address) as usize;
+        let nop = arch.write_u32(0x60000000);
+        for word in section.data[start..end].chunks_exact_mut(4) {
+            word.copy_from_slice(&nop);
+        }
+        Ok(section.relocations.remove_range(range))
+    }
+
+    /// Replaces `remove` bytes at address `at` in a section with `insert` (see
+    /// [ObjSection::splice]), shifting symbols at or after the removed range by the net size
+    /// change. Sized symbols containing the splice are resized instead. Fails without
+    /// modifying the object if the splice would remove the start of a symbol or the end of
+    /// one containing it, or if growing the section would overlap the next one. Other
+    /// sections are not moved. [known_functions](Self::known_functions) and the blocked
+    /// relocation ranges within the section are shifted along with the symbols.
+    pub fn splice_section(
+        &mut self,
+        section_index: usize,
+        at: u32,
+        remove: usize,
+        insert: &[u8],
+    ) -> Result<()> {
+        let section = self
+            .sections
+            .get(section_index)
+            .ok_or_else(|| anyhow!("Invalid section index {}", section_index))?;
+        let end = at as u64 + remove as u64;
+        let delta = insert.len() as i64 - remove as i64;
+        let mut shifted = vec![];
+        let mut resized = vec![];
+        for (symbol_index, symbol) in self.symbols.for_section(section_index) {
+            let symbol_end = symbol.address + symbol.size;
+            if symbol.address >= end {
+                shifted.push(symbol_index);
+            } else if symbol.address > at as u64 {
+                bail!(
+                    "Splice at {} removes the start of symbol {}",
+                    self.address_format.address(section, at),
+                    symbol.name
+                );
+            } else if symbol_end > at as u64 {
+                ensure!(
+                    symbol_end >= end,
+                    "Splice at {} removes the end of symbol {}",
+                    self.address_format.address(section, at),
+                    symbol.name
+                );
+                resized.push(symbol_index);
+            }
+        }
+        if let Some(address) = self.known_functions.keys().find(|addr| {
+            addr.section == section_index && addr.address > at && (addr.address as u64) < end
+        }) {
+            bail!(
+                "Splice at {} removes known function {}",
+                self.address_format.address(section, at),
+                self.address_format.address(section, address.address)
+            );
+        }
+        if delta > 0 {
+            let new_end = section.address + section.size + delta as u64;
+            if let Some((_, next)) = self.sections.iter().find(|(index, other)| {
+                *index != section_index
+                    && other.address >= section.address + section.size
+                    && other.address < new_end
+            }) {
+                bail!(
+                    "Splice at {} grows section {} into section {} at {:#010X}",
+                    self.address_format.address(section, at),
+                    section.name,
+                    next.name,
+                    next.address
+                );
+            }
+        }
+
+        // Everything past this point is validated; the section splice checks its own
+        // relocations and splits before modifying anything.
+        self.sections[section_index].splice(at, remove, insert)?;
+        let shift = |address: u32| {
+            if address as u64 >= end {
+                (address as i64 + delta) as u32
+            } else {
+                address.min(at)
+            }
+        };
+        for symbol_index in shifted {
+            let address = (self.symbols[symbol_index].address as i64 + delta) as u64;
+            self.symbols.set_address(symbol_index, Some(section_index), address)?;
+        }
+        for symbol_index in resized {
+            let mut symbol = self.symbols[symbol_index].clone();
+            symbol.size = (symbol.size as i64 + delta) as u64;
+            self.symbols.replace(symbol_index, symbol)?;
+        }
+        let shift_ranges = |ranges: &AddressRanges| {
+            let mut out = AddressRanges::new();
+            for (start, end) in ranges.iter() {
+                let section = start.section;
+                let (start, end) = if section == section_index {
+                    (shift(start.address), shift(end))
+                } else {
+                    (start.address, end)
+                };
+                if start < end {
+                    out.insert(
+                        SectionAddress::new(section, start),
+                        SectionAddress::new(section, end),
+                    );
+                }
+            }
+            out
+        };
+        self.blocked_relocation_sources = shift_ranges(&self.blocked_relocation_sources);
+        self.blocked_relocation_targets = shift_ranges(&self.blocked_relocation_targets);
+        self.known_functions = self
+            .known_functions
+            .iter()
+            .map(|(&addr, &size)| {
+                if addr.section == section_index {
+                    (SectionAddress::new(section_index, shift(addr.address)), size)
+                } else {
+                    (addr, size)
+                }
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Shifts all section, symbol, relocation, split and linker-generated addresses by `delta`.
+    /// Absolute and common symbols are left untouched. Errors (without modifying the object)
+    /// if any address would fall outside the 32-bit address space.
+    pub fn rebase(&mut self, delta: i64) -> Result<()> {
+        let shift = |address: u64| -> Result<u64> {
+            let shifted = address as i64 + delta;
+            ensure!(
+                (0..=u32::MAX as i64).contains(&shifted),
+                "Rebasing {:#010X} by {:#X} is out of range",
+                address,
+                delta
+            );
+            Ok(shifted as u64)
+        };
+        let shift_u32 = |address: u32| shift(address as u64).map(|a| a as u32);
+        let shift_opt = |address: Option<u32>| address.map(shift_u32).transpose();
+
+        let mut sections = Vec::with_capacity(self.sections.len());
+        for (_, section) in self.sections.iter() {
+            let mut section = section.clone();
+            shift(section.address + section.size)?;
+            section.address = shift(section.address)?;
+            section.virtual_address = section.virtual_address.map(shift).transpose()?;
+            let relocations = section
+                .relocations
+                .iter()
+                .map(|(address, reloc)| Ok((shift_u32(address)?, reloc.clone())))
+                .collect::<Result<Vec<_>>>()?;
+            section.relocations = ObjRelocations::new(relocations)?;
+            let mut splits = ObjSplits::default();
+            for (address, split) in section.splits.iter() {
+                let mut split = split.clone();
+                if split.end != 0 {
+                    split.end = shift_u32(split.end)?;
+                }
+                splits.push(shift_u32(address)?, split);
+            }
+            section.splits = splits;
+            sections.push(section);
+        }
+        let symbols = self
+            .symbols
+            .iter()
+            .map(|symbol| {
+                let mut symbol = symbol.clone();
+                if symbol.section.is_some() {
+                    symbol.address = shift(symbol.address)?;
+                }
+                Ok(symbol)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let entry = self.entry.map(shift).transpose()?;
+        let linker_symbols = [
+            shift_opt(self.sda2_base)?,
+            shift_opt(self.sda_base)?,
+            shift_opt(self.stack_address)?,
+            shift_opt(self.stack_end)?,
+            shift_opt(self.db_stack_addr)?,
+            shift_opt(self.arena_lo)?,
+            shift_opt(self.arena_hi)?,
+        ];
+        let shift_ranges = |ranges: &AddressRanges| -> Result<AddressRanges> {
+            let mut out = AddressRanges::new();
+            for (start, end) in ranges.iter() {
+                let start = SectionAddress::new(start.section, shift_u32(start.address)?);
+                out.insert(start, SectionAddress::new(start.section, shift_u32(end)?));
+            }
+            Ok(out)
+        };
+        let blocked_relocation_sources = shift_ranges(&self.blocked_relocation_sources)?;
+        let blocked_relocation_targets = shift_ranges(&self.blocked_relocation_targets)?;
+        let known_functions = self
+            .known_functions
+            .iter()
+            .map(|(addr, &size)| {
+                Ok((SectionAddress::new(addr.section, shift_u32(addr.address)?), size))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+
+        self.sections = ObjSections::new(self.kind, sections);
+        self.symbols = ObjSymbols::new(self.kind, symbols);
+        self.entry = entry;
+        [
+            self.sda2_base,
+            self.sda_base,
+            self.stack_address,
+            self.stack_end,
+            self.db_stack_addr,
+            self.arena_lo,
+            self.arena_hi,
+        ] = linker_symbols;
+        self.blocked_relocation_sources = blocked_relocation_sources;
+        self.blocked_relocation_targets = blocked_relocation_targets;
+        self.known_functions = known_functions;
+        Ok(())
+    }
+
+    /// Coalesces consecutive sections of the same kind and base name (e.g. `.text` and
+    /// `.text.1`) where each begins exactly at the end of the previous one. Sections with a
+    /// gap between them, differing `section_known` or virtual addressing, or referenced by
+    /// unresolved REL relocations are left separate. Addresses are shared between sections,
+    /// so symbols, relocations and splits keep their addresses and move to the merged
+    /// section's index. Section symbols of merged-away sections are removed, and relocations
+    /// targeting them are moved to the merged section's symbol with adjusted addends.
+    /// Returns the number of sections merged away.
+    pub fn merge_adjacent_sections(&mut self) -> Result<usize> {
+        let base_name = |name: &str| -> String {
+            let end = name
+                .char_indices()
+                .skip(1)
+                .find(|&(_, c)| c == '.' || c == '$')
+                .map_or(name.len(), |(i, _)| i);
+            name[..end].to_string()
+        };
+        let referenced = |section: &ObjSection| {
+            self.unresolved_relocations.iter().any(|reloc| {
+                reloc.section as usize == section.elf_index
+                    || reloc.target_section as usize == section.elf_index
+            })
+        };
+
+        let mut sections = Vec::<ObjSection>::with_capacity(self.sections.len());
+        let mut index_map = Vec::with_capacity(self.sections.len());
+        let mut merged = 0;
+        for (_, section) in self.sections.iter() {
+            if let Some(last) = sections.last_mut() {
+                let last_end = last.address + last.size;
+                let mergeable = last.kind == section.kind
+                    && base_name(&last.name) == base_name(&section.name)
+                    && section.address == last_end
+                    && last.section_known == section.section_known
+                    && last.virtual_address.map(|va| va + last.size) == section.virtual_address
+                    && !referenced(last)
+                    && !referenced(section);
+                if mergeable {
+                    log::debug!(
+                        "Merging section {} ({:#010X}) into {} ({:#010X})",
+                        section.name,
+                        section.address,
+                        last.name,
+                        last.address
+                    );
+                    // Splits ending at the section end now end at the fragment boundary
+                    for (_, split) in last.splits.iter_mut() {
+                        if split.end == 0 {
+                            split.end = last_end as u32;
+                        }
+                    }
+                    for (address, split) in section.splits.iter() {
+                        last.splits.push(address, split.clone());
+                    }
+                    for (address, reloc) in section.relocations.iter() {
+                        last.relocations.insert(address, reloc.clone())?;
+                    }
+                    last.name = base_name(&last.name);
+                    last.data.extend_from_slice(&section.data);
+                    last.size += section.size;
+                    last.align = max(last.align, section.align);
+                    index_map.push(sections.len() - 1);
+                    merged += 1;
+                    continue;
+                }
+            }
+            index_map.push(sections.len());
+            sections.push(section.clone());
+        }
+        if merged == 0 {
+            return Ok(0);
+        }
+
+        let symbols = self
+            .symbols
+            .iter()
+            .map(|symbol| ObjSymbol {
+                section: symbol.section.map(|idx| index_map[idx]),
+                ..symbol.clone()
+            })
+            .collect_vec();
+        self.remap_section_addresses(&index_map.iter().map(|&idx| Some(idx)).collect_vec());
+        self.sections = ObjSections::new(self.kind, sections);
+        self.symbols = ObjSymbols::new(self.kind, symbols);
+
+        // Section symbols of merged fragments no longer mark the start of a section, so
+        // relocations targeting them are moved to the merged section's symbol
+        let fragments = self
+            .symbols
+            .iter()
+            .enumerate()
+            .filter_map(|(symbol_index, symbol)| match symbol.section {
+                Some(section_index)
+                    if symbol.kind == ObjSymbolKind::Section
+                        && symbol.address != self.sections[section_index].address =>
+                {
+                    Some((symbol_index, section_index))
+                }
+                _ => None,
+            })
+            .collect_vec();
+        if !fragments.is_empty() {
+            for &(symbol_index, section_index) in &fragments {
+                let anchor = self.section_anchor(section_index)?;
+                self.retarget_relocations(symbol_index, anchor, true)?;
+            }
+            let mut symbol_map = HashMap::new();
+            let mut symbols = vec![];
+            for (symbol_index, symbol) in self.symbols.iter().enumerate() {
+                if !fragments.iter().any(|&(index, _)| index == symbol_index) {
+                    symbol_map.insert(symbol_index, symbols.len());
+                    symbols.push(symbol.clone());
+                }
+            }
+            self.symbols = ObjSymbols::new(self.kind, symbols);
+            self.remap_symbol_indices(&symbol_map)?;
+        }
+        Ok(merged)
+    }
+
+    /// Moves [known_functions](Self::known_functions) and the blocked relocation ranges to
+    /// new section indices after sections are merged or removed. `map` is indexed by the old
+    /// section index; entries in sections mapped to `None` are dropped.
+    fn remap_section_addresses(&mut self, map: &[Option<usize>]) {
+        let remap = |addr: SectionAddress| {
+            map[addr.section].map(|section| SectionAddress::new(section, addr.address))
+        };
+        let remap_ranges = |ranges: &AddressRanges| {
+            let mut out = AddressRanges::new();
+            for (start, end) in ranges.iter() {
+                if let Some(start) = remap(start) {
+                    out.insert(start, SectionAddress::new(start.section, end));
+                }
+            }
+            out
+        };
+        self.blocked_relocation_sources = remap_ranges(&self.blocked_relocation_sources);
+        self.blocked_relocation_targets = remap_ranges(&self.blocked_relocation_targets);
+        self.known_functions = self
+            .known_functions
+            .iter()
+            .filter_map(|(&addr, &size)| Some((remap(addr)?, size)))
+            .collect();
+    }
+
+    /// Removes analysis-only data, keeping everything needed to emit the executable.
+    /// Linker generated symbols, section symbols and relocation targets are always kept.
+    pub fn strip(&mut self, options: StripOptions) -> Result<()> {
+        let is_debug_section = |name: &str| {
+            matches!(name, ".comment" | ".line" | ".mwcats") || name.starts_with(".debug")
+        };
+        let keep_section = self
+            .sections
+            .iter()
+            .map(|(_, section)| !(options.debug_sections && is_debug_section(&section.name)))
+            .collect_vec();
+        let mut section_map = vec![None; keep_section.len()];
+        let mut sections = vec![];
+        for (section_index, section) in self.sections.iter() {
+            if keep_section[section_index] {
+                section_map[section_index] = Some(sections.len());
+                sections.push(section.clone());
+            }
+        }
+
+        let referenced = sections
+            .iter()
+            .flat_map(|section| section.relocations.iter().map(|(_, reloc)| reloc.target_symbol))
+            .collect::<HashSet<_>>();
+        let mut symbol_map = vec![None; self.symbols.count()];
+        let mut symbols = vec![];
+        for (symbol_index, symbol) in self.symbols.iter().enumerate() {
+            let keep = referenced.contains(&symbol_index)
+                || match symbol.section {
+                    Some(section_index) if !keep_section[section_index] => false,
+                    _ => {
+                        !(options.local_symbols && symbol.flags.is_local())
+                            || symbol.kind == ObjSymbolKind::Section
+                            || is_linker_generated_label(&symbol.name)
+                            || is_linker_generated_object(&symbol.name)
+                    }
+                };
+            if keep {
+                symbol_map[symbol_index] = Some(symbols.len());
+                symbols.push(ObjSymbol {
+                    section: match symbol.section {
+                        Some(section_index) => {
+                            Some(section_map[section_index].ok_or_else(|| {
+                                anyhow!("Symbol {} in stripped section is referenced", symbol.name)
+                            })?)
+                        }
+                        None => None,
+                    },
+                    ..symbol.clone()
+                });
+            }
+        }
+        log::debug!(
+            "Stripped {} sections and {} symbols",
+            self.sections.len() - sections.len(),
+            self.symbols.count() - symbols.len()
+        );
+
+        if self
+            .blocked_relocation_sources
+            .iter()
+            .chain(self.blocked_relocation_targets.iter())
+            .any(|(start, _)| section_map[start.section].is_none())
+        {
+            bail!("Blocked relocation range in stripped section");
+        }
+        self.remap_section_addresses(&section_map);
+        self.sections = ObjSections::new(self.kind, sections);
+        self.symbols = ObjSymbols::new(self.kind, symbols);
+        self.remap_symbol_indices(
+            &symbol_map.iter().enumerate().filter_map(|(i, new)| new.map(|n| (i, n))).collect(),
+        )?;
+        if options.debug_sections {
+            self.mw_comment = None;
+        }
+        if options.metadata {
+            self.split_meta = None;
+            self.known_functions.clear();
+        }
+        Ok(())
+    }
+
+    /// Address ranges between the first and last section that belong to no section, in
+    /// address order, e.g. a hole between `.bss` and `.sdata`. Empty for relocatable objects.
+    pub fn address_gaps(&self) -> Vec<Range<u32>> {
+        if self.kind != ObjKind::Executable {
+            return vec![];
+        }
+        let mut gaps = vec![];
+        let mut last_end: Option<u32> = None;
+        for (_, section) in self.sections.iter().sorted_by_key(|(_, s)| s.address) {
+            let start = section.address as u32;
+            let end = (section.address + section.size) as u32;
+            if let Some(last) = last_end {
+                if start > last {
+                    gaps.push(last..start);
+                }
+            }
+            last_end = Some(last_end.map_or(end, |last| max(last, end)));
+        }
+        gaps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::{
+        test_obj, test_section, test_split, test_unit, ObjArchitecture, ObjRelocKind,
+        ObjSymbolFlagSet, ObjUnit,
+    };
+
+    #[test]
+    fn test_nop_out_range() {
+        let data = [0x7C0802A6u32, 0x48000001, 0x4E800020]
+            .iter()
+            .flat_map(|w| w.to_be_bytes())
+            .collect_vec();
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, data),
+            test_section(".data", ObjSectionKind::Data, 0x80003200, vec![0; 8]),
+        ]);
+        obj.sections[0]
+            .relocations
+            .insert(0x80003104, ObjReloc {
+                kind: ObjRelocKind::PpcRel24,
+                target_symbol: 0,
+                addend: 0,
+                module: None,
+            })
+            .unwrap();
+
+        let removed = obj.nop_out_range(0, 0x80003104..0x80003108).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].0, 0x80003104);
+        assert_eq!(removed[0].1.kind, ObjRelocKind::PpcRel24);
+        assert!(obj.sections[0].relocations.is_empty());
+        let words = obj.sections[0]
+            .data
+            .chunks_exact(4)
+            .map(|w| u32::from_be_bytes(w.try_into().unwrap()))
+            .collect_vec();
+        assert_eq!(words, vec![0x7C0802A6, 0x60000000, 0x4E800020]);
+
+        assert!(obj.nop_out_range(0, 0x80003102..0x80003108).is_err());
+        assert!(obj.nop_out_range(0, 0x80003108..0x80003110).is_err());
+        assert!(obj.nop_out_range(1, 0x80003200..0x80003204).is_err());
+    }
+
+    #[test]
+    fn test_splice_section() {
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x20]),
+            test_section(".data", ObjSectionKind::Data, 0x80003130, vec![0; 0x10]),
+        ]);
+        obj.known_functions.insert(SectionAddress::new(0, 0x80003110), Some(0x10));
+        obj.blocked_relocation_sources
+            .insert(SectionAddress::new(0, 0x80003110), SectionAddress::new(0, 0x80003120));
+        obj.add_split(0, 0x80003100, test_split("a.cpp", 0x80003110)).unwrap();
+        obj.add_split(0, 0x80003110, test_split("b.cpp", 0x80003120)).unwrap();
+        let mut function = |name: &str, address| {
+            obj.symbols
+                .add_direct(ObjSymbol {
+                    name: name.to_string(),
+                    address,
+                    section: Some(0),
+                    size: 0x10,
+                    size_known: true,
+                    kind: ObjSymbolKind::Function,
+                    ..Default::default()
+                })
+                .unwrap()
+        };
+        let a = function("fn_80003100", 0x80003100);
+        let b = function("fn_80003110", 0x80003110);
+        obj.sections[0]
+            .relocations
+            .insert(0x80003114, ObjReloc {
+                kind: ObjRelocKind::PpcRel24,
+                target_symbol: a,
+                addend: 0,
+                module: None,
+            })
+            .unwrap();
+
+        // Insert a trampoline inside the first function
+        obj.splice_section(0, 0x80003108, 0, &[0x60; 8]).unwrap();
+        let section = &obj.sections[0];
+        assert_eq!(section.size, 0x28);
+        assert_eq!(&section.data[8..0x10], &[0x60; 8]);
+        assert_eq!((obj.symbols[a].address, obj.symbols[a].size), (0x80003100, 0x18));
+        assert_eq!((obj.symbols[b].address, obj.symbols[b].size), (0x80003118, 0x10));
+        assert_eq!(obj.symbols.at_section_address(0, 0x80003118).count(), 1);
+        assert_eq!(section.relocations.iter().map(|(addr, _)| addr).collect_vec(), vec![
+            0x8000311C
+        ]);
+        let splits = section.splits.iter().map(|(addr, s)| (addr, s.end)).collect_vec();
+        assert_eq!(splits, vec![(0x80003100, 0x80003118), (0x80003118, 0x80003128)]);
+        assert_eq!(obj.known_functions.keys().collect_vec(), vec![&SectionAddress::new(
+            0, 0x80003118
+        )]);
+        assert_eq!(obj.blocked_relocation_sources.iter().collect_vec(), vec![(
+            SectionAddress::new(0, 0x80003118),
+            0x80003128
+        )]);
+
+        // Splices inside or removing a relocated field are rejected
+        assert!(obj.splice_section(0, 0x8000311E, 0, &[0; 4]).is_err());
+        assert!(obj.splice_section(0, 0x8000311C, 4, &[]).is_err());
+        // Misaligning the relocation is rejected
+        assert!(obj.splice_section(0, 0x80003108, 0, &[0; 2]).is_err());
+        // Removing the start of a symbol is rejected
+        assert!(obj.splice_section(0, 0x80003114, 8, &[]).is_err());
+        // Growing into the next section is rejected
+        assert!(obj.splice_section(0, 0x80003100, 0, &[0; 0x10]).is_err());
+        assert_eq!(obj.sections[0].size, 0x28);
+        assert_eq!(obj.symbols[b].address, 0x80003118);
+    }
+
+    #[test]
+    fn test_address_gaps() {
+        let obj = test_obj(vec![
+            test_section(".sdata", ObjSectionKind::Data, 0x80005000, vec![0; 0x40]),
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x100]),
+            test_section(".data", ObjSectionKind::Data, 0x80003200, vec![0; 0x100]),
+            test_section(".bss", ObjSectionKind::Bss, 0x80003300, vec![0; 0x80]),
+        ]);
+        assert_eq!(obj.address_gaps(), vec![0x80003380..0x80005000]);
+    }
+
+    #[test]
+    fn test_allocate_common_symbols() {
+        let mut bss = test_section(".bss", ObjSectionKind::Bss, 0, vec![]);
+        bss.size = 0x10;
+        let common = |name: &str, size: u64, align: u32| ObjSymbol {
+            name: name.to_string(),
+            address: align as u64,
+            size,
+            size_known: true,
+            flags: ObjSymbolFlagSet(ObjSymbolFlags::Global | ObjSymbolFlags::Common),
+            kind: ObjSymbolKind::Object,
+            ..Default::default()
+        };
+        let mut obj = ObjInfo::new(
+            ObjKind::Relocatable,
+            ObjArchitecture::PowerPc,
+            "test".to_string(),
+            vec![common("b", 6, 4), common("a", 8, 8)],
+            vec![bss],
+        );
+        obj.allocate_common_symbols().unwrap();
+
+        assert_eq!(obj.sections[0].size, 0x10 + 8 + 6);
+        assert_eq!(obj.sections[0].align, 8);
+        let (_, a) = obj.symbols.by_name("a").unwrap().unwrap();
+        assert_eq!((a.section, a.address), (Some(0), 0x10));
+        let (_, b) = obj.symbols.by_name("b").unwrap().unwrap();
+        assert_eq!((b.section, b.address), (Some(0), 0x18));
+        assert!(obj.symbols.iter().all(|s| !s.flags.is_common()));
+        assert_eq!(obj.data_size(), 0x1E);
+    }
+
+    #[test]
+    fn test_common_split_allocation() {
+        let mut obj = test_obj(vec![test_section(".bss", ObjSectionKind::Bss, 0x80004000, vec![])]);
+        obj.sections[0].size = 0x20;
+        obj.link_order.push(test_unit("a.cpp"));
+        obj.link_order.push(test_unit("b.cpp"));
+        obj.add_split(0, 0x80004000, test_split("a.cpp", 0x80004010)).unwrap();
+        obj.add_split(0, 0x80004010, ObjSplit { common: true, ..test_split("b.cpp", 0x80004020) })
+            .unwrap();
+        for (name, address, size, align) in [
+            ("a_var", 0x80004000, 0x10, None),
+            ("b_x", 0x80004010, 8, Some(8)),
+            ("b_y", 0x80004018, 4, Some(4)),
+        ] {
+            obj.add_symbol(
+                ObjSymbol {
+                    name: name.to_string(),
+                    address,
+                    section: Some(0),
+                    size,
+                    size_known: true,
+                    flags: ObjSymbolFlagSet(ObjSymbolFlags::Global.into()),
+                    kind: ObjSymbolKind::Object,
+                    align,
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+        }
+
+        let mut objects = crate::util::split::split_obj(&obj, None).unwrap();
+        assert_eq!(objects[0].sections[0].size, 0x10);
+        let unit = &mut objects[1];
+        assert!(unit.sections.is_empty());
+        assert!(unit.symbols.iter().all(|s| s.flags.is_common() && s.section.is_none()));
+
+        unit.allocate_common_symbols().unwrap();
+        let (bss_index, bss) = unit.sections.by_name(".bss").unwrap().unwrap();
+        assert_eq!((bss.size, bss.align), (0xC, 8));
+        let (_, b_x) = unit.symbols.by_name("b_x").unwrap().unwrap();
+        assert_eq!((b_x.section, b_x.address), (Some(bss_index), 0));
+        let (_, b_y) = unit.symbols.by_name("b_y").unwrap().unwrap();
+        assert_eq!((b_y.section, b_y.address), (Some(bss_index), 8));
+    }
+
+    #[test]
+    fn test_snap_split_boundaries() {
+        let build = || {
+            let mut obj =
+                test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![
+                    0;
+                    0x40
+                ])]);
+            for (name, address, size) in
+                [("fn_a", 0x80003100, 0x20), ("fn_b", 0x80003120, 0x10), ("fn_c", 0x80003130, 0x10)]
+            {
+                obj.add_symbol(
+                    ObjSymbol {
+                        name: name.to_string(),
+                        address,
+                        section: Some(0),
+                        size,
+                        size_known: true,
+                        kind: ObjSymbolKind::Function,
+                        ..Default::default()
+                    },
+                    false,
+                )
+                .unwrap();
+            }
+            obj.add_split(0, 0x80003100, test_split("a.c", 0x80003128)).unwrap();
+            obj.add_split(0, 0x80003128, test_split("b.c", 0x80003140)).unwrap();
+            obj
+        };
+        let splits = |obj: &ObjInfo| {
+            obj.sections[0]
+                .splits
+                .iter()
+                .map(|(addr, split)| (split.unit.clone(), addr, split.end))
+                .collect_vec()
+        };
+
+        // Split end inside fn_b
+        let mut obj = build();
+        assert_eq!(obj.snap_split_boundaries(SnapDirection::Grow).unwrap(), 2);
+        assert_eq!(splits(&obj), vec![
+            ("a.c".to_string(), 0x80003100, 0x80003130),
+            ("b.c".to_string(), 0x80003130, 0x80003140)
+        ]);
+        assert_eq!(obj.snap_split_boundaries(SnapDirection::Grow).unwrap(), 0);
+
+        let mut obj = build();
+        assert_eq!(obj.snap_split_boundaries(SnapDirection::Shrink).unwrap(), 2);
+        assert_eq!(splits(&obj), vec![
+            ("a.c".to_string(), 0x80003100, 0x80003120),
+            ("b.c".to_string(), 0x80003120, 0x80003140)
+        ]);
+    }
+
+    #[test]
+    fn test_unit_size_breakdown() {
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x100]),
+            test_section(".data", ObjSectionKind::Data, 0x80003200, vec![0; 0x40]),
+            test_section(".bss", ObjSectionKind::Bss, 0x80003240, vec![]),
+        ]);
+        obj.sections[2].size = 0x20;
+        obj.link_order.push(test_unit("a.cpp"));
+        obj.link_order.push(ObjUnit { autogenerated: true, ..test_unit("auto_01_80003180_text") });
+        obj.add_split(0, 0x80003100, test_split("a.cpp", 0x80003180)).unwrap();
+        obj.add_split(0, 0x80003180, test_split("auto_01_80003180_text", 0x80003200)).unwrap();
+        obj.add_split(1, 0x80003200, test_split("a.cpp", 0x80003210)).unwrap();
+        obj.add_split(1, 0x80003210, ObjSplit {
+            autogenerated: true,
+            ..test_split("auto_01_80003180_text", 0x80003240)
+        })
+        .unwrap();
+        obj.add_split(2, 0x80003240, ObjSplit { common: true, ..test_split("a.cpp", 0x80003260) })
+            .unwrap();
+
+        let breakdown = obj.unit_size_breakdown();
+        assert_eq!(breakdown.len(), 2);
+        assert_eq!(breakdown["a.cpp"], UnitSizes {
+            code: 0x80,
+            data: 0x10,
+            bss: 0x20,
+            autogenerated: false
+        });
+        assert_eq!(breakdown["auto_01_80003180_text"], UnitSizes {
+            code: 0x80,
+            data: 0x30,
+            bss: 0,
+            autogenerated: true
+        });
+    }
+
+    #[test]
+    fn test_set_section_kind() {
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x100]),
+            test_section(".data", ObjSectionKind::Data, 0x80003200, vec![1; 0x80]),
+            test_section(".bss", ObjSectionKind::Bss, 0x80003280, vec![]),
+        ]);
+        obj.sections[2].size = 0x40;
+        assert_eq!((obj.code_size(), obj.data_size()), (0x100, 0xC0));
+        obj.known_functions.insert(SectionAddress::new(0, 0x80003100), Some(0x80));
+
+        obj.set_section_kind(1, ObjSectionKind::Code).unwrap();
+        assert_eq!((obj.code_size(), obj.data_size()), (0x180, 0x40));
+
+        obj.set_section_kind(0, ObjSectionKind::ReadOnlyData).unwrap();
+        assert_eq!((obj.code_size(), obj.data_size()), (0x80, 0x140));
+        assert!(obj.known_functions.is_empty());
+
+        // Non-zero data can't become bss, and bss has no data to become code
+        assert!(obj.set_section_kind(1, ObjSectionKind::Bss).is_err());
+        assert!(obj.set_section_kind(2, ObjSectionKind::Code).is_err());
+        obj.set_section_kind(0, ObjSectionKind::Bss).unwrap();
+        assert!(obj.sections[0].data.is_empty());
+        assert_eq!((obj.code_size(), obj.data_size()), (0x80, 0x140));
+    }
+
+    #[test]
+    fn test_rebase() {
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x10]),
+            test_section(".data", ObjSectionKind::Data, 0x80004000, vec![0; 0x10]),
+        ]);
+        let symbol = obj
+            .add_symbol(
+                ObjSymbol {
+                    name: "fn_80003100".to_string(),
+                    address: 0x80003100,
+                    section: Some(0),
+                    size: 0x10,
+                    size_known: true,
+                    kind: ObjSymbolKind::Function,
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+        let abs = obj
+            .add_symbol(
+                ObjSymbol { name: "abs".to_string(), address: 0x1234, ..Default::default() },
+                false,
+            )
+            .unwrap();
+        obj.sections[1]
+            .relocations
+            .insert(0x80004004, ObjReloc {
+                kind: ObjRelocKind::Absolute,
+                target_symbol: symbol,
+                addend: 0,
+                module: None,
+            })
+            .unwrap();
+        obj.sections[0].splits.push(0x80003100, test_split("a.c", 0x80003110));
+        obj.entry = Some(0x80003100);
+        obj.sda_base = Some(0x80408000);
+        obj.known_functions.insert(SectionAddress::new(0, 0x80003100), Some(0x10));
+
+        obj.rebase(-0x1000).unwrap();
+        assert_eq!(obj.sections[0].address, 0x80002100);
+        assert_eq!(obj.sections[1].address, 0x80003000);
+        assert_eq!(obj.symbols[symbol].address, 0x80002100);
+        assert_eq!(obj.symbols[abs].address, 0x1234);
+        assert_eq!(
+            obj.symbols.at_section_address(0, 0x80003110).next().map(|(idx, _)| idx),
+            Some(symbol)
+        );
+        assert!(obj.sections[1].relocations.contains(0x80003004));
+        assert_eq!(obj.sections[0].splits.iter().map(|(a, s)| (a, s.end)).collect_vec(), vec![(
+            0x80002100, 0x80002110
+        )]);
+        assert_eq!(obj.entry, Some(0x80002100));
+        assert_eq!(obj.sda_base, Some(0x80407000));
+        assert!(obj.known_functions.contains_key(&SectionAddress::new(0, 0x80002100)));
+
+        assert!(obj.rebase(0x80000000).is_err());
+        assert_eq!(obj.sections[0].address, 0x80002100);
+    }
+
+    #[test]
+    fn test_strip() {
+        use object::{Object, ObjectSection, ObjectSymbol};
+
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0x60; 0x20]),
+            test_section(".comment", ObjSectionKind::ReadOnlyData, 0, vec![0; 0x10]),
+        ]);
+        for (name, address, section, flags) in [
+            ("fn_80003100", 0x80003100, Some(0), ObjSymbolFlags::Global),
+            ("helper", 0x80003110, Some(0), ObjSymbolFlags::Local),
+            ("_f_text", 0x80003100, Some(0), ObjSymbolFlags::Global),
+            ("_stack_addr", 0x80400000, None, ObjSymbolFlags::Global),
+        ] {
+            obj.add_symbol(
+                ObjSymbol {
+                    name: name.to_string(),
+                    address,
+                    section,
+                    flags: ObjSymbolFlagSet(flags.into()),
+                    kind: if section.is_some() {
+                        ObjSymbolKind::Function
+                    } else {
+                        ObjSymbolKind::Unknown
+                    },
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+        }
+        let (fn_index, _) = obj.symbols.by_name("fn_80003100").unwrap().unwrap();
+        obj.sections[0]
+            .relocations
+            .insert(0x80003114, ObjReloc {
+                kind: ObjRelocKind::PpcRel24,
+                target_symbol: fn_index,
+                addend: 0,
+                module: None,
+            })
+            .unwrap();
+        obj.known_functions.insert(SectionAddress::new(0, 0x80003100), Some(0x10));
+        obj.entry = Some(0x80003100);
+
+        obj.strip(StripOptions::default()).unwrap();
+        assert_eq!(obj.sections.len(), 1);
+        assert!(obj.known_functions.is_empty());
+        assert!(obj.symbols.iter().all(|s| !s.flags.is_local()));
+        assert!(obj.symbols.by_name("helper").unwrap().is_none());
+        assert!(obj.symbols.by_name("_f_text").unwrap().is_some());
+        assert_eq!(obj.stack_address, Some(0x80400000));
+        let (fn_index, _) = obj.symbols.by_name("fn_80003100").unwrap().unwrap();
+        assert_eq!(obj.sections[0].relocations.at(0x80003114).unwrap().target_symbol, fn_index);
+
+        // The stripped object still writes out as a valid ELF
+        let out = crate::util::elf::write_elf(&obj, false).unwrap();
+        let file = object::read::File::parse(out.as_slice()).unwrap();
+        assert_eq!(file.entry(), 0x80003100);
+        assert!(file.section_by_name(".comment").is_none());
+        let text = file.section_by_name(".text").unwrap();
+        assert_eq!(text.address(), 0x80003100);
+        assert_eq!(text.data().unwrap(), &[0x60; 0x20]);
+        assert!(file.symbols().any(|s| s.name() == Ok("fn_80003100")));
+        assert!(file.symbols().all(|s| s.name() != Ok("helper")));
+    }
+
+    #[test]
+    fn test_merge_adjacent_sections() {
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0x60; 0x10]),
+            test_section(".text.1", ObjSectionKind::Code, 0x80003110, vec![0x4E; 0x10]),
+            // Gap after .text.1
+            test_section(".text.2", ObjSectionKind::Code, 0x80003140, vec![0x38; 0x10]),
+            test_section(".data", ObjSectionKind::Data, 0x80003150, vec![0; 0x10]),
+        ]);
+        let symbol = obj
+            .add_symbol(
+                ObjSymbol {
+                    name: "fn_80003110".to_string(),
+                    address: 0x80003110,
+                    section: Some(1),
+                    size: 0x10,
+                    size_known: true,
+                    kind: ObjSymbolKind::Function,
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+        let data_symbol = obj
+            .add_symbol(
+                ObjSymbol {
+                    name: "lbl_80003150".to_string(),
+                    address: 0x80003150,
+                    section: Some(3),
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+        let fragment = obj.section_anchor(1).unwrap();
+        obj.sections[1]
+            .relocations
+            .insert(0x80003114, ObjReloc {
+                kind: ObjRelocKind::PpcRel24,
+                target_symbol: symbol,
+                addend: 0,
+                module: None,
+            })
+            .unwrap();
+        obj.sections[3]
+            .relocations
+            .insert(0x80003150, ObjReloc {
+                kind: ObjRelocKind::Absolute,
+                target_symbol: fragment,
+                addend: 4,
+                module: None,
+            })
+            .unwrap();
+        obj.sections[0].splits.push(0x80003100, test_split("a.c", 0));
+        obj.sections[1].splits.push(0x80003110, test_split("b.c", 0x80003120));
+        obj.known_functions.insert(SectionAddress::new(1, 0x80003110), Some(0x10));
+
+        assert_eq!(obj.merge_adjacent_sections().unwrap(), 1);
+        assert_eq!(obj.sections.len(), 3);
+        let text = &obj.sections[0];
+        assert_eq!((text.name.as_str(), text.address, text.size), (".text", 0x80003100, 0x20));
+        assert_eq!(&text.data[0xC..0x14], &[0x60, 0x60, 0x60, 0x60, 0x4E, 0x4E, 0x4E, 0x4E]);
+        assert!(text.relocations.contains(0x80003114));
+        assert_eq!(text.splits.iter().map(|(a, s)| (a, s.end)).collect_vec(), vec![
+            (0x80003100, 0x80003110),
+            (0x80003110, 0x80003120)
+        ]);
+        assert_eq!(obj.sections[1].name, ".text.2");
+        assert_eq!(obj.symbols[symbol].section, Some(0));
+        assert_eq!(obj.symbols[data_symbol].section, Some(2));
+        assert_eq!(
+            obj.symbols.at_section_address(0, 0x80003110).next().map(|(idx, _)| idx),
+            Some(symbol)
+        );
+        assert!(obj.known_functions.contains_key(&SectionAddress::new(0, 0x80003110)));
+        // The .text.1 section symbol is replaced by one for the merged section
+        assert_eq!(obj.symbols.count(), 3);
+        let reloc = obj.sections[2].relocations.at(0x80003150).unwrap();
+        let target = &obj.symbols[reloc.target_symbol];
+        assert_eq!((target.name.as_str(), target.kind), (".text", ObjSymbolKind::Section));
+        assert_eq!((target.address, reloc.addend), (0x80003100, 0x14));
+
+        assert_eq!(obj.merge_adjacent_sections().unwrap(), 0);
+    }
+}
@@ -0,0 +1,1705 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ops::Range,
+};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use itertools::Itertools;
+
+use crate::{
+    analysis::cfa::SectionAddress,
+    array_ref_mut,
+    obj::{
+        ObjInfo, ObjKind, ObjReloc, ObjRelocKind, ObjSection, ObjSectionKind, ObjSymbol,
+        ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind, ObjSymbolScope, SymbolIndex,
+    },
+};
+
+/// A relocation whose kind doesn't match the instruction at its address,
+/// or that isn't aligned for its section kind.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RelocMismatch {
+    pub section: usize,
+    pub section_kind: ObjSectionKind,
+    pub address: u32,
+    pub kind: ObjRelocKind,
+    /// Word at the relocation address, if readable.
+    pub instruction: Option<u32>,
+    /// The relocation doesn't sit on an instruction boundary (code)
+    /// or the alignment required by its kind (data).
+    pub misaligned: bool,
+}
+
+/// Relocation statistics for a section. See [ObjInfo::relocation_coverage_report].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RelocCoverage {
+    pub section: usize,
+    pub relocations: usize,
+    /// Largest address range without any relocations.
+    pub largest_gap: Range<u32>,
+    /// Code section whose largest gap exceeds the threshold.
+    pub suspicious: bool,
+}
+
+/// A relocation whose addend reaches past the end of its target symbol.
+/// See [ObjInfo::relocation_addend_audit].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AddendSuggestion {
+    pub section: usize,
+    pub address: u32,
+    pub target_symbol: SymbolIndex,
+    pub addend: i64,
+    /// A symbol better matching `target + addend`, with the remaining addend.
+    pub suggestion: Option<(SymbolIndex, i64)>,
+}
+
+/// A relocation with its target resolved, for display. See [ObjInfo::relocation_overlay].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RelocOverlay {
+    pub kind: ObjRelocKind,
+    pub target_symbol: SymbolIndex,
+    /// Target symbol name.
+    pub target: String,
+    pub addend: i64,
+    pub module: Option<u32>,
+    /// Bits of the word at the address written by the relocation.
+    pub field_mask: u32,
+}
+
+/// Where a relocation's target lives relative to the unit containing the relocation.
+/// See [ObjInfo::classify_relocation].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RelocClassification {
+    /// The target is in the same unit, and can be referenced locally.
+    Internal,
+    /// The target is in another unit of this module, and must be referenced as an
+    /// undefined symbol.
+    CrossUnit(String),
+    /// The target is resolved at link time rather than by any unit: it's in another module,
+    /// or is an absolute or common symbol.
+    External(u32),
+}
+
+impl ObjInfo {
+    /// Relocations in the given section, in ascending address order.
+    /// Emitters (`.rela` sections, asm) should use this rather than relying on the
+    /// ordering of the underlying storage.
+    pub fn relocations_in_order(
+        &self,
+        section_index: usize,
+    ) -> impl DoubleEndedIterator<Item = (u32, &ObjReloc)> {
+        self.sections[section_index].relocations.iter()
+    }
+
+    /// Relocations in the given section grouped by target symbol, each group in ascending
+    /// address order. Used for building import tables.
+    pub fn relocations_by_target(
+        &self,
+        section_index: usize,
+    ) -> BTreeMap<SymbolIndex, Vec<(u32, &ObjReloc)>> {
+        let mut result = BTreeMap::<SymbolIndex, Vec<(u32, &ObjReloc)>>::new();
+        for (address, reloc) in self.relocations_in_order(section_index) {
+            result.entry(reloc.target_symbol).or_default().push((address, reloc));
+        }
+        result
+    }
+
+    /// Compares section data against the same section in another object, ignoring
+    /// the bits covered by either side's relocations. (e.g. after relinking)
+    pub fn sections_equal_ignoring_relocs(&self, other: &ObjInfo, section_index: usize) -> bool {
+        let (Some(left), Some(right)) =
+            (self.sections.get(section_index), other.sections.get(section_index))
+        else {
+            return false;
+        };
+        if left.data.len() != right.data.len() {
+            return false;
+        }
+        let arch = self.architecture.arch();
+        let mut masks = BTreeMap::<u32, u32>::new();
+        for section in [left, right] {
+            for (address, reloc) in section.relocations.iter() {
+                *masks.entry(address - section.address as u32).or_default() |=
+                    arch.reloc_field_mask(reloc.kind);
+            }
+        }
+        left.data.chunks(4).zip(right.data.chunks(4)).enumerate().all(|(i, (l, r))| {
+            match masks.get(&(i as u32 * 4)) {
+                Some(&mask) if l.len() == 4 => {
+                    let l = arch.read_u32(l.try_into().unwrap());
+                    let r = arch.read_u32(r.try_into().unwrap());
+                    l & !mask == r & !mask
+                }
+                _ => l == r,
+            }
+        })
+    }
+
+    /// Copies the section data with the fields written by each relocation zeroed out
+    /// (per [Architecture::reloc_field_mask]), so the bytes don't depend on link addresses.
+    pub fn section_bytes_with_relocs_zeroed(&self, section_index: usize) -> Vec<u8> {
+        let section = &self.sections[section_index];
+        let arch = self.architecture.arch();
+        let mut data = section.raw_bytes().to_vec();
+        for (address, reloc) in section.relocations.iter() {
+            let offset = (address as u64 - section.address) as usize;
+            let Some(bytes) = data.get_mut(offset..offset + 4) else {
+                continue;
+            };
+            let word = arch.read_u32(bytes.try_into().unwrap());
+            bytes.copy_from_slice(&arch.write_u32(word & !arch.reloc_field_mask(reloc.kind)));
+        }
+        data
+    }
+
+    /// Flags relocations whose addend magnitude exceeds the size of the target symbol, which
+    /// usually means the target was mis-resolved and the addend compensates. For each, a
+    /// better target covering the final address is suggested when one exists (per
+    /// [ObjSymbols::for_relocation]). Relocations against other modules are skipped.
+    /// Nothing is modified.
+    pub fn relocation_addend_audit(&self) -> Result<Vec<AddendSuggestion>> {
+        let mut out = vec![];
+        for (section_index, section) in self.sections.iter() {
+            for (address, reloc) in section.relocations.iter() {
+                if matches!(reloc.module, Some(module_id) if module_id != self.module_id) {
+                    continue;
+                }
+                let symbol = &self.symbols[reloc.target_symbol];
+                if reloc.addend.unsigned_abs() <= symbol.size {
+                    continue;
+                }
+                let target = symbol.address as i64 + reloc.addend;
+                let suggestion = match (symbol.section, u32::try_from(target)) {
+                    (Some(target_section), Ok(target)) => self
+                        .symbols
+                        .for_relocation(SectionAddress::new(target_section, target), reloc.kind)?
+                        .filter(|&(index, _)| index != reloc.target_symbol)
+                        .map(|(index, s)| (index, target as i64 - s.address as i64)),
+                    _ => None,
+                };
+                out.push(AddendSuggestion {
+                    section: section_index,
+                    address,
+                    target_symbol: reloc.target_symbol,
+                    addend: reloc.addend,
+                    suggestion,
+                });
+            }
+        }
+        Ok(out)
+    }
+
+    /// Re-binds relocations to the closest symbol that now covers their target, e.g. after
+    /// [materialize_known_functions](Self::materialize_known_functions) adds a function
+    /// inside a region previously referenced via a section anchor. The addend is adjusted to
+    /// keep the target address unchanged. Returns the number of relocations updated; running
+    /// it again without further symbol changes updates nothing.
+    pub fn reresolve_relocations(&mut self) -> Result<usize> {
+        let mut updates = vec![];
+        for (section_index, section) in self.sections.iter() {
+            for (address, reloc) in section.relocations.iter() {
+                if matches!(reloc.module, Some(module_id) if module_id != self.module_id) {
+                    continue;
+                }
+                let symbol = &self.symbols[reloc.target_symbol];
+                let target = symbol.address as i64 + reloc.addend;
+                let (Some(target_section), Ok(target)) = (symbol.section, u32::try_from(target))
+                else {
+                    continue;
+                };
+                let Some((index, candidate)) = self
+                    .symbols
+                    .for_relocation(SectionAddress::new(target_section, target), reloc.kind)?
+                else {
+                    continue;
+                };
+                let better = candidate.address > symbol.address
+                    || (candidate.address == symbol.address
+                        && symbol.kind == ObjSymbolKind::Section
+                        && candidate.kind != ObjSymbolKind::Section);
+                if index != reloc.target_symbol && better {
+                    updates.push((
+                        section_index,
+                        address,
+                        index,
+                        target as i64 - candidate.address as i64,
+                    ));
+                }
+            }
+        }
+        for &(section_index, address, target_symbol, addend) in &updates {
+            let reloc = self.sections[section_index].relocations.at_mut(address).unwrap();
+            log::debug!(
+                "Re-binding relocation @ {:#010X} from {} to {}",
+                address,
+                self.symbols[reloc.target_symbol].name,
+                self.symbols[target_symbol].name
+            );
+            reloc.target_symbol = target_symbol;
+            reloc.addend = addend;
+        }
+        Ok(updates.len())
+    }
+
+    /// Returns a section's data with every relocation applied against resolved symbol
+    /// addresses, as written to a linked DOL. `sda21` relocations are resolved against
+    /// `_SDA_BASE_` (r13) for `.sdata`/`.sbss` targets and `_SDA2_BASE_` (r2) for
+    /// `.sdata2`/`.sbss2` targets. Fails on relocations against other modules.
+    pub fn linked_section_bytes(&self, section_index: usize) -> Result<Vec<u8>> {
+        ensure!(self.kind == ObjKind::Executable, "Linking a relocatable object is not supported");
+        let section = self
+            .sections
+            .get(section_index)
+            .ok_or_else(|| anyhow!("Invalid section index {}", section_index))?;
+        let arch = self.architecture.arch();
+        let mut data = section.data.clone();
+        for (address, reloc) in section.relocations.iter() {
+            let location = self.address_format.address(section, address);
+            if matches!(reloc.module, Some(module_id) if module_id != self.module_id) {
+                bail!(
+                    "Unresolved external relocation {} @ {}",
+                    reloc.display_with(&self.symbols),
+                    location
+                );
+            }
+            let symbol = self.symbols.get(reloc.target_symbol).ok_or_else(|| {
+                anyhow!("Relocation @ {} targets missing symbol {}", location, reloc.target_symbol)
+            })?;
+            let target = reloc.target_address(symbol.address)?;
+            let offset = (address as u64).wrapping_sub(section.address) as usize;
+            ensure!(
+                offset.checked_add(4).is_some_and(|end| end <= data.len()),
+                "Relocation @ {} is outside of section data",
+                location
+            );
+            let bytes = array_ref_mut!(data, offset, 4);
+            if reloc.kind == ObjRelocKind::PpcEmbSda21 {
+                let section_name = symbol.section.map(|index| self.sections[index].name.as_str());
+                let (reg, base) = match section_name {
+                    Some(".sdata" | ".sbss") => (13, self.sda_base),
+                    Some(".sdata2" | ".sbss2") => (2, self.sda2_base),
+                    _ => (0, Some(0)),
+                };
+                let base = base.ok_or_else(|| {
+                    anyhow!("sda21 relocation @ {} requires small data base (r{})", location, reg)
+                })?;
+                let diff = target as i64 - base as i64;
+                ensure!(
+                    i16::try_from(diff).is_ok(),
+                    "sda21 relocation @ {} to {:#010X} out of range of r{}",
+                    location,
+                    target,
+                    reg
+                );
+                let ins = arch.read_u32(*bytes);
+                *bytes = arch.write_u32((ins & !0x1FFFFF) | (reg << 16) | (diff as u16 as u32));
+            } else {
+                reloc
+                    .apply(bytes, address, target, arch)
+                    .with_context(|| format!("Failed to apply relocation @ {}", location))?;
+            }
+        }
+        Ok(data)
+    }
+
+    /// Checks that each relocation site holds an instruction compatible with the relocation
+    /// kind (e.g. `rel24` on a `b`/`bl`), to catch mislabeled relocations from importers.
+    /// Relocations in code must also sit on an instruction boundary, and relocations in data
+    /// must respect the alignment of their kind.
+    pub fn verify_relocations(&self) -> Vec<RelocMismatch> {
+        self.sections
+            .iter()
+            .flat_map(|(section_index, section)| {
+                self.section_reloc_mismatches(section_index, section)
+            })
+            .collect()
+    }
+
+    pub(super) fn section_reloc_mismatches(
+        &self,
+        section_index: usize,
+        section: &ObjSection,
+    ) -> Vec<RelocMismatch> {
+        let arch = self.architecture.arch();
+        let mut mismatches = vec![];
+        for (address, reloc) in section.relocations.iter() {
+            let align = match section.kind {
+                ObjSectionKind::Code => arch.instruction_size() as u64,
+                _ => arch.reloc_alignment(reloc.kind) as u64,
+            };
+            let misaligned = (address as u64).wrapping_sub(section.address) % align != 0;
+            let instruction = section.read_word(address, arch).ok();
+            if misaligned
+                || !instruction.is_some_and(|ins| arch.reloc_matches_instruction(reloc.kind, ins))
+            {
+                mismatches.push(RelocMismatch {
+                    section: section_index,
+                    section_kind: section.kind,
+                    address,
+                    kind: reloc.kind,
+                    instruction,
+                    misaligned,
+                });
+            }
+        }
+        mismatches
+    }
+
+    /// Scans code for `r13`/`r2`-relative loads, stores and `addi`s, and adds `PpcEmbSda21`
+    /// relocations against the symbols they address via `_SDA_BASE_`/`_SDA2_BASE_`.
+    /// Instructions already covered by a relocation are skipped.
+    /// Returns the number of relocations added.
+    pub fn detect_sda_relocations(&mut self) -> Result<usize> {
+        let arch = self.architecture.arch();
+        let mut relocations = vec![];
+        for (section_index, section) in
+            self.sections.iter().filter(|(_, s)| s.kind == ObjSectionKind::Code)
+        {
+            for (i, word) in section.data.chunks_exact(4).enumerate() {
+                let address = section.address as u32 + i as u32 * 4;
+                if section.relocations.contains(address) {
+                    continue;
+                }
+                let ins = arch.read_u32(word.try_into().unwrap());
+                // addi, integer & float loads/stores
+                if !matches!(ins >> 26, 14 | 32..=55) {
+                    continue;
+                }
+                let base = match (ins >> 16) & 0x1F {
+                    13 => self.sda_base,
+                    2 => self.sda2_base,
+                    _ => None,
+                };
+                let Some(base) = base else {
+                    continue;
+                };
+                let target = base.wrapping_add(ins as i16 as u32);
+                let Ok((target_section, _)) = self.sections.at_address(target) else {
+                    continue;
+                };
+                let Some((target_symbol, symbol)) = self.symbols.for_relocation(
+                    SectionAddress::new(target_section, target),
+                    ObjRelocKind::PpcEmbSda21,
+                )?
+                else {
+                    continue;
+                };
+                relocations.push((section_index, address, ObjReloc {
+                    kind: ObjRelocKind::PpcEmbSda21,
+                    target_symbol,
+                    addend: target as i64 - symbol.address as i64,
+                    module: None,
+                }));
+            }
+        }
+        let count = relocations.len();
+        for (section_index, address, reloc) in relocations {
+            self.insert_relocation(section_index, address, reloc)?;
+        }
+        Ok(count)
+    }
+
+    /// Resolves each relocation in a section for display alongside disassembly, keyed by
+    /// address. Unlike [ObjRelocations], entries carry the target name and field mask.
+    pub fn relocation_overlay(&self, section_index: usize) -> BTreeMap<u32, RelocOverlay> {
+        let arch = self.architecture.arch();
+        self.relocations_in_order(section_index)
+            .map(|(address, reloc)| {
+                (address, RelocOverlay {
+                    kind: reloc.kind,
+                    target_symbol: reloc.target_symbol,
+                    target: self.symbols[reloc.target_symbol].name.clone(),
+                    addend: reloc.addend,
+                    module: reloc.module,
+                    field_mask: arch.reloc_field_mask(reloc.kind),
+                })
+            })
+            .collect()
+    }
+
+    /// Counts relocations (from any section) whose target lies within the given section.
+    /// In executables, the target address (symbol address + addend) is mapped to its section;
+    /// relocatable targets are classified by their symbol's section. Relocations against other
+    /// modules are excluded.
+    pub fn relocations_targeting_section(&self, section_index: usize) -> usize {
+        if self.sections.get(section_index).is_none() {
+            return 0;
+        }
+        self.sections
+            .iter()
+            .flat_map(|(_, section)| section.relocations.iter())
+            .filter(|(_, reloc)| match reloc.module {
+                Some(module_id) => module_id == self.module_id,
+                None => true,
+            })
+            .filter(|(_, reloc)| {
+                let symbol = &self.symbols[reloc.target_symbol];
+                if self.kind != ObjKind::Executable {
+                    return symbol.section == Some(section_index);
+                }
+                u32::try_from(symbol.address as i64 + reloc.addend)
+                    .ok()
+                    .and_then(|address| self.sections.at_address(address).ok())
+                    .is_some_and(|(index, _)| index == section_index)
+            })
+            .count()
+    }
+
+    /// Reports relocation counts and the largest relocation-free range per (non-BSS) section.
+    /// Code sections with a gap larger than `threshold` bytes are flagged, since they often
+    /// indicate data misidentified as code or missed references.
+    pub fn relocation_coverage_report(&self, threshold: u32) -> Vec<RelocCoverage> {
+        self.sections
+            .iter()
+            .filter(|(_, s)| s.kind != ObjSectionKind::Bss)
+            .map(|(section_index, section)| {
+                let start = section.address as u32;
+                let end = (section.address + section.size) as u32;
+                let mut largest_gap = start..start;
+                let mut last = start;
+                for address in section.relocations.iter().map(|(a, _)| a).chain([end]) {
+                    if address.saturating_sub(last) > largest_gap.len() as u32 {
+                        largest_gap = last..address;
+                    }
+                    last = address + 4;
+                }
+                RelocCoverage {
+                    section: section_index,
+                    relocations: section.relocations.len(),
+                    suspicious: section.kind == ObjSectionKind::Code
+                        && largest_gap.len() as u32 > threshold,
+                    largest_gap,
+                }
+            })
+            .collect()
+    }
+
+    /// Promotes local symbols referenced by relocations from another unit to global scope,
+    /// since the split objects wouldn't link otherwise. Units are determined by the splits
+    /// containing the relocation and the target symbol; unsplit addresses are ignored.
+    /// Returns the promoted symbols.
+    pub fn promote_cross_unit_symbols(&mut self) -> Result<Vec<SymbolIndex>> {
+        let unit_at = |section_index: usize, address: u32| {
+            self.sections
+                .get(section_index)
+                .and_then(|section| section.splits.for_address(address))
+                .map(|(_, split)| split.unit.as_str())
+        };
+        let mut promote = BTreeSet::new();
+        for (section_index, section) in self.sections.iter() {
+            for (address, reloc) in section.relocations.iter() {
+                if reloc.module.is_some() {
+                    continue;
+                }
+                let target = &self.symbols[reloc.target_symbol];
+                let Some(target_section) = target.section else {
+                    continue;
+                };
+                if target.flags.scope() != ObjSymbolScope::Local
+                    || promote.contains(&reloc.target_symbol)
+                {
+                    continue;
+                }
+                match (
+                    unit_at(section_index, address),
+                    unit_at(target_section, target.address as u32),
+                ) {
+                    (Some(source_unit), Some(target_unit)) if source_unit != target_unit => {
+                        log::info!(
+                            "Promoting {} ({}) to global: referenced from {} @ {:#010X}",
+                            target.name,
+                            target_unit,
+                            source_unit,
+                            address
+                        );
+                        promote.insert(reloc.target_symbol);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        for &symbol_idx in &promote {
+            let mut symbol = self.symbols[symbol_idx].clone();
+            symbol.flags.set_scope(ObjSymbolScope::Global);
+            self.symbols.replace(symbol_idx, symbol)?;
+        }
+        Ok(promote.into_iter().collect())
+    }
+
+    /// Renames every named, non-section symbol to `f(name)`, updating the name index.
+    /// Symbol indices, and therefore relocations, are unaffected. Name hashes of renamed
+    /// symbols are cleared.
+    pub fn apply_name_transform<F>(&mut self, f: F) -> Result<()>
+    where F: Fn(&str) -> String {
+        self.transform_symbol_names(|_| true, f)
+    }
+
+    /// Prefixes the names of all global symbols, e.g. to avoid collisions when linking
+    /// against the original object.
+    pub fn prefix_global_symbols(&mut self, prefix: &str) -> Result<()> {
+        self.transform_symbol_names(|s| s.flags.is_global(), |name| format!("{prefix}{name}"))
+    }
+
+    fn transform_symbol_names<P, F>(&mut self, predicate: P, f: F) -> Result<()>
+    where
+        P: Fn(&ObjSymbol) -> bool,
+        F: Fn(&str) -> String,
+    {
+        for symbol_index in 0..self.symbols.count() {
+            let symbol = &self.symbols[symbol_index];
+            if symbol.name.is_empty() || symbol.kind == ObjSymbolKind::Section || !predicate(symbol)
+            {
+                continue;
+            }
+            let name = f(&symbol.name);
+            if name != symbol.name {
+                let symbol = ObjSymbol { name, name_hash: None, ..symbol.clone() };
+                self.symbols.replace(symbol_index, symbol)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rewrites every symbol index referenced by the object (relocation targets) according
+    /// to `map`, after symbols have been reordered or removed. Fails without modifying the
+    /// object if a referenced index is missing from `map`, or if a resulting index is out
+    /// of bounds.
+    pub fn remap_symbol_indices(&mut self, map: &HashMap<SymbolIndex, SymbolIndex>) -> Result<()> {
+        let count = self.symbols.count();
+        for (_, section) in self.sections.iter() {
+            for (address, reloc) in section.relocations.iter() {
+                match map.get(&reloc.target_symbol) {
+                    Some(&new_index) if new_index < count => {}
+                    Some(&new_index) => bail!(
+                        "Relocation at {} remapped to invalid symbol index {}",
+                        self.address_format.address(section, address),
+                        new_index
+                    ),
+                    None => bail!(
+                        "Relocation at {} targets unmapped symbol index {}",
+                        self.address_format.address(section, address),
+                        reloc.target_symbol
+                    ),
+                }
+            }
+        }
+        for (_, section) in self.sections.iter_mut() {
+            for (_, reloc) in section.relocations.iter_mut() {
+                reloc.target_symbol = map[&reloc.target_symbol];
+            }
+        }
+        Ok(())
+    }
+
+    /// Rewrites every relocation targeting `from` to target `to`, returning the number of
+    /// relocations changed. With `adjust_addend`, addends are shifted by the difference in
+    /// symbol addresses so each relocation still resolves to the same address.
+    pub fn retarget_relocations(
+        &mut self,
+        from: SymbolIndex,
+        to: SymbolIndex,
+        adjust_addend: bool,
+    ) -> Result<usize> {
+        let count = self.symbols.count();
+        ensure!(from < count && to < count, "Invalid symbol index {} or {}", from, to);
+        let delta = if adjust_addend {
+            self.symbols[from].address as i64 - self.symbols[to].address as i64
+        } else {
+            0
+        };
+        let mut retargeted = 0;
+        for (_, section) in self.sections.iter_mut() {
+            for (_, reloc) in section.relocations.iter_mut() {
+                if reloc.target_symbol == from {
+                    reloc.target_symbol = to;
+                    reloc.addend += delta;
+                    retargeted += 1;
+                }
+            }
+        }
+        Ok(retargeted)
+    }
+
+    /// Returns the section symbol at the start of the given section, creating one if needed.
+    /// Relocations can target it with an addend to reference "section + offset" where no
+    /// named symbol exists yet.
+    pub fn section_anchor(&mut self, section_index: usize) -> Result<SymbolIndex> {
+        let section = self
+            .sections
+            .get(section_index)
+            .ok_or_else(|| anyhow!("Invalid section index {}", section_index))?;
+        if let Some((index, _)) = self.symbols.kind_at_section_address(
+            section_index,
+            section.address as u32,
+            ObjSymbolKind::Section,
+        )? {
+            return Ok(index);
+        }
+        let symbol = ObjSymbol {
+            name: section.name.clone(),
+            address: section.address,
+            section: Some(section_index),
+            size: section.size,
+            size_known: true,
+            flags: ObjSymbolFlagSet(ObjSymbolFlags::Local.into()),
+            kind: ObjSymbolKind::Section,
+            ..Default::default()
+        };
+        self.symbols.add_direct(symbol)
+    }
+
+    /// Resolves the `(section index, offset from section start)` a relocation targets,
+    /// regardless of whether a named symbol exists at the target. Returns `None` for
+    /// relocations against other modules, symbols without a section, or targets outside
+    /// of the target section.
+    pub fn section_relative_target(&self, reloc: &ObjReloc) -> Option<(usize, u32)> {
+        if matches!(reloc.module, Some(module_id) if module_id != self.module_id) {
+            return None;
+        }
+        let symbol = &self.symbols[reloc.target_symbol];
+        let section_index = symbol.section?;
+        let section = self.sections.get(section_index)?;
+        let address = (symbol.address as i64).checked_add(reloc.addend)?;
+        let offset = u64::try_from(address).ok()?.checked_sub(section.address)?;
+        (offset <= section.size).then_some((section_index, offset as u32))
+    }
+
+    /// Locate the relocation applying to the word containing `address`, along with the index
+    /// of the section containing it. Only valid for executable objects, where section
+    /// addresses are unique.
+    pub fn relocation_at(&self, address: u32) -> Option<(usize, &ObjReloc)> {
+        let (section_index, section) = self.sections.at_address(address).ok()?;
+        section.relocations.at(address & !3).map(|reloc| (section_index, reloc))
+    }
+
+    /// Classifies a relocation emitted from `source_unit` by the unit containing its target
+    /// symbol, as determined by split boundaries. Fails if the target isn't covered by a split.
+    pub fn classify_relocation(
+        &self,
+        reloc: &ObjReloc,
+        source_unit: &str,
+    ) -> Result<RelocClassification> {
+        let module_id = reloc.module.unwrap_or(self.module_id);
+        if module_id != self.module_id {
+            return Ok(RelocClassification::External(module_id));
+        }
+        let symbol = &self.symbols[reloc.target_symbol];
+        let Some(section_index) = symbol.section else {
+            return Ok(RelocClassification::External(module_id));
+        };
+        let section = &self.sections[section_index];
+        let address = symbol.address as u32;
+        let Some((_, split)) = section.splits.for_address(address) else {
+            bail!(
+                "Relocation target {} at {} is not covered by a split",
+                symbol.name,
+                self.address_format.address(section, address)
+            );
+        };
+        Ok(if split.unit == source_unit {
+            RelocClassification::Internal
+        } else {
+            RelocClassification::CrossUnit(split.unit.clone())
+        })
+    }
+
+    /// Locate all relocations targeting the given symbol, as `(section, address, kind)`.
+    pub fn xrefs_to(&self, index: SymbolIndex) -> Vec<(usize, u32, ObjRelocKind)> {
+        self.sections
+            .iter()
+            .flat_map(|(section_index, section)| {
+                section
+                    .relocations
+                    .iter()
+                    .filter(move |(_, reloc)| reloc.target_symbol == index)
+                    .map(move |(address, reloc)| (section_index, address, reloc.kind))
+            })
+            .collect()
+    }
+
+    /// Inserts a relocation into a section. A relocation claiming the current module as
+    /// its external module is reported and normalized to an internal one (`module: None`).
+    pub fn insert_relocation(
+        &mut self,
+        section_index: usize,
+        address: u32,
+        mut reloc: ObjReloc,
+    ) -> Result<()> {
+        let section = self
+            .sections
+            .get_mut(section_index)
+            .ok_or_else(|| anyhow!("Invalid section index {}", section_index))?;
+        if reloc.module == Some(self.module_id) {
+            log::warn!(
+                "Relocation @ {} targets its own module {} as external; treating as internal",
+                self.address_format.address(section, address),
+                self.module_id
+            );
+            reloc.module = None;
+        }
+        section.relocations.insert(address, reloc)?;
+        Ok(())
+    }
+
+    /// Removes relocations whose source address falls within
+    /// [blocked_relocation_sources](Self::blocked_relocation_sources), such as those imported
+    /// from an ELF or config rather than produced by analysis (which already respects it).
+    /// Returns the number of relocations removed.
+    pub fn prune_blocked_relocations(&mut self) -> usize {
+        let mut count = 0;
+        for (section_index, section) in self.sections.iter_mut() {
+            let blocked = section
+                .relocations
+                .iter()
+                .map(|(address, _)| address)
+                .filter(|&address| {
+                    self.blocked_relocation_sources
+                        .contains(SectionAddress::new(section_index, address))
+                })
+                .collect_vec();
+            for address in blocked {
+                let removed = section.relocations.remove_range(address..=address);
+                for (_, reloc) in removed {
+                    log::info!(
+                        "Pruning {:?} relocation at {} (blocked source)",
+                        reloc.kind,
+                        self.address_format.address(section, address)
+                    );
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// Locate relocations against external modules that aren't present in `loaded_modules`.
+    /// Relocations against this object's own module ID are always considered resolved.
+    pub fn unresolved_external_relocations(&self, loaded_modules: &[u32]) -> Vec<(u32, &ObjReloc)> {
+        self.sections
+            .iter()
+            .flat_map(|(_, section)| section.relocations.iter())
+            .filter(|(_, reloc)| match reloc.module {
+                Some(module_id) => {
+                    module_id != self.module_id && !loaded_modules.contains(&module_id)
+                }
+                None => false,
+            })
+            .collect()
+    }
+
+    /// Rewrites module IDs according to `map`: this object's own [module_id](Self::module_id),
+    /// the target module of every relocation, and all unresolved relocations. IDs missing from
+    /// `map` are left unchanged. Fails without modifying the object if two distinct modules
+    /// would end up with the same ID.
+    pub fn remap_module_ids(&mut self, map: &HashMap<u32, u32>) -> Result<()> {
+        let remap = |id: u32| map.get(&id).copied().unwrap_or(id);
+        let referenced = std::iter::once(self.module_id)
+            .chain(
+                self.sections
+                    .iter()
+                    .flat_map(|(_, s)| s.relocations.iter())
+                    .filter_map(|(_, reloc)| reloc.module),
+            )
+            .chain(self.unresolved_relocations.iter().map(|reloc| reloc.module_id))
+            .collect::<BTreeSet<u32>>();
+        let mut remapped = HashMap::<u32, u32>::new();
+        for id in referenced {
+            if let Some(existing) = remapped.insert(remap(id), id) {
+                bail!("Modules {} and {} both remap to module ID {}", existing, id, remap(id));
+            }
+        }
+
+        self.module_id = remap(self.module_id);
+        for (_, section) in self.sections.iter_mut() {
+            for (_, reloc) in section.relocations.iter_mut() {
+                if let Some(module_id) = &mut reloc.module {
+                    *module_id = remap(*module_id);
+                }
+            }
+        }
+        for reloc in &mut self.unresolved_relocations {
+            reloc.module_id = remap(reloc.module_id);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        obj::{test_obj, test_section, test_split, ObjSymbols},
+        util::rel::RelReloc,
+    };
+
+    #[test]
+    fn test_unresolved_external_relocations() {
+        let mut obj =
+            test_obj(vec![test_section(".data", ObjSectionKind::Data, 0x80000000, vec![0; 8])]);
+        obj.module_id = 1;
+        let relocations = &mut obj.sections[0].relocations;
+        relocations
+            .insert(0x80000000, ObjReloc {
+                kind: ObjRelocKind::Absolute,
+                target_symbol: 0,
+                addend: 0,
+                module: Some(0),
+            })
+            .unwrap();
+        relocations
+            .insert(0x80000004, ObjReloc {
+                kind: ObjRelocKind::Absolute,
+                target_symbol: 0,
+                addend: 0,
+                module: Some(2),
+            })
+            .unwrap();
+        let unresolved = obj.unresolved_external_relocations(&[0]);
+        assert_eq!(unresolved.len(), 1);
+        assert_eq!(unresolved[0].0, 0x80000004);
+        assert_eq!(unresolved[0].1.module, Some(2));
+    }
+
+    #[test]
+    fn test_classify_relocation() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x100])]);
+        obj.add_split(0, 0x80003100, test_split("a.cpp", 0x80003180)).unwrap();
+        obj.add_split(0, 0x80003180, test_split("b.cpp", 0x80003200)).unwrap();
+        let mut symbol = |name: &str, address, section| {
+            obj.symbols
+                .add_direct(ObjSymbol {
+                    name: name.to_string(),
+                    address,
+                    section,
+                    kind: ObjSymbolKind::Function,
+                    ..Default::default()
+                })
+                .unwrap()
+        };
+        let local = symbol("fn_80003120", 0x80003120, Some(0));
+        let remote = symbol("fn_80003180", 0x80003180, Some(0));
+        let abs = symbol("abs_symbol", 0x80000000, None);
+        let reloc = |target_symbol, module| ObjReloc {
+            kind: ObjRelocKind::PpcRel24,
+            target_symbol,
+            addend: 0,
+            module,
+        };
+
+        let classify = |reloc| obj.classify_relocation(&reloc, "a.cpp").unwrap();
+        assert_eq!(classify(reloc(local, None)), RelocClassification::Internal);
+        assert_eq!(
+            classify(reloc(remote, None)),
+            RelocClassification::CrossUnit("b.cpp".to_string())
+        );
+        assert_eq!(classify(reloc(remote, Some(2))), RelocClassification::External(2));
+        assert_eq!(classify(reloc(abs, None)), RelocClassification::External(0));
+    }
+
+    #[test]
+    fn test_insert_relocation() {
+        let mut obj =
+            test_obj(vec![test_section(".data", ObjSectionKind::Data, 0x80003100, vec![0; 0x10])]);
+        obj.module_id = 3;
+        let reloc =
+            |module| ObjReloc { kind: ObjRelocKind::Absolute, target_symbol: 0, addend: 0, module };
+        obj.insert_relocation(0, 0x80003100, reloc(Some(3))).unwrap();
+        obj.insert_relocation(0, 0x80003104, reloc(Some(1))).unwrap();
+        assert_eq!(obj.sections[0].relocations.at(0x80003100).unwrap().module, None);
+        assert_eq!(obj.sections[0].relocations.at(0x80003104).unwrap().module, Some(1));
+        assert!(obj.insert_relocation(0, 0x80003100, reloc(None)).is_err());
+        assert!(obj.insert_relocation(1, 0x80003100, reloc(None)).is_err());
+    }
+
+    #[test]
+    fn test_prune_blocked_relocations() {
+        let mut obj =
+            test_obj(vec![test_section(".data", ObjSectionKind::Data, 0x80003100, vec![0; 0x10])]);
+        for address in [0x80003100, 0x80003108] {
+            obj.sections[0]
+                .relocations
+                .insert(address, ObjReloc {
+                    kind: ObjRelocKind::Absolute,
+                    target_symbol: 0,
+                    addend: 0,
+                    module: None,
+                })
+                .unwrap();
+        }
+        obj.blocked_relocation_sources
+            .insert(SectionAddress::new(0, 0x80003108), SectionAddress::new(0, 0x80003110));
+
+        assert_eq!(obj.prune_blocked_relocations(), 1);
+        let addresses = obj.sections[0].relocations.iter().map(|(addr, _)| addr).collect_vec();
+        assert_eq!(addresses, vec![0x80003100]);
+        assert_eq!(obj.prune_blocked_relocations(), 0);
+    }
+
+    #[test]
+    fn test_remap_module_ids() {
+        let mut obj =
+            test_obj(vec![test_section(".data", ObjSectionKind::Data, 0x80000000, vec![0; 8])]);
+        obj.module_id = 1;
+        for (address, module) in [(0x80000000, 1), (0x80000004, 2)] {
+            obj.sections[0]
+                .relocations
+                .insert(address, ObjReloc {
+                    kind: ObjRelocKind::Absolute,
+                    target_symbol: 0,
+                    addend: 0,
+                    module: Some(module),
+                })
+                .unwrap();
+        }
+        obj.unresolved_relocations.push(RelReloc {
+            kind: ObjRelocKind::Absolute,
+            section: 0,
+            address: 0x4,
+            module_id: 2,
+            target_section: 1,
+            addend: 0,
+            original_section: 0,
+            original_target_section: 1,
+        });
+
+        // Module 2 would collide with module 1's new ID
+        let map = HashMap::from([(1, 2)]);
+        assert!(obj.remap_module_ids(&map).is_err());
+        assert_eq!(obj.module_id, 1);
+
+        let map = HashMap::from([(1, 10), (2, 20)]);
+        obj.remap_module_ids(&map).unwrap();
+        assert_eq!(obj.module_id, 10);
+        let modules =
+            obj.sections[0].relocations.iter().map(|(_, reloc)| reloc.module).collect_vec();
+        assert_eq!(modules, vec![Some(10), Some(20)]);
+        assert_eq!(obj.unresolved_relocations[0].module_id, 20);
+    }
+
+    #[test]
+    fn test_section_bytes_with_relocs_zeroed() {
+        let data = [0x3C608000u32, 0x38634560, 0x48001235, 0x60000000]
+            .iter()
+            .flat_map(|ins| ins.to_be_bytes())
+            .chain([0x80, 0x00, 0x31, 0x00])
+            .collect_vec();
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, data.clone())]);
+        for (address, kind) in [
+            (0x80003100, ObjRelocKind::PpcAddr16Ha),
+            (0x80003104, ObjRelocKind::PpcAddr16Lo),
+            (0x80003108, ObjRelocKind::PpcRel24),
+            (0x80003110, ObjRelocKind::Absolute),
+        ] {
+            obj.sections[0]
+                .relocations
+                .insert(address, ObjReloc { kind, target_symbol: 0, addend: 0, module: None })
+                .unwrap();
+        }
+        assert_eq!(obj.sections[0].raw_bytes(), data.as_slice());
+        let zeroed = obj.section_bytes_with_relocs_zeroed(0);
+        let words =
+            zeroed.chunks(4).map(|c| u32::from_be_bytes(c.try_into().unwrap())).collect_vec();
+        assert_eq!(words, vec![0x3C600000, 0x38630000, 0x48000001, 0x60000000, 0]);
+        // The section itself is unchanged
+        assert_eq!(obj.sections[0].data, data);
+    }
+
+    #[test]
+    fn test_remap_symbol_indices() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x10])]);
+        for (name, address) in [("fn_a", 0x80003100), ("fn_b", 0x80003104), ("fn_c", 0x80003108)] {
+            obj.add_symbol(
+                ObjSymbol {
+                    name: name.to_string(),
+                    address,
+                    section: Some(0),
+                    kind: ObjSymbolKind::Function,
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+        }
+        for (address, target_symbol) in [(0x80003100, 2), (0x80003104, 0)] {
+            obj.sections[0]
+                .relocations
+                .insert(address, ObjReloc {
+                    kind: ObjRelocKind::PpcRel24,
+                    target_symbol,
+                    addend: 0,
+                    module: None,
+                })
+                .unwrap();
+        }
+
+        // Compact away fn_b
+        let symbols = obj.symbols.iter().filter(|s| s.name != "fn_b").cloned().collect_vec();
+        obj.symbols = ObjSymbols::new(obj.kind, symbols);
+        obj.remap_symbol_indices(&HashMap::from([(0, 0), (2, 1)])).unwrap();
+        let targets = obj.sections[0]
+            .relocations
+            .iter()
+            .map(|(_, r)| obj.symbols[r.target_symbol].name.as_str())
+            .collect_vec();
+        assert_eq!(targets, vec!["fn_c", "fn_a"]);
+
+        assert!(obj.remap_symbol_indices(&HashMap::from([(0, 5), (1, 1)])).is_err());
+        // fn_c is unmapped
+        assert!(obj.remap_symbol_indices(&HashMap::from([(0, 0)])).is_err());
+        assert_eq!(obj.sections[0].relocations.at(0x80003100).unwrap().target_symbol, 1);
+    }
+
+    #[test]
+    fn test_relocations_targeting_section() {
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x20]),
+            test_section(".rodata", ObjSectionKind::ReadOnlyData, 0x80004000, vec![0; 0x10]),
+            test_section(".data", ObjSectionKind::Data, 0x80005000, vec![0; 0x10]),
+        ]);
+        let rodata = obj
+            .add_symbol(
+                ObjSymbol {
+                    name: "lbl_80004000".to_string(),
+                    address: 0x80004000,
+                    section: Some(1),
+                    kind: ObjSymbolKind::Object,
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+        let data = obj
+            .add_symbol(
+                ObjSymbol {
+                    name: "lbl_80005000".to_string(),
+                    address: 0x80005000,
+                    section: Some(2),
+                    kind: ObjSymbolKind::Object,
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+        // Absolute symbol pointing into .rodata
+        let abs = obj
+            .add_symbol(
+                ObjSymbol {
+                    name: "abs_80004008".to_string(),
+                    address: 0x80004008,
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+        for (address, target_symbol) in
+            [(0x80003100, rodata), (0x80003104, rodata), (0x80003108, data), (0x8000310C, abs)]
+        {
+            obj.sections[0]
+                .relocations
+                .insert(address, ObjReloc {
+                    kind: ObjRelocKind::PpcAddr16Lo,
+                    target_symbol,
+                    addend: 0,
+                    module: None,
+                })
+                .unwrap();
+        }
+        obj.sections[2]
+            .relocations
+            .insert(0x80005008, ObjReloc {
+                kind: ObjRelocKind::Absolute,
+                target_symbol: rodata,
+                addend: 4,
+                module: None,
+            })
+            .unwrap();
+        // Addend moves the target out of the symbol's section
+        obj.sections[2]
+            .relocations
+            .insert(0x8000500C, ObjReloc {
+                kind: ObjRelocKind::Absolute,
+                target_symbol: rodata,
+                addend: 0x1004,
+                module: None,
+            })
+            .unwrap();
+        assert_eq!(obj.relocations_targeting_section(1), 4);
+        assert_eq!(obj.relocations_targeting_section(2), 2);
+        assert_eq!(obj.relocations_targeting_section(0), 0);
+        assert_eq!(obj.relocations_targeting_section(3), 0);
+    }
+
+    #[test]
+    fn test_retarget_relocations() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x20])]);
+        let mut symbols = vec![];
+        for (name, address) in [("lbl_80003110", 0x80003110), ("fn_80003100", 0x80003100)] {
+            symbols.push(
+                obj.add_symbol(
+                    ObjSymbol {
+                        name: name.to_string(),
+                        address,
+                        section: Some(0),
+                        ..Default::default()
+                    },
+                    false,
+                )
+                .unwrap(),
+            );
+        }
+        let (from, to) = (symbols[0], symbols[1]);
+        for (address, target_symbol, addend) in
+            [(0x80003100, from, 0), (0x80003104, from, 4), (0x80003108, to, 0)]
+        {
+            obj.sections[0]
+                .relocations
+                .insert(address, ObjReloc {
+                    kind: ObjRelocKind::PpcAddr16Lo,
+                    target_symbol,
+                    addend,
+                    module: None,
+                })
+                .unwrap();
+        }
+        assert_eq!(obj.retarget_relocations(from, to, true).unwrap(), 2);
+        assert!(obj.xrefs_to(from).is_empty());
+        assert_eq!(obj.xrefs_to(to).len(), 3);
+        let addends = obj.sections[0].relocations.iter().map(|(_, r)| r.addend).collect_vec();
+        assert_eq!(addends, vec![0x10, 0x14, 0]);
+
+        assert_eq!(obj.retarget_relocations(to, from, false).unwrap(), 3);
+        assert_eq!(obj.sections[0].relocations.at(0x80003100).unwrap().addend, 0x10);
+        assert!(obj.retarget_relocations(from, 5, false).is_err());
+    }
+
+    #[test]
+    fn test_relocation_at() {
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x10]),
+            test_section(".data", ObjSectionKind::Data, 0x80004000, vec![0; 0x10]),
+        ]);
+        for (section_index, address, kind) in
+            [(0, 0x80003108, ObjRelocKind::PpcRel24), (1, 0x80004004, ObjRelocKind::Absolute)]
+        {
+            obj.sections[section_index]
+                .relocations
+                .insert(address, ObjReloc { kind, target_symbol: 0, addend: 0, module: None })
+                .unwrap();
+        }
+        let kind_at = |address| obj.relocation_at(address).map(|(i, r)| (i, r.kind));
+        assert_eq!(kind_at(0x80003108), Some((0, ObjRelocKind::PpcRel24)));
+        assert_eq!(kind_at(0x8000310A), Some((0, ObjRelocKind::PpcRel24)));
+        assert_eq!(kind_at(0x80004004), Some((1, ObjRelocKind::Absolute)));
+        assert_eq!(kind_at(0x80003104), None);
+        assert_eq!(kind_at(0x80005000), None);
+    }
+
+    #[test]
+    fn test_relocation_overlay() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x10])]);
+        let target = obj
+            .add_symbol(
+                ObjSymbol {
+                    name: "lbl_80004000".to_string(),
+                    address: 0x80004000,
+                    kind: ObjSymbolKind::Object,
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+        for (address, kind, addend) in
+            [(0x80003108, ObjRelocKind::PpcAddr16Lo, 8), (0x80003100, ObjRelocKind::PpcAddr16Ha, 8)]
+        {
+            obj.sections[0]
+                .relocations
+                .insert(address, ObjReloc { kind, target_symbol: target, addend, module: None })
+                .unwrap();
+        }
+        let overlay = obj.relocation_overlay(0);
+        assert_eq!(overlay.keys().copied().collect_vec(), vec![0x80003100, 0x80003108]);
+        assert_eq!(overlay[&0x80003100], RelocOverlay {
+            kind: ObjRelocKind::PpcAddr16Ha,
+            target_symbol: target,
+            target: "lbl_80004000".to_string(),
+            addend: 8,
+            module: None,
+            field_mask: 0xFFFF,
+        });
+        assert_eq!(overlay[&0x80003108].kind, ObjRelocKind::PpcAddr16Lo);
+        assert!(!overlay.contains_key(&0x80003104));
+    }
+
+    #[test]
+    fn test_section_relative_target() {
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x10]),
+            test_section(".rodata", ObjSectionKind::ReadOnlyData, 0x80004000, vec![0; 0x40]),
+        ]);
+        let anchor = obj.section_anchor(1).unwrap();
+        assert_eq!(obj.section_anchor(1).unwrap(), anchor);
+        assert_eq!(obj.symbols[anchor].kind, ObjSymbolKind::Section);
+        assert_eq!(obj.symbols[anchor].address, 0x80004000);
+
+        let reloc = |target_symbol, addend| ObjReloc {
+            kind: ObjRelocKind::PpcAddr16Lo,
+            target_symbol,
+            addend,
+            module: None,
+        };
+        // No named symbol exists at .rodata+0x18
+        assert_eq!(obj.symbols.at_section_address(1, 0x80004018).count(), 0);
+        assert_eq!(obj.section_relative_target(&reloc(anchor, 0x18)), Some((1, 0x18)));
+
+        let named = obj
+            .add_symbol(
+                ObjSymbol {
+                    name: "lbl_80004020".to_string(),
+                    address: 0x80004020,
+                    section: Some(1),
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+        assert_eq!(obj.section_relative_target(&reloc(named, 4)), Some((1, 0x24)));
+        assert_eq!(obj.section_relative_target(&reloc(anchor, 0x100)), None);
+        assert_eq!(obj.section_relative_target(&reloc(anchor, -4)), None);
+        let external = ObjReloc { module: Some(2), ..reloc(anchor, 0) };
+        assert_eq!(obj.section_relative_target(&external), None);
+    }
+
+    #[test]
+    fn test_relocations_in_order() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x20])]);
+        for (address, target_symbol) in [(0x80003118, 1), (0x80003100, 0), (0x8000310C, 1)] {
+            obj.sections[0]
+                .relocations
+                .insert(address, ObjReloc {
+                    kind: ObjRelocKind::PpcRel24,
+                    target_symbol,
+                    addend: 0,
+                    module: None,
+                })
+                .unwrap();
+        }
+        let addresses = obj.relocations_in_order(0).map(|(addr, _)| addr).collect_vec();
+        assert_eq!(addresses, vec![0x80003100, 0x8000310C, 0x80003118]);
+        assert!(addresses.windows(2).all(|w| w[0] < w[1]));
+
+        let by_target = obj.relocations_by_target(0);
+        assert_eq!(by_target.keys().copied().collect_vec(), vec![0, 1]);
+        assert_eq!(by_target[&1].iter().map(|(addr, _)| *addr).collect_vec(), vec![
+            0x8000310C, 0x80003118
+        ]);
+    }
+
+    #[test]
+    fn test_sections_equal_ignoring_relocs() {
+        let build = |instructions: [u32; 4]| {
+            let data = instructions.iter().flat_map(|ins| ins.to_be_bytes()).collect_vec();
+            let mut obj =
+                test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, data)]);
+            for (address, kind) in [
+                (0x80003100, ObjRelocKind::PpcAddr16Ha),
+                (0x80003104, ObjRelocKind::PpcAddr16Lo),
+                (0x80003108, ObjRelocKind::PpcRel24),
+            ] {
+                obj.sections[0]
+                    .relocations
+                    .insert(address, ObjReloc { kind, target_symbol: 0, addend: 0, module: None })
+                    .unwrap();
+            }
+            obj
+        };
+        // lis r3, x@ha; addi r3, r3, x@l; bl fn; blr
+        let left = build([0x3C608000, 0x38631234, 0x48000001, 0x4E800020]);
+        let right = build([0x3C608001, 0x38635678, 0x48001235, 0x4E800020]);
+        assert!(left.sections_equal_ignoring_relocs(&right, 0));
+        // lis r4, x@ha (register differs)
+        let right = build([0x3C808001, 0x38635678, 0x48001235, 0x4E800020]);
+        assert!(!left.sections_equal_ignoring_relocs(&right, 0));
+        assert!(!left.sections_equal_ignoring_relocs(&right, 1));
+    }
+
+    #[test]
+    fn test_reresolve_relocations() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x100])]);
+        let anchor = obj
+            .symbols
+            .add_direct(ObjSymbol {
+                name: ".text".to_string(),
+                address: 0x80003100,
+                section: Some(0),
+                kind: ObjSymbolKind::Section,
+                ..Default::default()
+            })
+            .unwrap();
+        obj.sections[0]
+            .relocations
+            .insert(0x80003110, ObjReloc {
+                kind: ObjRelocKind::PpcRel24,
+                target_symbol: anchor,
+                addend: 0x80,
+                module: None,
+            })
+            .unwrap();
+        assert_eq!(obj.reresolve_relocations().unwrap(), 0);
+
+        let function = obj
+            .symbols
+            .add_direct(ObjSymbol {
+                name: "fn_80003180".to_string(),
+                address: 0x80003180,
+                section: Some(0),
+                size: 0x20,
+                size_known: true,
+                kind: ObjSymbolKind::Function,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(obj.reresolve_relocations().unwrap(), 1);
+        let reloc = obj.sections[0].relocations.at(0x80003110).unwrap();
+        assert_eq!((reloc.target_symbol, reloc.addend), (function, 0));
+        assert_eq!(obj.reresolve_relocations().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_linked_section_bytes() {
+        let mut text = vec![0; 0x10];
+        // lis r3, 0; addi r3, r3, 0; bl 0; lwz r3, 0(r13)
+        for (i, ins) in [0x3C600000u32, 0x38630000, 0x48000001, 0x806D0000].into_iter().enumerate()
+        {
+            text[i * 4..i * 4 + 4].copy_from_slice(&ins.to_be_bytes());
+        }
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, text),
+            test_section(".data", ObjSectionKind::Data, 0x8000C000, vec![0; 0x20]),
+            test_section(".sdata", ObjSectionKind::Data, 0x8000D000, vec![0; 0x20]),
+        ]);
+        obj.sda_base = Some(0x80014FF0);
+        let mut symbol = |name: &str, section, address| {
+            obj.symbols
+                .add_direct(ObjSymbol {
+                    name: name.to_string(),
+                    address,
+                    section: Some(section),
+                    ..Default::default()
+                })
+                .unwrap()
+        };
+        let function = symbol("fn_80003100", 0, 0x80003100);
+        let data = symbol("lbl_8000C000", 1, 0x8000C000);
+        let sdata = symbol("lbl_8000D000", 2, 0x8000D000);
+        for (address, kind, target_symbol, addend) in [
+            (0x80003100, ObjRelocKind::PpcAddr16Ha, data, 0x10),
+            (0x80003104, ObjRelocKind::PpcAddr16Lo, data, 0x10),
+            (0x80003108, ObjRelocKind::PpcRel24, function, 0),
+            (0x8000310C, ObjRelocKind::PpcEmbSda21, sdata, 8),
+        ] {
+            obj.sections[0]
+                .relocations
+                .insert(address, ObjReloc { kind, target_symbol, addend, module: None })
+                .unwrap();
+        }
+
+        let linked = obj.linked_section_bytes(0).unwrap();
+        let words = linked.chunks_exact(4).map(|w| u32::from_be_bytes(w.try_into().unwrap()));
+        assert_eq!(words.collect_vec(), vec![0x3C608001, 0x3863C010, 0x4BFFFFF9, 0x806D8018]);
+        // Section data is left untouched
+        assert_eq!(obj.sections[0].data[..4], [0x3C, 0x60, 0x00, 0x00]);
+
+        obj.sections[0].relocations.at_mut(0x80003108).unwrap().module = Some(1);
+        assert!(obj.linked_section_bytes(0).is_err());
+    }
+
+    #[test]
+    fn test_relocation_addend_audit() {
+        let mut obj =
+            test_obj(vec![test_section(".data", ObjSectionKind::Data, 0x80003100, vec![0; 0x100])]);
+        let mut object = |name: &str, address| {
+            obj.symbols
+                .add_direct(ObjSymbol {
+                    name: name.to_string(),
+                    address,
+                    section: Some(0),
+                    size: 8,
+                    size_known: true,
+                    kind: ObjSymbolKind::Object,
+                    ..Default::default()
+                })
+                .unwrap()
+        };
+        let near = object("lbl_80003100", 0x80003100);
+        let far = object("lbl_80003180", 0x80003180);
+        for (address, addend) in [(0x80003110, 4), (0x80003114, 0x84)] {
+            obj.sections[0]
+                .relocations
+                .insert(address, ObjReloc {
+                    kind: ObjRelocKind::Absolute,
+                    target_symbol: near,
+                    addend,
+                    module: None,
+                })
+                .unwrap();
+        }
+
+        let audit = obj.relocation_addend_audit().unwrap();
+        assert_eq!(audit, vec![AddendSuggestion {
+            section: 0,
+            address: 0x80003114,
+            target_symbol: near,
+            addend: 0x84,
+            suggestion: Some((far, 4)),
+        }]);
+        // Reported only; the relocation is unchanged
+        assert_eq!(obj.sections[0].relocations.at(0x80003114).unwrap().target_symbol, near);
+    }
+
+    #[test]
+    fn test_prefix_global_symbols() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x10])]);
+        let mut symbol = |name: &str, address, flags: ObjSymbolFlags| {
+            obj.symbols
+                .add_direct(ObjSymbol {
+                    name: name.to_string(),
+                    address,
+                    section: Some(0),
+                    flags: ObjSymbolFlagSet(flags.into()),
+                    kind: ObjSymbolKind::Function,
+                    ..Default::default()
+                })
+                .unwrap()
+        };
+        let main = symbol("main", 0x80003100, ObjSymbolFlags::Global);
+        let helper = symbol("helper", 0x80003108, ObjSymbolFlags::Local);
+        obj.sections[0]
+            .relocations
+            .insert(0x80003104, ObjReloc {
+                kind: ObjRelocKind::PpcRel24,
+                target_symbol: main,
+                addend: 0,
+                module: None,
+            })
+            .unwrap();
+
+        obj.prefix_global_symbols("orig_").unwrap();
+        assert_eq!(obj.symbols[main].name, "orig_main");
+        assert_eq!(obj.symbols[helper].name, "helper");
+        assert!(obj.symbols.by_name("main").unwrap().is_none());
+        let (index, _) = obj.symbols.by_name("orig_main").unwrap().unwrap();
+        let target = obj.sections[0].relocations.at(0x80003104).unwrap().target_symbol;
+        assert_eq!(target, index);
+
+        obj.apply_name_transform(|name| name.to_ascii_uppercase()).unwrap();
+        assert!(obj.symbols.by_name("HELPER").unwrap().is_some());
+        assert_eq!(obj.symbols[target].name, "ORIG_MAIN");
+    }
+
+    #[test]
+    fn test_xrefs_to() {
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x20]),
+            test_section(".data", ObjSectionKind::Data, 0x80004000, vec![0; 0x20]),
+        ]);
+        let target = obj
+            .add_symbol(
+                ObjSymbol {
+                    name: "fn_target".to_string(),
+                    address: 0x80003110,
+                    section: Some(0),
+                    size: 0x10,
+                    size_known: true,
+                    kind: ObjSymbolKind::Function,
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+        let reloc = |kind, target_symbol| ObjReloc { kind, target_symbol, addend: 0, module: None };
+        obj.sections[0]
+            .relocations
+            .insert(0x80003104, reloc(ObjRelocKind::PpcRel24, target))
+            .unwrap();
+        obj.sections[1]
+            .relocations
+            .insert(0x80004008, reloc(ObjRelocKind::Absolute, target))
+            .unwrap();
+        obj.sections[1]
+            .relocations
+            .insert(0x8000400C, reloc(ObjRelocKind::Absolute, target + 1))
+            .unwrap();
+
+        assert_eq!(obj.xrefs_to(target), vec![
+            (0, 0x80003104, ObjRelocKind::PpcRel24),
+            (1, 0x80004008, ObjRelocKind::Absolute),
+        ]);
+    }
+
+    #[test]
+    fn test_verify_relocations() {
+        let data = vec![
+            0x48, 0x00, 0x00, 0x01, // bl
+            0x3C, 0x60, 0x80, 0x00, // lis r3
+        ];
+        let mut obj = test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, data)]);
+        let reloc = |kind| ObjReloc { kind, target_symbol: 0, addend: 0, module: None };
+        obj.sections[0].relocations.insert(0x80003100, reloc(ObjRelocKind::PpcRel24)).unwrap();
+        obj.sections[0].relocations.insert(0x80003104, reloc(ObjRelocKind::PpcAddr16Lo)).unwrap();
+
+        assert_eq!(obj.verify_relocations(), vec![RelocMismatch {
+            section: 0,
+            section_kind: ObjSectionKind::Code,
+            address: 0x80003104,
+            kind: ObjRelocKind::PpcAddr16Lo,
+            instruction: Some(0x3C608000),
+            misaligned: false,
+        }]);
+    }
+
+    #[test]
+    fn test_verify_relocations_alignment() {
+        let data = vec![
+            0x48, 0x00, 0x00, 0x01, // bl
+            0x48, 0x00, 0x00, 0x01, // bl
+        ];
+        // Misaligned section starts put the relocations mid-word
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003102, data),
+            test_section(".data", ObjSectionKind::Data, 0x80003202, vec![0; 0x8]),
+        ]);
+        let reloc = |kind| ObjReloc { kind, target_symbol: 0, addend: 0, module: None };
+        obj.sections[0].relocations.insert(0x80003104, reloc(ObjRelocKind::PpcRel24)).unwrap();
+        obj.sections[1].relocations.insert(0x80003204, reloc(ObjRelocKind::Absolute)).unwrap();
+
+        assert_eq!(obj.verify_relocations(), vec![RelocMismatch {
+            section: 0,
+            section_kind: ObjSectionKind::Code,
+            address: 0x80003104,
+            kind: ObjRelocKind::PpcRel24,
+            instruction: Some(0x00014800),
+            misaligned: true,
+        }]);
+    }
+
+    #[test]
+    fn test_detect_sda_relocations() {
+        let data = vec![
+            0x80, 0x6D, 0x80, 0x10, // lwz r3, -0x7FF0(r13)
+            0x80, 0x01, 0x00, 0x14, // lwz r0, 0x14(r1)
+            0x4E, 0x80, 0x00, 0x20, // blr
+        ];
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, data),
+            test_section(".sdata", ObjSectionKind::Data, 0x80400000, vec![0; 0x20]),
+        ]);
+        obj.sda_base = Some(0x80408000);
+        let target = obj
+            .add_symbol(
+                ObjSymbol {
+                    name: "lbl_80400010".to_string(),
+                    address: 0x80400010,
+                    section: Some(1),
+                    size: 4,
+                    size_known: true,
+                    kind: ObjSymbolKind::Object,
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap();
+        assert_eq!(obj.detect_sda_relocations().unwrap(), 1);
+
+        let relocations = obj.sections[0].relocations.iter().collect_vec();
+        assert_eq!(relocations.len(), 1);
+        let (address, reloc) = relocations[0];
+        assert_eq!(address, 0x80003100);
+        assert_eq!(reloc.kind, ObjRelocKind::PpcEmbSda21);
+        assert_eq!(reloc.target_symbol, target);
+        assert_eq!(reloc.addend, 0);
+        // Already covered
+        assert_eq!(obj.detect_sda_relocations().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_relocation_coverage_report() {
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x100]),
+            test_section(".text2", ObjSectionKind::Code, 0x80003200, vec![0; 0x100]),
+        ]);
+        let reloc =
+            || ObjReloc { kind: ObjRelocKind::PpcRel24, target_symbol: 0, addend: 0, module: None };
+        for address in (0x80003100..0x80003200).step_by(0x20) {
+            obj.sections[0].relocations.insert(address, reloc()).unwrap();
+        }
+        obj.sections[1].relocations.insert(0x80003200, reloc()).unwrap();
+
+        let report = obj.relocation_coverage_report(0x40);
+        assert_eq!(report, vec![
+            RelocCoverage {
+                section: 0,
+                relocations: 8,
+                largest_gap: 0x80003104..0x80003120,
+                suspicious: false,
+            },
+            RelocCoverage {
+                section: 1,
+                relocations: 1,
+                largest_gap: 0x80003204..0x80003300,
+                suspicious: true,
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_promote_cross_unit_symbols() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x30])]);
+        let mut add_function = |name: &str, address: u64, scope: ObjSymbolFlags| {
+            obj.add_symbol(
+                ObjSymbol {
+                    name: name.to_string(),
+                    address,
+                    section: Some(0),
+                    size: 0x10,
+                    size_known: true,
+                    flags: ObjSymbolFlagSet(scope.into()),
+                    kind: ObjSymbolKind::Function,
+                    ..Default::default()
+                },
+                false,
+            )
+            .unwrap()
+        };
+        let helper = add_function("helper", 0x80003100, ObjSymbolFlags::Local);
+        let private = add_function("private", 0x80003110, ObjSymbolFlags::Local);
+        let caller = add_function("caller", 0x80003120, ObjSymbolFlags::Global);
+        obj.add_split(0, 0x80003100, test_split("a.c", 0x80003120)).unwrap();
+        obj.add_split(0, 0x80003120, test_split("b.c", 0x80003130)).unwrap();
+        for (address, target_symbol) in [(0x80003110, helper), (0x80003124, helper)] {
+            obj.sections[0]
+                .relocations
+                .insert(address, ObjReloc {
+                    kind: ObjRelocKind::PpcRel24,
+                    target_symbol,
+                    addend: 0,
+                    module: None,
+                })
+                .unwrap();
+        }
+
+        assert_eq!(obj.promote_cross_unit_symbols().unwrap(), vec![helper]);
+        assert_eq!(obj.symbols[helper].flags.scope(), ObjSymbolScope::Global);
+        assert_eq!(obj.symbols[private].flags.scope(), ObjSymbolScope::Local);
+        assert_eq!(obj.symbols[caller].flags.scope(), ObjSymbolScope::Global);
+        assert!(obj.promote_cross_unit_symbols().unwrap().is_empty());
+    }
+}
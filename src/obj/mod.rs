@@ -1,4 +1,10 @@
 mod addresses;
+mod analysis;
+mod arch;
+mod cache;
+mod diff;
+mod layout;
+mod linking;
 mod relocations;
 mod sections;
 mod splits;
@@ -6,24 +12,37 @@ mod symbols;
 
 use std::{
     cmp::{max, min},
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, HashMap},
     hash::Hash,
+    io::{BufRead, Read, Seek, Write},
+    ops::Range,
 };
 
+pub use analysis::{Diagnostic, DiagnosticCategory, SectionAnalysis, Severity, ValidationLevel};
 use anyhow::{anyhow, bail, ensure, Result};
+pub use arch::{Architecture, PowerPc};
+pub use diff::{ObjDiff, ObjSectionDiff};
+pub use layout::{SnapDirection, StripOptions, UnitSizes};
+pub use linking::{
+    AddendSuggestion, RelocClassification, RelocCoverage, RelocMismatch, RelocOverlay,
+};
 use objdiff_core::obj::split_meta::SplitMeta;
-pub use relocations::{ObjReloc, ObjRelocKind, ObjRelocations};
+pub use relocations::{
+    join_ha_lo, split_ha_lo, NamedReloc, ObjReloc, ObjRelocKind, ObjRelocations,
+    RelocationRangeError,
+};
 pub use sections::{ObjSection, ObjSectionKind, ObjSections};
+use serde::{Deserialize, Serialize};
 pub use splits::{ObjSplit, ObjSplits};
 pub use symbols::{
-    best_match_for_reloc, ObjDataKind, ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind,
-    ObjSymbolScope, ObjSymbols, SymbolIndex,
+    best_match_for_reloc, disambiguate_demangled_names, ObjDataKind, ObjSymbol, ObjSymbolFlagSet,
+    ObjSymbolFlags, ObjSymbolKind, ObjSymbolScope, ObjSymbols, SymbolIndex, SymbolMergePolicy,
 };
 
 use crate::{
     analysis::cfa::SectionAddress,
     obj::addresses::AddressRanges,
-    util::{comment::MWComment, rel::RelReloc},
+    util::{comment::MWComment, config, lcf, rel::RelReloc},
 };
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -39,6 +58,14 @@ pub enum ObjArchitecture {
     PowerPc,
 }
 
+impl ObjArchitecture {
+    pub fn arch(self) -> &'static dyn Architecture {
+        match self {
+            ObjArchitecture::PowerPc => &PowerPc,
+        }
+    }
+}
+
 /// Translation unit information.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct ObjUnit {
@@ -49,6 +76,63 @@ pub struct ObjUnit {
     pub comment_version: Option<u8>,
 }
 
+/// How addresses are formatted in diagnostics.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum AddressFormat {
+    /// Section name followed by the absolute address, e.g. `.text 0x80003220`.
+    #[default]
+    Absolute,
+    /// Offset from the start of the section, e.g. `.text+0x120`.
+    /// More readable for relocatable modules, where absolute addresses are meaningless.
+    SectionRelative,
+}
+
+impl AddressFormat {
+    pub fn address(self, section: &ObjSection, address: u32) -> String {
+        match self {
+            AddressFormat::Absolute => format!("{} {:#010X}", section.name, address),
+            AddressFormat::SectionRelative => {
+                format!("{}{}", section.name, Self::offset(section, address))
+            }
+        }
+    }
+
+    pub fn range(self, section: &ObjSection, start: u32, end: u32) -> String {
+        match self {
+            AddressFormat::Absolute => format!("{} {:#010X}-{:#010X}", section.name, start, end),
+            AddressFormat::SectionRelative => format!(
+                "{}{}-{}",
+                section.name,
+                Self::offset(section, start),
+                Self::offset(section, end).trim_start_matches('+')
+            ),
+        }
+    }
+
+    fn offset(section: &ObjSection, address: u32) -> String {
+        let offset = address as i64 - section.address as i64;
+        if offset < 0 {
+            format!("-{:#X}", -offset)
+        } else {
+            format!("+{:#X}", offset)
+        }
+    }
+}
+
+/// Naming scheme for units created by split analysis (e.g. from `.ctors` and `extabindex`
+/// entries). Both are deterministic for identical input; a numeric suffix is appended when
+/// a name is already taken.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoUnitNaming {
+    /// `auto_{symbol}_{section}`, from the unit's first function. (e.g. `auto_foo_text`)
+    #[default]
+    Symbol,
+    /// `auto_{section index}_{address}_{section}`, as used for gap splits.
+    /// (e.g. `auto_01_80003100_text`) Stable across symbol renames.
+    Address,
+}
+
 #[derive(Debug, Clone)]
 pub struct ObjInfo {
     pub kind: ObjKind,
@@ -81,6 +165,11 @@ pub struct ObjInfo {
     /// Module ID (0 for main)
     pub module_id: u32,
     pub unresolved_relocations: Vec<RelReloc>,
+
+    /// Address formatting used in diagnostics.
+    pub address_format: AddressFormat,
+    /// Naming scheme for autogenerated units.
+    pub auto_unit_naming: AutoUnitNaming,
 }
 
 impl ObjInfo {
@@ -113,9 +202,24 @@ impl ObjInfo {
             known_functions: Default::default(),
             module_id: 0,
             unresolved_relocations: vec![],
+            address_format: AddressFormat::Absolute,
+            auto_unit_naming: AutoUnitNaming::Symbol,
         }
     }
 
+    /// Reads an analyzed object previously written with [ObjInfo::write_cache].
+    pub fn read_cache<R: Read + Seek + ?Sized>(r: &mut R) -> Result<Self> { cache::read_cache(r) }
+
+    /// Writes a GNU ld linker script for the object. See [lcf::write_gnu_ldscript].
+    pub fn write_linker_script<W: Write + ?Sized>(&self, w: &mut W) -> Result<()> {
+        lcf::write_gnu_ldscript(w, self)
+    }
+
+    /// Writes the full analyzed state to a versioned binary cache.
+    pub fn write_cache<W: Write + ?Sized>(&self, w: &mut W) -> Result<()> {
+        cache::write_cache(self, w)
+    }
+
     pub fn add_symbol(&mut self, in_symbol: ObjSymbol, replace: bool) -> Result<SymbolIndex> {
         match in_symbol.name.as_str() {
             "_SDA_BASE_" => self.sda_base = Some(in_symbol.address as u32),
@@ -130,7 +234,90 @@ impl ObjInfo {
         self.symbols.add(in_symbol, replace)
     }
 
+    /// Returns the symbol of the given kind at the address, creating an auto-named one
+    /// (`fn_` for functions, `lbl_` otherwise) if none exists. When `kind` is
+    /// [ObjSymbolKind::Unknown], any existing non-section symbol at the address matches.
+    pub fn ensure_symbol_at(
+        &mut self,
+        address: SectionAddress,
+        kind: ObjSymbolKind,
+    ) -> Result<SymbolIndex> {
+        ensure!(
+            self.sections.get(address.section).is_some_and(|s| s.contains(address.address)),
+            "Address {:#010X} outside of section {}",
+            address.address,
+            address.section
+        );
+        if let Some((index, _)) =
+            self.symbols.kind_at_section_address(address.section, address.address, kind)?
+        {
+            return Ok(index);
+        }
+        if kind == ObjSymbolKind::Unknown {
+            if let Some((index, _)) = self
+                .symbols
+                .at_section_address(address.section, address.address)
+                .find(|(_, s)| s.kind != ObjSymbolKind::Section)
+            {
+                return Ok(index);
+            }
+        }
+        self.add_symbol(
+            ObjSymbol {
+                name: self.auto_symbol_name(address, kind),
+                address: address.address as u64,
+                section: Some(address.section),
+                kind,
+                ..Default::default()
+            },
+            false,
+        )
+    }
+
+    /// Generates a name for an auto-created symbol: `fn_` for functions, `lbl_` otherwise.
+    fn auto_symbol_name(&self, address: SectionAddress, kind: ObjSymbolKind) -> String {
+        let prefix = if kind == ObjSymbolKind::Function { "fn" } else { "lbl" };
+        if self.module_id == 0 {
+            format!("{}_{:08X}", prefix, address.address)
+        } else {
+            format!(
+                "{}_{}_{}_{:X}",
+                prefix,
+                self.module_id,
+                self.sections[address.section].name.trim_start_matches('.'),
+                address.address
+            )
+        }
+    }
+
     pub fn add_split(&mut self, section_index: usize, address: u32, split: ObjSplit) -> Result<()> {
+        self.add_split_inner(section_index, address, split).map(|_| ())
+    }
+
+    /// Adds a batch of splits, returning the number that were skipped because an existing
+    /// split for the same unit already covered them.
+    pub fn add_splits<I>(&mut self, splits: I) -> Result<usize>
+    where I: IntoIterator<Item = (SectionAddress, ObjSplit)> {
+        let mut covered = 0;
+        for (addr, split) in splits {
+            if self.add_split_inner(addr.section, addr.address, split)? {
+                covered += 1;
+            }
+        }
+        if covered > 0 {
+            log::debug!("Skipped {} splits already covered by existing splits", covered);
+        }
+        Ok(covered)
+    }
+
+    /// Returns `true` if the split was already covered by an existing split for the same unit.
+    fn add_split_inner(
+        &mut self,
+        section_index: usize,
+        address: u32,
+        split: ObjSplit,
+    ) -> Result<bool> {
+        let fmt = self.address_format;
         let section = self
             .sections
             .get_mut(section_index)
@@ -139,13 +326,10 @@ impl ObjInfo {
         let section_end = (section.address + section.size) as u32;
         ensure!(
             split.end == 0 || split.end <= section_end,
-            "Split {} {:#010X}-{:#010X} is outside section {} {:#010X}-{:#010X}",
+            "Split {} {} is outside section {}",
             split.unit,
-            address,
-            split.end,
-            section.name,
-            section_start,
-            section_end
+            fmt.range(section, address, split.end),
+            fmt.range(section, section_start, section_end)
         );
 
         if let Some((existing_addr, existing_split)) = section.splits.for_unit(&split.unit)? {
@@ -157,11 +341,9 @@ impl ObjInfo {
                 (Some(a), Some(b)) if a == b => Some(a),
                 (Some(a), Some(b)) => {
                     bail!(
-                        "Conflicting alignment for split {} {} {:#010X}-{:#010X}: {:#X} != {:#X}",
+                        "Conflicting alignment for split {} {}: {:#X} != {:#X}",
                         split.unit,
-                        section.name,
-                        existing_addr,
-                        existing_split.end,
+                        fmt.range(section, existing_addr, existing_split.end),
                         a,
                         b
                     );
@@ -171,26 +353,40 @@ impl ObjInfo {
                 _ => None,
             };
 
+            // Preserve a pending section rename from either split
+            let new_rename = match (&split.rename, &existing_split.rename) {
+                (Some(a), Some(b)) if a != b => {
+                    bail!(
+                        "Conflicting rename for split {} {}: {} != {}",
+                        split.unit,
+                        fmt.range(section, existing_addr, existing_split.end),
+                        a,
+                        b
+                    );
+                }
+                (Some(a), _) => Some(a.clone()),
+                (_, b) => b.clone(),
+            };
+
             // TODO don't merge if common flag is different?
             ensure!(
                 split.common == existing_split.common,
-                "Conflicting common flag for split {} {} {:#010X}-{:#010X} ({}) and {:#010X}-{:#010X} ({})",
+                "Conflicting common flag for split {} {} ({}) and {} ({})",
                 split.unit,
-                section.name,
-                existing_addr,
-                existing_split.end,
+                fmt.range(section, existing_addr, existing_split.end),
                 existing_split.common,
-                address,
-                split.end,
+                fmt.range(section, address, split.end),
                 split.common
             );
 
             // Only set autogenerated flag if both splits are autogenerated
             let new_autogenerated = split.autogenerated && existing_split.autogenerated;
+            // Only skip the merged split if both splits are skipped
+            let new_skip = split.skip && existing_split.skip;
 
             // If the new split is contained within the existing split, do nothing
             if new_start >= existing_addr && new_end <= existing_split.end {
-                log::debug!(
+                log::trace!(
                     "Split {} {} {:#010X}-{:#010X} already covers {:#010X}-{:#010X}",
                     split.unit,
                     section.name,
@@ -199,7 +395,7 @@ impl ObjInfo {
                     address,
                     split.end
                 );
-                return Ok(());
+                return Ok(true);
             }
 
             log::debug!(
@@ -227,7 +423,7 @@ impl ObjInfo {
                         existing_addr,
                         existing_split.end
                     );
-                    return Ok(());
+                    return Ok(false);
                 }
 
                 ensure!(
@@ -277,21 +473,20 @@ impl ObjInfo {
                     existing.unit.clone_from(&split.unit);
                 }
             }
-            self.add_split(section_index, new_start, ObjSplit {
+            return self.add_split_inner(section_index, new_start, ObjSplit {
                 unit: split.unit,
                 end: new_end,
                 align: new_align,
                 common: split.common,
                 autogenerated: new_autogenerated,
-                skip: false,  // ?
-                rename: None, // ?
-            })?;
-            return Ok(());
+                skip: new_skip,
+                rename: new_rename,
+            });
         }
 
         log::debug!("Adding split @ {} {:#010X}: {:?}", section.name, address, split);
         section.splits.push(address, split);
-        Ok(())
+        Ok(false)
     }
 
     pub fn is_unit_autogenerated(&self, unit: &str) -> bool {
@@ -301,21 +496,40 @@ impl ObjInfo {
             .all(|(_, _, _, split)| split.autogenerated)
     }
 
-    /// Calculate the total size of all code sections.
+    /// Records a unit imported from a relocatable object, taking its `comment_version` from
+    /// the object's `.comment` section. Objects without one record version 0, so none is
+    /// emitted for the unit when splitting. Updates the existing [ObjUnit] if present.
+    pub fn import_unit(&mut self, unit_obj: &ObjInfo) {
+        let comment_version = Some(unit_obj.mw_comment.as_ref().map_or(0, |c| c.version));
+        if let Some(unit) = self.link_order.iter_mut().find(|u| u.name == unit_obj.name) {
+            unit.comment_version = comment_version;
+        } else {
+            self.link_order.push(ObjUnit {
+                name: unit_obj.name.clone(),
+                autogenerated: false,
+                comment_version,
+            });
+        }
+    }
+
+    /// Calculate the total size of all code sections, excluding skipped splits.
     pub fn code_size(&self) -> u32 {
+        let skipped = self.skipped_ranges();
         self.sections
             .iter()
             .filter(|(_, section)| section.kind == ObjSectionKind::Code)
-            .map(|(_, section)| section.size as u32)
+            .map(|(idx, section)| section.size as u32 - skipped_size(&skipped, idx))
             .sum()
     }
 
-    /// Calculate the total size of all data sections, including common BSS symbols.
+    /// Calculate the total size of all data sections, including common BSS symbols
+    /// and excluding skipped splits.
     pub fn data_size(&self) -> u32 {
+        let skipped = self.skipped_ranges();
         self.sections
             .iter()
             .filter(|(_, section)| section.kind != ObjSectionKind::Code)
-            .map(|(_, section)| section.size as u32)
+            .map(|(idx, section)| section.size as u32 - skipped_size(&skipped, idx))
             .chain(
                 // Include common symbols
                 self.symbols
@@ -325,4 +539,538 @@ impl ObjInfo {
             )
             .sum()
     }
+
+    /// Address ranges of splits marked `skip`, by section index.
+    /// Skipped splits aren't emitted as part of any unit, but their bytes remain in the
+    /// section, and are expected to be covered by another unit or padding at link time.
+    pub fn skipped_ranges(&self) -> Vec<(usize, Range<u32>)> {
+        self.sections
+            .iter()
+            .flat_map(|(section_index, section)| {
+                let section_end = (section.address + section.size) as u32;
+                section.splits.iter().filter(|(_, split)| split.skip).map(move |(addr, split)| {
+                    let end = if split.end == 0 { section_end } else { split.end };
+                    (section_index, addr..end)
+                })
+            })
+            .collect()
+    }
+
+    /// Validates split section renames (e.g. `.ctors$10`) before emitting split objects.
+    /// Renames matching the original section name are cleared. Errors if a rename would
+    /// produce two sections with the same name within one unit.
+    pub fn apply_split_renames(&mut self) -> Result<()> {
+        let mut unit_sections = HashMap::<String, HashMap<String, (usize, String)>>::new();
+        for (section_index, section) in self.sections.iter_mut() {
+            for (addr, split) in section.splits.iter_mut() {
+                if split.rename.as_deref() == Some(section.name.as_str()) {
+                    split.rename = None;
+                }
+                let out_name = split.rename.as_ref().unwrap_or(&section.name);
+                let sections = unit_sections.entry(split.unit.clone()).or_default();
+                match sections.get(out_name) {
+                    Some((other_index, other_name)) if *other_index != section_index => {
+                        bail!(
+                            "Split {} {} {:#010X}-{:#010X} renamed to {} collides with section {}",
+                            split.unit,
+                            section.name,
+                            addr,
+                            split.end,
+                            out_name,
+                            other_name
+                        );
+                    }
+                    Some(_) => {}
+                    None => {
+                        sections.insert(out_name.clone(), (section_index, section.name.clone()));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the split layout in the splits config format (`splits.txt`).
+    /// Units containing only autogenerated splits are omitted unless `all` is set.
+    pub fn write_splits<W>(&self, w: &mut W, all: bool) -> Result<()>
+    where W: Write + ?Sized {
+        config::write_splits(w, self, all)
+    }
+
+    /// Reads a splits config (`splits.txt`), adding each split with [`ObjInfo::add_split`].
+    /// A split's `rename` is kept as the section name override for its unit, and `skip`
+    /// excludes the split from output.
+    pub fn read_splits<R>(&mut self, r: &mut R) -> Result<()>
+    where R: BufRead + ?Sized {
+        config::read_splits(r, self)
+    }
+
+    /// Compares this object against another build of the same object.
+    /// Symbols are matched by name and sections by name.
+    pub fn diff(&self, other: &ObjInfo) -> ObjDiff { diff::diff_objs(self, other) }
+
+    /// Returns the index of the section containing a symbol.
+    /// Common and absolute symbols have no owning section and return `None`.
+    pub fn section_of_symbol(&self, index: SymbolIndex) -> Option<usize> {
+        let symbol = self.symbols.get(index)?;
+        if symbol.flags.is_common() {
+            return None;
+        }
+        symbol.section.filter(|&section_index| self.sections.get(section_index).is_some())
+    }
+
+    /// Iterate over function symbols in address order, per [ObjSymbols::iter_ordered].
+    pub fn functions(&self) -> impl DoubleEndedIterator<Item = (SymbolIndex, &ObjSymbol)> {
+        self.symbols.iter_ordered().filter(|(_, s)| s.kind == ObjSymbolKind::Function)
+    }
+
+    /// Iterate over data (object) symbols in address order, per [ObjSymbols::iter_ordered].
+    pub fn data_symbols(&self) -> impl DoubleEndedIterator<Item = (SymbolIndex, &ObjSymbol)> {
+        self.symbols.iter_ordered().filter(|(_, s)| s.kind == ObjSymbolKind::Object)
+    }
+}
+
+fn skipped_size(skipped: &[(usize, Range<u32>)], section_index: usize) -> u32 {
+    skipped
+        .iter()
+        .filter(|(idx, _)| *idx == section_index)
+        .map(|(_, range)| range.len() as u32)
+        .sum()
+}
+
+/// Creates a known, 4-byte aligned section sized to `data`, for tests.
+#[cfg(test)]
+pub(crate) fn test_section(
+    name: &str,
+    kind: ObjSectionKind,
+    address: u64,
+    data: Vec<u8>,
+) -> ObjSection {
+    ObjSection {
+        name: name.to_string(),
+        kind,
+        address,
+        size: data.len() as u64,
+        data,
+        align: 4,
+        elf_index: 0,
+        relocations: Default::default(),
+        virtual_address: None,
+        file_offset: 0,
+        section_known: true,
+        splits: Default::default(),
+        compressed_size_cache: Default::default(),
+    }
+}
+
+/// Creates a split of `unit` ending at `end`, for tests.
+#[cfg(test)]
+pub(crate) fn test_split(unit: &str, end: u32) -> ObjSplit {
+    ObjSplit {
+        unit: unit.to_string(),
+        end,
+        align: None,
+        common: false,
+        autogenerated: false,
+        skip: false,
+        rename: None,
+    }
+}
+
+/// Creates a user-defined unit, for tests.
+#[cfg(test)]
+pub(crate) fn test_unit(name: &str) -> ObjUnit {
+    ObjUnit { name: name.to_string(), autogenerated: false, comment_version: None }
+}
+
+/// Creates an empty executable with the given sections, for tests.
+#[cfg(test)]
+pub(crate) fn test_obj(sections: Vec<ObjSection>) -> ObjInfo {
+    ObjInfo::new(
+        ObjKind::Executable,
+        ObjArchitecture::PowerPc,
+        "test".to_string(),
+        vec![],
+        sections,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+
+    #[test]
+    fn test_functions_data_symbols() {
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x100]),
+            test_section(".data", ObjSectionKind::Data, 0x80003200, vec![0; 0x100]),
+        ]);
+        for (name, section, address, kind) in [
+            ("fn_80003180", 0, 0x80003180, ObjSymbolKind::Function),
+            ("lbl_80003210", 1, 0x80003210, ObjSymbolKind::Object),
+            ("fn_80003100", 0, 0x80003100, ObjSymbolKind::Function),
+            ("lbl_80003140", 0, 0x80003140, ObjSymbolKind::Unknown),
+            ("lbl_80003200", 1, 0x80003200, ObjSymbolKind::Object),
+        ] {
+            obj.symbols
+                .add_direct(ObjSymbol {
+                    name: name.to_string(),
+                    address,
+                    section: Some(section),
+                    kind,
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        let names = |iter: Vec<(SymbolIndex, &ObjSymbol)>| {
+            iter.into_iter().map(|(_, s)| s.name.clone()).collect_vec()
+        };
+        assert_eq!(names(obj.functions().collect()), vec!["fn_80003100", "fn_80003180"]);
+        assert_eq!(names(obj.data_symbols().collect()), vec!["lbl_80003200", "lbl_80003210"]);
+    }
+
+    #[test]
+    fn test_add_splits_covered() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x100])]);
+        obj.add_split(0, 0x80003100, test_split("a.cpp", 0x80003180)).unwrap();
+        let redundant =
+            [(0x80003100, 0x80003110), (0x80003120, 0x80003180), (0x80003140, 0x80003150)]
+                .map(|(start, end)| (SectionAddress::new(0, start), test_split("a.cpp", end)));
+        assert_eq!(obj.add_splits(redundant).unwrap(), 3);
+        assert_eq!(obj.sections[0].splits.iter().count(), 1);
+
+        let extending = [(SectionAddress::new(0, 0x80003180), test_split("a.cpp", 0x80003200))];
+        assert_eq!(obj.add_splits(extending).unwrap(), 0);
+        let (addr, split) = obj.sections[0].splits.iter().next().unwrap();
+        assert_eq!((addr, split.end), (0x80003100, 0x80003200));
+    }
+
+    #[test]
+    fn test_section_of_symbol() {
+        let mut obj = test_obj(vec![
+            test_section(".init", ObjSectionKind::Code, 0x80003100, vec![0; 0x100]),
+            test_section(".text", ObjSectionKind::Code, 0x80003200, vec![0; 0x100]),
+        ]);
+        let function = obj
+            .symbols
+            .add_direct(ObjSymbol {
+                name: "fn_80003240".to_string(),
+                address: 0x80003240,
+                section: Some(1),
+                kind: ObjSymbolKind::Function,
+                ..Default::default()
+            })
+            .unwrap();
+        let common = obj
+            .symbols
+            .add_direct(ObjSymbol {
+                name: "lbl_common".to_string(),
+                size: 4,
+                flags: ObjSymbolFlagSet(ObjSymbolFlags::Common.into()),
+                kind: ObjSymbolKind::Object,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(obj.section_of_symbol(function), Some(1));
+        assert_eq!(obj.section_of_symbol(common), None);
+        assert_eq!(obj.section_of_symbol(common + 1), None);
+    }
+
+    #[test]
+    fn test_write_splits_round_trip() {
+        let sections = || {
+            vec![
+                test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x100]),
+                test_section(".data", ObjSectionKind::Data, 0x80003200, vec![0; 0x100]),
+            ]
+        };
+        let mut obj = test_obj(sections());
+        obj.link_order.push(test_unit("a.cpp"));
+        obj.link_order.push(test_unit("b.cpp"));
+        obj.add_split(0, 0x80003100, test_split("a.cpp", 0x80003180)).unwrap();
+        obj.add_split(0, 0x80003180, test_split("b.cpp", 0x80003200)).unwrap();
+        obj.add_split(1, 0x80003200, ObjSplit {
+            rename: Some(".data$10".to_string()),
+            ..test_split("a.cpp", 0x80003240)
+        })
+        .unwrap();
+        obj.add_split(1, 0x80003240, ObjSplit { skip: true, ..test_split("b.cpp", 0x80003300) })
+            .unwrap();
+
+        let mut buf = Vec::new();
+        obj.write_splits(&mut buf, false).unwrap();
+        let mut other = test_obj(sections());
+        config::apply_splits(&mut buf.as_slice(), &mut other).unwrap();
+
+        assert_eq!(other.link_order, obj.link_order);
+        for (section_index, section) in obj.sections.iter() {
+            let expected = section.splits.iter().map(|(a, s)| (a, s.clone())).collect_vec();
+            let actual = other.sections[section_index]
+                .splits
+                .iter()
+                .map(|(a, s)| (a, s.clone()))
+                .collect_vec();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    fn read_splits_obj(splits: &str) -> Result<ObjInfo> {
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x100]),
+            test_section(".data", ObjSectionKind::Data, 0x80003200, vec![0; 0x100]),
+        ]);
+        obj.read_splits(&mut splits.as_bytes())?;
+        Ok(obj)
+    }
+
+    #[test]
+    fn test_read_splits() {
+        let obj = read_splits_obj(
+            "Sections:
+    .text type:code align:4
+    .data type:data align:8
+
+a.cpp:
+    .text start:0x80003100 end:0x80003180
+    .data start:0x80003200 end:0x80003240 rename:.data$10
+
+b.cpp:
+    .text start:0x80003180 end:0x80003200
+    .data start:0x80003240 end:0x80003300 skip
+",
+        )
+        .unwrap();
+        assert_eq!(obj.link_order, vec![test_unit("a.cpp"), test_unit("b.cpp")]);
+        assert_eq!(
+            obj.sections[0].splits.for_address(0x80003180),
+            Some((0x80003180, &test_split("b.cpp", 0x80003200)))
+        );
+        let (_, split) = obj.sections[1].splits.for_address(0x80003200).unwrap();
+        assert_eq!(split.rename.as_deref(), Some(".data$10"));
+        let (_, split) = obj.sections[1].splits.for_address(0x80003240).unwrap();
+        assert!(split.skip);
+    }
+
+    #[test]
+    fn test_read_splits_overlap() {
+        let err = read_splits_obj(
+            "a.cpp:
+    .text start:0x80003100 end:0x80003140
+b.cpp:
+    .text start:0x80003140 end:0x80003180
+a.cpp:
+    .text start:0x80003180 end:0x800031C0
+",
+        )
+        .unwrap_err();
+        let message = format!("{err:#}");
+        assert!(message.contains("line 6"), "{message}");
+        assert!(message.contains("overlaps existing split"), "{message}");
+    }
+
+    #[test]
+    fn test_read_splits_conflicting_align() {
+        let err = read_splits_obj(
+            "a.cpp:
+    .text start:0x80003100 end:0x80003140 align:8
+    .text start:0x80003140 end:0x80003180 align:16
+",
+        )
+        .unwrap_err();
+        let message = format!("{err:#}");
+        assert!(message.contains("line 3"), "{message}");
+        assert!(message.contains("Conflicting alignment"), "{message}");
+    }
+
+    #[test]
+    fn test_read_splits_missing_section() {
+        let err = read_splits_obj(
+            "a.cpp:
+    .bss start:0x80003100 end:0x80003140
+",
+        )
+        .unwrap_err();
+        let message = format!("{err:#}");
+        assert!(message.contains("line 2"), "{message}");
+        assert!(message.contains("Section .bss not found"), "{message}");
+    }
+
+    #[test]
+    fn test_split_rename() {
+        let mut obj =
+            test_obj(vec![test_section(".ctors", ObjSectionKind::ReadOnlyData, 0x80003100, vec![
+                0, 0, 0, 1, 0, 0, 0, 2,
+            ])]);
+        obj.link_order.push(test_unit("a.cpp"));
+        obj.add_split(0, 0x80003100, test_split("a.cpp", 0x80003104)).unwrap();
+        // Merging into the existing split keeps the pending rename
+        obj.add_split(0, 0x80003104, ObjSplit {
+            rename: Some(".ctors$10".to_string()),
+            ..test_split("a.cpp", 0x80003108)
+        })
+        .unwrap();
+        obj.apply_split_renames().unwrap();
+
+        let objects = crate::util::split::split_obj(&obj, None).unwrap();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].name, "a.cpp");
+        assert_eq!(objects[0].sections[0].name, ".ctors$10");
+    }
+
+    #[test]
+    fn test_split_rename_collision() {
+        let mut obj = test_obj(vec![
+            test_section(".data", ObjSectionKind::Data, 0x80003100, vec![0; 0x10]),
+            test_section(".sdata", ObjSectionKind::Data, 0x80003110, vec![0; 0x10]),
+        ]);
+        obj.add_split(0, 0x80003100, test_split("a.cpp", 0x80003110)).unwrap();
+        obj.add_split(1, 0x80003110, ObjSplit {
+            rename: Some(".data".to_string()),
+            ..test_split("a.cpp", 0x80003120)
+        })
+        .unwrap();
+        assert!(obj.apply_split_renames().is_err());
+    }
+
+    #[test]
+    fn test_split_skip() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![
+                0x60;
+                0x100
+            ])]);
+        obj.link_order.push(test_unit("a.cpp"));
+        obj.link_order.push(test_unit("b.cpp"));
+        obj.add_split(0, 0x80003100, test_split("a.cpp", 0x80003180)).unwrap();
+        obj.add_split(0, 0x80003180, ObjSplit { skip: true, ..test_split("b.cpp", 0x80003200) })
+            .unwrap();
+
+        assert_eq!(obj.skipped_ranges(), vec![(0, 0x80003180..0x80003200)]);
+        assert_eq!(obj.code_size(), 0x80);
+
+        let objects = crate::util::split::split_obj(&obj, None).unwrap();
+        assert_eq!(objects[0].sections.len(), 1);
+        assert_eq!(objects[0].sections[0].size, 0x80);
+        assert!(objects[1].sections.is_empty());
+        assert_eq!(obj.sections[0].data, vec![0x60; 0x100]);
+    }
+
+    #[test]
+    fn test_import_unit_comment_version() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x30])]);
+        obj.mw_comment = Some(MWComment::new(10).unwrap());
+        obj.link_order.push(test_unit("a.c"));
+        obj.add_split(0, 0x80003100, test_split("a.c", 0x80003110)).unwrap();
+        obj.add_split(0, 0x80003110, test_split("b.c", 0x80003120)).unwrap();
+        obj.add_split(0, 0x80003120, test_split("c.s", 0x80003130)).unwrap();
+
+        for (name, version) in [("a.c", Some(8)), ("b.c", Some(14)), ("c.s", None)] {
+            let mut unit_obj = ObjInfo::new(
+                ObjKind::Relocatable,
+                ObjArchitecture::PowerPc,
+                name.to_string(),
+                vec![],
+                vec![],
+            );
+            unit_obj.mw_comment = version.map(|v| MWComment::new(v).unwrap());
+            obj.import_unit(&unit_obj);
+        }
+        assert_eq!(obj.link_order.iter().map(|u| u.comment_version).collect_vec(), vec![
+            Some(8),
+            Some(14),
+            Some(0)
+        ]);
+
+        let objects = crate::util::split::split_obj(&obj, None).unwrap();
+        let versions =
+            objects.iter().map(|o| o.mw_comment.as_ref().map(|c| c.version)).collect_vec();
+        assert_eq!(versions, vec![Some(8), Some(14), None]);
+    }
+
+    #[test]
+    fn test_address_format() {
+        let mut obj =
+            test_obj(vec![test_section(".text", ObjSectionKind::Code, 0, vec![0; 0x200])]);
+        obj.kind = ObjKind::Relocatable;
+        obj.module_id = 1;
+        let section = &obj.sections[0];
+        assert_eq!(AddressFormat::Absolute.address(section, 0x120), ".text 0x00000120");
+        assert_eq!(AddressFormat::SectionRelative.address(section, 0x120), ".text+0x120");
+        assert_eq!(
+            AddressFormat::SectionRelative.range(section, 0x120, 0x140),
+            ".text+0x120-0x140"
+        );
+
+        obj.address_format = AddressFormat::SectionRelative;
+        let err = obj.add_split(0, 0x100, test_split("a.cpp", 0x300)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Split a.cpp .text+0x100-0x300 is outside section .text+0x0-0x200"
+        );
+    }
+
+    #[test]
+    fn test_ensure_symbol_at() {
+        let mut obj = test_obj(vec![
+            test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x10]),
+            test_section(".data", ObjSectionKind::Data, 0x80004000, vec![0; 0x10]),
+        ]);
+        let addr = SectionAddress::new(0, 0x80003104);
+        let index = obj.ensure_symbol_at(addr, ObjSymbolKind::Function).unwrap();
+        assert_eq!(obj.ensure_symbol_at(addr, ObjSymbolKind::Function).unwrap(), index);
+        assert_eq!(obj.ensure_symbol_at(addr, ObjSymbolKind::Unknown).unwrap(), index);
+        assert_eq!(obj.symbols[index].name, "fn_80003104");
+        assert_eq!(obj.symbols.count(), 1);
+
+        let data = SectionAddress::new(1, 0x80004008);
+        let object = obj.ensure_symbol_at(data, ObjSymbolKind::Object).unwrap();
+        assert_ne!(object, index);
+        assert_eq!(obj.symbols[object].name, "lbl_80004008");
+        assert_eq!(obj.ensure_symbol_at(data, ObjSymbolKind::Object).unwrap(), object);
+        assert_eq!(obj.symbols.count(), 2);
+
+        assert!(obj
+            .ensure_symbol_at(SectionAddress::new(1, 0x80004010), ObjSymbolKind::Object)
+            .is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let build = |fn_b: [u8; 8]| {
+            let mut data = vec![0x60, 0, 0, 0, 0x4E, 0x80, 0x00, 0x20];
+            data.extend_from_slice(&fn_b);
+            let mut obj =
+                test_obj(vec![test_section(".text", ObjSectionKind::Code, 0x80003100, data)]);
+            for (name, address) in [("fn_a", 0x80003100), ("fn_b", 0x80003108)] {
+                obj.add_symbol(
+                    ObjSymbol {
+                        name: name.to_string(),
+                        address,
+                        section: Some(0),
+                        size: 8,
+                        size_known: true,
+                        kind: ObjSymbolKind::Function,
+                        ..Default::default()
+                    },
+                    false,
+                )
+                .unwrap();
+            }
+            obj
+        };
+        let left = build([0x38, 0x60, 0x00, 0x00, 0x4E, 0x80, 0x00, 0x20]);
+        let right = build([0x38, 0x60, 0x00, 0x01, 0x4E, 0x80, 0x00, 0x20]);
+
+        assert!(left.diff(&left).is_empty());
+        let diff = left.diff(&right);
+        assert!(diff.added_symbols.is_empty());
+        assert!(diff.removed_symbols.is_empty());
+        assert_eq!(diff.changed_symbols, vec!["fn_b".to_string()]);
+        assert_eq!(diff.sections.len(), 1);
+        assert_eq!(diff.sections[0].changed_ranges, vec![0x80003108..0x8000310C]);
+    }
 }
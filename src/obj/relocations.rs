@@ -2,14 +2,17 @@ use std::{
     collections::{btree_map, BTreeMap},
     error::Error,
     fmt,
-    ops::RangeBounds,
+    ops::{Bound, RangeBounds},
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, ensure, Result};
 use object::elf;
 use serde::{Deserialize, Serialize};
 
-use crate::obj::SymbolIndex;
+use crate::{
+    array_ref,
+    obj::{Architecture, ObjSymbolKind, ObjSymbols, PowerPc, SymbolIndex},
+};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum ObjRelocKind {
@@ -22,10 +25,37 @@ pub enum ObjRelocKind {
     PpcEmbSda21,
 }
 
-impl Serialize for ObjRelocKind {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where S: serde::Serializer {
-        serializer.serialize_str(match self {
+impl ObjRelocKind {
+    /// Branch target relocations. (`rel24`, `rel14`)
+    pub fn is_branch(self) -> bool {
+        matches!(self, ObjRelocKind::PpcRel24 | ObjRelocKind::PpcRel14)
+    }
+
+    /// Relocations writing one half of an address. (`hi`, `ha`, `l`)
+    pub fn is_halfword(self) -> bool {
+        matches!(
+            self,
+            ObjRelocKind::PpcAddr16Hi | ObjRelocKind::PpcAddr16Ha | ObjRelocKind::PpcAddr16Lo
+        )
+    }
+
+    /// Relocations relative to the relocation address.
+    pub fn is_pc_relative(self) -> bool { self.is_branch() }
+
+    /// The symbol kind this relocation is expected to target, if any: branches target
+    /// functions and `sda21` targets objects. `abs` is excluded, since pointers in data
+    /// (vtables, jump tables) commonly target functions and labels.
+    pub fn expected_target_kind(self) -> Option<ObjSymbolKind> {
+        match self {
+            ObjRelocKind::PpcRel24 | ObjRelocKind::PpcRel14 => Some(ObjSymbolKind::Function),
+            ObjRelocKind::PpcEmbSda21 => Some(ObjSymbolKind::Object),
+            _ => None,
+        }
+    }
+
+    /// Short name, as used in configuration files. (e.g. `rel24`)
+    pub fn name(self) -> &'static str {
+        match self {
             ObjRelocKind::Absolute => "abs",
             ObjRelocKind::PpcAddr16Hi => "hi",
             ObjRelocKind::PpcAddr16Ha => "ha",
@@ -33,8 +63,34 @@ impl Serialize for ObjRelocKind {
             ObjRelocKind::PpcRel24 => "rel24",
             ObjRelocKind::PpcRel14 => "rel14",
             ObjRelocKind::PpcEmbSda21 => "sda21",
+        }
+    }
+
+    /// Maps an ELF `R_PPC_*` relocation type, returning `None` for unsupported types.
+    pub fn from_elf(r_type: u32) -> Option<ObjRelocKind> {
+        Some(match r_type {
+            elf::R_PPC_ADDR32 | elf::R_PPC_UADDR32 => ObjRelocKind::Absolute,
+            elf::R_PPC_ADDR16_LO => ObjRelocKind::PpcAddr16Lo,
+            elf::R_PPC_ADDR16_HI => ObjRelocKind::PpcAddr16Hi,
+            elf::R_PPC_ADDR16_HA => ObjRelocKind::PpcAddr16Ha,
+            elf::R_PPC_REL24 => ObjRelocKind::PpcRel24,
+            elf::R_PPC_REL14 => ObjRelocKind::PpcRel14,
+            elf::R_PPC_EMB_SDA21 => ObjRelocKind::PpcEmbSda21,
+            _ => return None,
         })
     }
+
+    /// Mask of the bits written by this relocation,
+    /// within the 4-byte word at the relocation address.
+    /// Equivalent to [Architecture::reloc_field_mask] for [PowerPc].
+    pub fn field_mask(self) -> u32 { PowerPc.reloc_field_mask(self) }
+}
+
+impl Serialize for ObjRelocKind {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: serde::Serializer {
+        serializer.serialize_str(self.name())
+    }
 }
 
 impl<'de> Deserialize<'de> for ObjRelocKind {
@@ -55,7 +111,19 @@ impl<'de> Deserialize<'de> for ObjRelocKind {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Computes the `@ha` and `@l` halves of a target address + addend.
+/// The low half is sign-extended when loaded, so the high half is rounded up to compensate.
+pub fn split_ha_lo(target: u32, addend: i64) -> (u16, u16) {
+    let value = (target as i64 + addend) as u32;
+    let ha = (value.wrapping_add(0x8000) >> 16) as u16;
+    let lo = value as u16;
+    (ha, lo)
+}
+
+/// Reconstructs the full 32-bit value from an `@ha` / `@l` pair.
+pub fn join_ha_lo(ha: u16, lo: u16) -> u32 { ((ha as u32) << 16).wrapping_add(lo as i16 as u32) }
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct ObjReloc {
     pub kind: ObjRelocKind,
     // pub address: u64,
@@ -65,44 +133,26 @@ pub struct ObjReloc {
     pub module: Option<u32>,
 }
 
-impl ObjReloc {
-    /// Calculates the ELF r_offset and r_type for a relocation.
-    pub fn to_elf(&self, addr: u32) -> (u64, u32) {
-        let mut r_offset = addr as u64;
-        let r_type = match self.kind {
-            ObjRelocKind::Absolute => {
-                if r_offset & 3 == 0 {
-                    elf::R_PPC_ADDR32
-                } else {
-                    elf::R_PPC_UADDR32
-                }
-            }
-            ObjRelocKind::PpcAddr16Hi => {
-                r_offset = (r_offset & !3) + 2;
-                elf::R_PPC_ADDR16_HI
-            }
-            ObjRelocKind::PpcAddr16Ha => {
-                r_offset = (r_offset & !3) + 2;
-                elf::R_PPC_ADDR16_HA
-            }
-            ObjRelocKind::PpcAddr16Lo => {
-                r_offset = (r_offset & !3) + 2;
-                elf::R_PPC_ADDR16_LO
-            }
-            ObjRelocKind::PpcRel24 => {
-                r_offset &= !3;
-                elf::R_PPC_REL24
-            }
-            ObjRelocKind::PpcRel14 => {
-                r_offset &= !3;
-                elf::R_PPC_REL14
-            }
-            ObjRelocKind::PpcEmbSda21 => {
-                r_offset &= !3;
-                elf::R_PPC_EMB_SDA21
-            }
-        };
-        (r_offset, r_type)
+/// Portable form of an [ObjReloc], referencing the target symbol by name,
+/// since symbol indices aren't stable across objects.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct NamedReloc {
+    pub kind: ObjRelocKind,
+    /// Target symbol name.
+    pub target: String,
+    pub addend: i64,
+    /// Target module ID, for relocations against another module.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub module: Option<u32>,
+}
+
+impl NamedReloc {
+    /// Resolves the target symbol name to an index, e.g. with [ObjSymbols::by_name].
+    pub fn resolve<F>(&self, resolve: F) -> Result<ObjReloc>
+    where F: FnOnce(&str) -> Option<SymbolIndex> {
+        let target_symbol = resolve(&self.target)
+            .ok_or_else(|| anyhow!("Failed to resolve relocation target {}", self.target))?;
+        Ok(ObjReloc { kind: self.kind, target_symbol, addend: self.addend, module: self.module })
     }
 }
 
@@ -125,6 +175,117 @@ impl fmt::Display for ExistingRelocationError {
 
 impl Error for ExistingRelocationError {}
 
+#[derive(Debug)]
+pub struct RelocationRangeError {
+    pub kind: ObjRelocKind,
+    pub target: u64,
+    pub addend: i64,
+}
+
+impl fmt::Display for RelocationRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} relocation target {:#X} with addend {:#X} is out of range",
+            self.kind, self.target, self.addend
+        )
+    }
+}
+
+impl Error for RelocationRangeError {}
+
+impl ObjReloc {
+    /// Calculates the ELF r_offset and r_type for a relocation.
+    /// Equivalent to [Architecture::reloc_to_elf] for [PowerPc].
+    pub fn to_elf(&self, addr: u32) -> (u64, u32) { PowerPc.reloc_to_elf(self, addr) }
+
+    pub fn to_named(&self, symbols: &ObjSymbols) -> NamedReloc {
+        NamedReloc {
+            kind: self.kind,
+            target: symbols[self.target_symbol].name.clone(),
+            addend: self.addend,
+            module: self.module,
+        }
+    }
+
+    /// Renders the relocation for display, e.g. `rel24 -> FooBar+0x10 (module 3)`.
+    /// Targets missing from `symbols` are shown by index.
+    pub fn display_with(&self, symbols: &ObjSymbols) -> String {
+        let mut out = format!("{} -> ", self.kind.name());
+        match symbols.get(self.target_symbol) {
+            Some(symbol) if !symbol.name.is_empty() => out.push_str(&symbol.name),
+            Some(_) => out.push_str(&format!("<symbol {}>", self.target_symbol)),
+            None => out.push_str(&format!("<unresolved symbol {}>", self.target_symbol)),
+        }
+        match self.addend {
+            0 => {}
+            addend if addend < 0 => out.push_str(&format!("-{:#X}", addend.unsigned_abs())),
+            addend => out.push_str(&format!("+{:#X}", addend)),
+        }
+        if let Some(module) = self.module {
+            out.push_str(&format!(" (module {})", module));
+        }
+        out
+    }
+
+    /// Patches the word at relocation site `address`, in `arch` byte order, to reference the
+    /// resolved `target` address (including the addend). `sda21` relocations depend on the
+    /// small data base and are applied by [ObjInfo::linked_section_bytes](crate::obj::ObjInfo::linked_section_bytes).
+    pub fn apply(
+        &self,
+        bytes: &mut [u8; 4],
+        address: u32,
+        target: u32,
+        arch: &dyn Architecture,
+    ) -> Result<()> {
+        let ins = arch.read_u32(*bytes);
+        let diff = target.wrapping_sub(address) as i32;
+        let ins = match self.kind {
+            ObjRelocKind::Absolute => target,
+            ObjRelocKind::PpcAddr16Hi => (ins & !0xFFFF) | (target >> 16),
+            ObjRelocKind::PpcAddr16Ha => (ins & !0xFFFF) | (target.wrapping_add(0x8000) >> 16),
+            ObjRelocKind::PpcAddr16Lo => (ins & !0xFFFF) | (target & 0xFFFF),
+            ObjRelocKind::PpcRel24 => {
+                ensure!(
+                    (-0x2000000..0x2000000).contains(&diff) && diff & 3 == 0,
+                    "rel24 branch @ {:#010X} to {:#010X} out of range",
+                    address,
+                    target
+                );
+                (ins & !0x3FFFFFC) | (diff as u32 & 0x3FFFFFC)
+            }
+            ObjRelocKind::PpcRel14 => {
+                ensure!(
+                    (-0x8000..0x8000).contains(&diff) && diff & 3 == 0,
+                    "rel14 branch @ {:#010X} to {:#010X} out of range",
+                    address,
+                    target
+                );
+                (ins & !0xFFFC) | (diff as u32 & 0xFFFC)
+            }
+            ObjRelocKind::PpcEmbSda21 => {
+                bail!("sda21 relocation @ {:#010X} requires a small data base", address)
+            }
+        };
+        *bytes = arch.write_u32(ins);
+        Ok(())
+    }
+
+    /// Resolves `target + addend` to the 32-bit address written by the relocation.
+    /// Absolute and half-word relocations address the full 32-bit space, so a result
+    /// outside of it indicates a bad addend.
+    pub fn target_address(&self, target: u64) -> Result<u32, RelocationRangeError> {
+        let address = target as i64 + self.addend;
+        match u32::try_from(address) {
+            Ok(address) => Ok(address),
+            Err(_) if !(self.kind == ObjRelocKind::Absolute || self.kind.is_halfword()) => {
+                Ok(address as u32)
+            }
+            Err(_) => Err(RelocationRangeError { kind: self.kind, target, addend: self.addend }),
+        }
+    }
+}
+
 impl ObjRelocations {
     pub fn new(relocations: Vec<(u32, ObjReloc)>) -> Result<Self, ExistingRelocationError> {
         let mut map = BTreeMap::new();
@@ -140,6 +301,43 @@ impl ObjRelocations {
         Ok(Self { relocations: map })
     }
 
+    /// Decodes packed big-endian `Elf32_Rela` records. Symbol indices refer directly to
+    /// `symtab`. Offsets are returned as-is; [insert](Self::insert) aligns them to the
+    /// containing word. Records with unsupported types or out of range symbols are reported
+    /// together in a single error once the whole stream has been decoded.
+    pub fn from_rela_bytes(data: &[u8], symtab: &ObjSymbols) -> Result<Vec<(u32, ObjReloc)>> {
+        const RELA_SIZE: usize = 12;
+        if data.len() % RELA_SIZE != 0 {
+            bail!("Rela data size {:#X} is not a multiple of {:#X}", data.len(), RELA_SIZE);
+        }
+        let mut out = Vec::with_capacity(data.len() / RELA_SIZE);
+        let mut errors = vec![];
+        for record in data.chunks_exact(RELA_SIZE) {
+            let r_offset = u32::from_be_bytes(*array_ref!(record, 0, 4));
+            let r_info = u32::from_be_bytes(*array_ref!(record, 4, 4));
+            let r_addend = i32::from_be_bytes(*array_ref!(record, 8, 4));
+            let (r_sym, r_type) = (r_info >> 8, r_info & 0xFF);
+            let Some(kind) = ObjRelocKind::from_elf(r_type) else {
+                errors.push(format!("{:#010X}: unsupported relocation type {}", r_offset, r_type));
+                continue;
+            };
+            if r_sym as usize >= symtab.count() {
+                errors.push(format!("{:#010X}: symbol index {} out of range", r_offset, r_sym));
+                continue;
+            }
+            out.push((r_offset, ObjReloc {
+                kind,
+                target_symbol: r_sym as SymbolIndex,
+                addend: r_addend as i64,
+                module: None,
+            }));
+        }
+        if !errors.is_empty() {
+            bail!("Failed to decode {} relocation(s):\n{}", errors.len(), errors.join("\n"));
+        }
+        Ok(out)
+    }
+
     pub fn len(&self) -> usize { self.relocations.len() }
 
     pub fn insert(&mut self, address: u32, reloc: ObjReloc) -> Result<(), ExistingRelocationError> {
@@ -181,4 +379,238 @@ impl ObjRelocations {
     }
 
     pub fn contains(&self, address: u32) -> bool { self.relocations.contains_key(&address) }
+
+    /// Removes all relocations within `range`, returning them in address order.
+    pub fn remove_range<R>(&mut self, range: R) -> Vec<(u32, ObjReloc)>
+    where R: RangeBounds<u32> {
+        let addresses = self.relocations.range(range).map(|(&addr, _)| addr).collect::<Vec<_>>();
+        addresses
+            .into_iter()
+            .filter_map(|addr| self.relocations.remove(&addr).map(|reloc| (addr, reloc)))
+            .collect()
+    }
+
+    /// Finds the closest relocation strictly before `address`.
+    pub fn nearest_before(&self, address: u32) -> Option<(u32, &ObjReloc)> {
+        self.range(..address).next_back()
+    }
+
+    /// Finds the closest relocation strictly after `address`.
+    pub fn nearest_after(&self, address: u32) -> Option<(u32, &ObjReloc)> {
+        self.range((Bound::Excluded(address), Bound::Unbounded)).next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::{ObjKind, ObjSymbol};
+
+    #[test]
+    fn test_from_rela_bytes() {
+        let mut symbols = ObjSymbols::new(ObjKind::Relocatable, vec![]);
+        for name in ["", "foo", "bar"] {
+            symbols.add_direct(ObjSymbol { name: name.to_string(), ..Default::default() }).unwrap();
+        }
+        let record = |offset: u32, sym: u32, r_type: u32, addend: i32| {
+            [offset.to_be_bytes(), ((sym << 8) | r_type).to_be_bytes(), addend.to_be_bytes()]
+                .concat()
+        };
+        let data =
+            [record(0x10, 1, elf::R_PPC_REL24, 0), record(0x22, 2, elf::R_PPC_ADDR16_LO, -4)]
+                .concat();
+        let relocs = ObjRelocations::from_rela_bytes(&data, &symbols).unwrap();
+        assert_eq!(relocs, vec![
+            (0x10, ObjReloc {
+                kind: ObjRelocKind::PpcRel24,
+                target_symbol: 1,
+                addend: 0,
+                module: None
+            }),
+            (0x22, ObjReloc {
+                kind: ObjRelocKind::PpcAddr16Lo,
+                target_symbol: 2,
+                addend: -4,
+                module: None
+            }),
+        ]);
+
+        // Both bad records are reported
+        let data = [record(0x10, 1, elf::R_PPC_PLTREL24, 0), record(0x14, 9, elf::R_PPC_ADDR32, 0)]
+            .concat();
+        let err = ObjRelocations::from_rela_bytes(&data, &symbols).unwrap_err().to_string();
+        assert!(err.contains("0x00000010") && err.contains("0x00000014"));
+        assert!(ObjRelocations::from_rela_bytes(&data[..8], &symbols).is_err());
+    }
+
+    #[test]
+    fn test_display_with() {
+        let mut symbols = ObjSymbols::new(ObjKind::Relocatable, vec![]);
+        for name in ["FooBar", ""] {
+            symbols.add_direct(ObjSymbol { name: name.to_string(), ..Default::default() }).unwrap();
+        }
+        let display = |kind, target_symbol, addend, module| {
+            ObjReloc { kind, target_symbol, addend, module }.display_with(&symbols)
+        };
+        assert_eq!(
+            display(ObjRelocKind::PpcRel24, 0, 0x10, Some(3)),
+            "rel24 -> FooBar+0x10 (module 3)"
+        );
+        assert_eq!(display(ObjRelocKind::PpcAddr16Ha, 0, -8, None), "ha -> FooBar-0x8");
+        assert_eq!(display(ObjRelocKind::Absolute, 0, 0, None), "abs -> FooBar");
+        assert_eq!(display(ObjRelocKind::PpcEmbSda21, 1, 0, None), "sda21 -> <symbol 1>");
+        assert_eq!(
+            display(ObjRelocKind::PpcAddr16Lo, 5, 4, Some(0)),
+            "l -> <unresolved symbol 5>+0x4 (module 0)"
+        );
+    }
+
+    #[test]
+    fn test_apply() {
+        let apply = |kind, ins: u32, address, target| {
+            let mut bytes = ins.to_be_bytes();
+            ObjReloc { kind, target_symbol: 0, addend: 0, module: None }
+                .apply(&mut bytes, address, target, &PowerPc)
+                .map(|_| u32::from_be_bytes(bytes))
+        };
+        assert_eq!(apply(ObjRelocKind::Absolute, 0, 0x80003100, 0x80004000).unwrap(), 0x80004000);
+        assert_eq!(
+            apply(ObjRelocKind::PpcAddr16Ha, 0x3C600000, 0, 0x8000C010).unwrap(),
+            0x3C608001
+        );
+        assert_eq!(
+            apply(ObjRelocKind::PpcAddr16Hi, 0x3C600000, 0, 0x8000C010).unwrap(),
+            0x3C608000
+        );
+        assert_eq!(
+            apply(ObjRelocKind::PpcAddr16Lo, 0x38630000, 0, 0x8000C010).unwrap(),
+            0x3863C010
+        );
+        assert_eq!(
+            apply(ObjRelocKind::PpcRel24, 0x48000001, 0x80003108, 0x80003100).unwrap(),
+            0x4BFFFFF9
+        );
+        assert_eq!(
+            apply(ObjRelocKind::PpcRel14, 0x41820000, 0x80003100, 0x80003120).unwrap(),
+            0x41820020
+        );
+        assert!(apply(ObjRelocKind::PpcRel24, 0x48000001, 0x80000000, 0x82000000).is_err());
+        assert!(apply(ObjRelocKind::PpcRel14, 0x41820000, 0x80000000, 0x80008000).is_err());
+        assert!(apply(ObjRelocKind::PpcEmbSda21, 0x806D0000, 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_reloc_eq_hash() {
+        use std::{collections::hash_map::RandomState, hash::BuildHasher};
+
+        let reloc = ObjReloc {
+            kind: ObjRelocKind::PpcAddr16Lo,
+            target_symbol: 1,
+            addend: 4,
+            module: Some(2),
+        };
+        let other = reloc.clone();
+        assert_eq!(reloc, other);
+        let state = RandomState::new();
+        assert_eq!(state.hash_one(&reloc), state.hash_one(&other));
+        assert_ne!(reloc, ObjReloc { module: None, ..other });
+    }
+
+    #[test]
+    fn test_named_reloc() {
+        use crate::obj::{ObjKind, ObjSymbol};
+
+        let symbols = ObjSymbols::new(ObjKind::Relocatable, vec![
+            ObjSymbol { name: "fn_a".to_string(), ..Default::default() },
+            ObjSymbol { name: "fn_b".to_string(), ..Default::default() },
+        ]);
+        let reloc = ObjReloc {
+            kind: ObjRelocKind::PpcRel24,
+            target_symbol: 1,
+            addend: -0x10,
+            module: None,
+        };
+        let named = reloc.to_named(&symbols);
+        let json = serde_json::to_string(&named).unwrap();
+        assert_eq!(json, r#"{"kind":"rel24","target":"fn_b","addend":-16}"#);
+
+        let named = serde_json::from_str::<NamedReloc>(&json).unwrap();
+        let resolved =
+            named.resolve(|name| symbols.by_name(name).ok().flatten().map(|(i, _)| i)).unwrap();
+        assert_eq!((resolved.kind, resolved.target_symbol), (ObjRelocKind::PpcRel24, 1));
+        assert_eq!((resolved.addend, resolved.module), (-0x10, None));
+        assert!(named.resolve(|_| None).is_err());
+
+        let yaml = serde_yaml::to_string(&NamedReloc { addend: i64::MIN, ..named }).unwrap();
+        assert_eq!(serde_yaml::from_str::<NamedReloc>(&yaml).unwrap().addend, i64::MIN);
+    }
+
+    #[test]
+    fn test_target_address() {
+        let reloc = |kind, addend| ObjReloc { kind, target_symbol: 0, addend, module: None };
+        let absolute = reloc(ObjRelocKind::Absolute, 0x10);
+        assert_eq!(absolute.target_address(0x80003100).unwrap(), 0x80003110);
+        assert!(absolute.target_address(0xFFFFFFF8).is_err());
+        assert!(reloc(ObjRelocKind::Absolute, -0x10).target_address(0x8).is_err());
+        assert!(reloc(ObjRelocKind::PpcAddr16Ha, 0x100000000).target_address(0).is_err());
+        assert_eq!(reloc(ObjRelocKind::PpcAddr16Lo, -4).target_address(0x8).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_split_ha_lo() {
+        // Low half has the high bit set: ha carries
+        assert_eq!(split_ha_lo(0x80538000, 0), (0x8054, 0x8000));
+        assert_eq!(join_ha_lo(0x8054, 0x8000), 0x80538000);
+        assert_eq!(split_ha_lo(0x80537FF0, 0x10), (0x8054, 0x8000));
+        assert_eq!(split_ha_lo(0x80537FFF, 0), (0x8053, 0x7FFF));
+        for target in [0x80000000, 0x8000FFFC, 0x803FFFF8, 0xFFFF8000, 0x7FFF] {
+            let (ha, lo) = split_ha_lo(target, 0);
+            assert_eq!(join_ha_lo(ha, lo), target);
+        }
+    }
+
+    #[test]
+    fn test_nearest() {
+        let reloc = |target_symbol| ObjReloc {
+            kind: ObjRelocKind::Absolute,
+            target_symbol,
+            addend: 0,
+            module: None,
+        };
+        let relocations =
+            ObjRelocations::new(vec![(0x100, reloc(0)), (0x108, reloc(1)), (0x110, reloc(2))])
+                .unwrap();
+        let before =
+            |address| relocations.nearest_before(address).map(|(a, r)| (a, r.target_symbol));
+        let after = |address| relocations.nearest_after(address).map(|(a, r)| (a, r.target_symbol));
+        // Between
+        assert_eq!(before(0x10C), Some((0x108, 1)));
+        assert_eq!(after(0x10C), Some((0x110, 2)));
+        assert_eq!(before(0x108), Some((0x100, 0)));
+        assert_eq!(after(0x108), Some((0x110, 2)));
+        // Before all
+        assert_eq!(before(0x100), None);
+        assert_eq!(after(0), Some((0x100, 0)));
+        // After all
+        assert_eq!(before(u32::MAX), Some((0x110, 2)));
+        assert_eq!(after(0x110), None);
+    }
+
+    #[test]
+    fn test_reloc_kind_categories() {
+        // (kind, is_branch, is_halfword, is_pc_relative)
+        for (kind, branch, halfword, pc_relative) in [
+            (ObjRelocKind::Absolute, false, false, false),
+            (ObjRelocKind::PpcAddr16Hi, false, true, false),
+            (ObjRelocKind::PpcAddr16Ha, false, true, false),
+            (ObjRelocKind::PpcAddr16Lo, false, true, false),
+            (ObjRelocKind::PpcRel24, true, false, true),
+            (ObjRelocKind::PpcRel14, true, false, true),
+            (ObjRelocKind::PpcEmbSda21, false, false, false),
+        ] {
+            assert_eq!(kind.is_branch(), branch, "{:?}", kind);
+            assert_eq!(kind.is_halfword(), halfword, "{:?}", kind);
+            assert_eq!(kind.is_pc_relative(), pc_relative, "{:?}", kind);
+        }
+    }
 }
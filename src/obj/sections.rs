@@ -2,12 +2,18 @@ use std::{
     cmp::min,
     collections::Bound,
     ops::{Index, IndexMut, Range, RangeBounds},
+    sync::Mutex,
 };
 
 use anyhow::{anyhow, bail, ensure, Result};
 use itertools::Itertools;
+use object::elf::{SHF_ALLOC, SHF_EXECINSTR, SHF_WRITE, SHT_NOBITS, SHT_PROGBITS};
+use xxhash_rust::xxh3::xxh3_64;
 
-use crate::obj::{ObjKind, ObjRelocations, ObjSplit, ObjSplits, ObjSymbol};
+use crate::{
+    obj::{Architecture, ObjKind, ObjRelocations, ObjSplit, ObjSplits, ObjSymbol, PowerPc},
+    util::ncompress::{compress_yaz0_parallel, YAZ0_PARALLEL_WINDOW},
+};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum ObjSectionKind {
@@ -17,6 +23,28 @@ pub enum ObjSectionKind {
     Bss,
 }
 
+impl ObjSectionKind {
+    /// ELF section flags (`SHF_*`) implied by the section kind.
+    pub fn elf_flags(self) -> u32 {
+        match self {
+            ObjSectionKind::Code => SHF_ALLOC | SHF_EXECINSTR,
+            ObjSectionKind::Data | ObjSectionKind::Bss => SHF_ALLOC | SHF_WRITE,
+            ObjSectionKind::ReadOnlyData => SHF_ALLOC,
+        }
+    }
+
+    /// ELF section type (`SHT_*`) implied by the section kind.
+    /// BSS sections are `SHT_NOBITS`, and have no data in the file.
+    pub fn elf_type(self) -> u32 {
+        match self {
+            ObjSectionKind::Code | ObjSectionKind::Data | ObjSectionKind::ReadOnlyData => {
+                SHT_PROGBITS
+            }
+            ObjSectionKind::Bss => SHT_NOBITS,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ObjSection {
     pub name: String,
@@ -32,6 +60,15 @@ pub struct ObjSection {
     pub file_offset: u64,
     pub section_known: bool,
     pub splits: ObjSplits,
+    pub compressed_size_cache: CompressedSizeCache,
+}
+
+/// Last [ObjSection::estimate_compressed_size] result, keyed by a hash of the section data.
+#[derive(Debug, Default)]
+pub struct CompressedSizeCache(Mutex<Option<(u64, usize)>>);
+
+impl Clone for CompressedSizeCache {
+    fn clone(&self) -> Self { Self(Mutex::new(*self.0.lock().unwrap())) }
 }
 
 #[derive(Debug, Clone)]
@@ -108,6 +145,14 @@ impl ObjSections {
         self.iter().filter(move |(_, s)| s.kind == kind)
     }
 
+    pub fn by_kind_mut(
+        &mut self,
+        kind: ObjSectionKind,
+    ) -> impl DoubleEndedIterator<Item = (usize, &mut ObjSection)> {
+        self.iter_mut().filter(move |(_, s)| s.kind == kind)
+    }
+
+    /// Locate the section with the given name, erroring if multiple sections share it.
     pub fn by_name(&self, name: &str) -> Result<Option<(usize, &ObjSection)>> {
         self.iter()
             .filter(move |(_, s)| s.name == name)
@@ -115,6 +160,27 @@ impl ObjSections {
             .map_err(|_| anyhow!("Multiple sections with name {}", name))
     }
 
+    /// Locate the section with the given name, erroring if multiple sections share it.
+    pub fn by_name_mut(&mut self, name: &str) -> Result<Option<(usize, &mut ObjSection)>> {
+        self.iter_mut()
+            .filter(move |(_, s)| s.name == name)
+            .at_most_one()
+            .map_err(|_| anyhow!("Multiple sections with name {}", name))
+    }
+
+    /// Locate the first (lowest index) section with the given name.
+    pub fn first_by_name(&self, name: &str) -> Option<(usize, &ObjSection)> {
+        self.iter().find(|(_, s)| s.name == name)
+    }
+
+    /// Locate all sections with the given name, in index order.
+    pub fn all_by_name<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl DoubleEndedIterator<Item = (usize, &'a ObjSection)> + 'a {
+        self.iter().filter(move |(_, s)| s.name == name)
+    }
+
     pub fn push(&mut self, section: ObjSection) -> usize {
         let index = self.sections.len();
         self.sections.push(section);
@@ -179,6 +245,45 @@ impl ObjSection {
         })
     }
 
+    /// The section data as stored, without applying or clearing any relocations.
+    /// Empty for bss sections.
+    #[inline]
+    pub fn raw_bytes(&self) -> &[u8] { &self.data }
+
+    /// Returns `len` bytes of section data starting at the given address.
+    /// Errors if the range is outside of the section data, or if the section is bss.
+    pub fn data_at(&self, address: u32, len: usize) -> Result<&[u8]> {
+        ensure!(
+            self.kind != ObjSectionKind::Bss,
+            "Attempted to read data from bss section {} @ {:#010X}",
+            self.name,
+            address
+        );
+        match (address as u64).checked_sub(self.address) {
+            Some(offset) if offset + len as u64 <= self.data.len() as u64 => {
+                Ok(&self.data[offset as usize..offset as usize + len])
+            }
+            _ => Err(anyhow!(
+                "Range {:#010X}-{:#010X} outside of section {}: {:#010X}-{:#010X}",
+                address,
+                address as u64 + len as u64,
+                self.name,
+                self.address,
+                self.address + self.data.len() as u64
+            )),
+        }
+    }
+
+    /// Reads a 32-bit word at the given address in the byte order of `arch`.
+    #[inline]
+    pub fn read_word(&self, address: u32, arch: &dyn Architecture) -> Result<u32> {
+        Ok(arch.read_u32(self.data_at(address, 4)?.try_into()?))
+    }
+
+    /// Reads a big-endian (PowerPC) `u32` at the given address.
+    #[inline]
+    pub fn read_u32(&self, address: u32) -> Result<u32> { self.read_word(address, &PowerPc) }
+
     #[inline]
     pub fn symbol_data(&self, symbol: &ObjSymbol) -> Result<&[u8]> {
         if symbol.size == 0 {
@@ -210,6 +315,132 @@ impl ObjSection {
         start_in_range && end_in_range
     }
 
+    /// Hashes the section data in `chunk_size` byte chunks. The last chunk may be shorter.
+    ///
+    /// Panics if `chunk_size` is 0.
+    pub fn chunk_hashes(&self, chunk_size: usize) -> Vec<u64> {
+        self.data.chunks(chunk_size).map(xxh3_64).collect()
+    }
+
+    /// Estimates the size of the section data once Yaz0 compressed, using the fast parallel
+    /// encoder. The result is cached until the section data changes.
+    pub fn estimate_compressed_size(&self) -> usize {
+        let hash = xxh3_64(&self.data);
+        let mut cache = self.compressed_size_cache.0.lock().unwrap();
+        match *cache {
+            Some((cached_hash, size)) if cached_hash == hash => size,
+            _ => {
+                let size = compress_yaz0_parallel(&self.data, YAZ0_PARALLEL_WINDOW).len();
+                *cache = Some((hash, size));
+                size
+            }
+        }
+    }
+
+    /// Compares section data against `other` by chunk hash, returning the address ranges
+    /// (relative to this section) of differing chunks. Adjacent differing chunks are merged.
+    /// If the data lengths differ, the trailing chunks present in only one side are included.
+    ///
+    /// Panics if `chunk_size` is 0.
+    pub fn changed_chunks(&self, other: &ObjSection, chunk_size: usize) -> Vec<Range<u32>> {
+        let hashes = self.chunk_hashes(chunk_size);
+        let other_hashes = other.chunk_hashes(chunk_size);
+        let data_len = self.data.len().max(other.data.len());
+        let mut ranges: Vec<Range<u32>> = vec![];
+        for i in 0..hashes.len().max(other_hashes.len()) {
+            if hashes.get(i).is_some() && hashes.get(i) == other_hashes.get(i) {
+                continue;
+            }
+            let start = (self.address as usize + i * chunk_size) as u32;
+            let end = (self.address as usize + min((i + 1) * chunk_size, data_len)) as u32;
+            match ranges.last_mut() {
+                Some(last) if last.end == start => last.end = end,
+                _ => ranges.push(start..end),
+            }
+        }
+        ranges
+    }
+
+    /// Replaces `remove` bytes at address `at` with `insert`, shifting relocations and split
+    /// boundaries at or after the end of the removed range by the net size change. Bytes
+    /// inserted at a split boundary belong to the preceding split. Fails without modifying the
+    /// section if the splice begins inside or removes a relocated field, removes a split
+    /// boundary, or, when relocations follow the splice, if the size change isn't a multiple
+    /// of 4. Symbols are owned by [ObjInfo](crate::obj::ObjInfo), so prefer
+    /// [ObjInfo::splice_section](crate::obj::ObjInfo::splice_section).
+    pub fn splice(&mut self, at: u32, remove: usize, insert: &[u8]) -> Result<()> {
+        ensure!(self.kind != ObjSectionKind::Bss, "Can't splice bss section {}", self.name);
+        let end = at as u64 + remove as u64;
+        ensure!(
+            at as u64 >= self.address && end <= self.address + self.size,
+            "Splice {:#010X}-{:#010X} outside of section {}: {:#010X}-{:#010X}",
+            at,
+            end,
+            self.name,
+            self.address,
+            self.address + self.size
+        );
+        let delta = insert.len() as i64 - remove as i64;
+        for (address, reloc) in self.relocations.iter() {
+            let field_end = address as u64 + 4;
+            ensure!(
+                !(address < at && field_end > at as u64)
+                    && !(remove > 0 && (address as u64) < end && field_end > at as u64),
+                "Splice at {:#010X} overlaps {:?} relocation at {:#010X}",
+                at,
+                reloc.kind,
+                address
+            );
+            ensure!(
+                (address as u64) < end || delta % 4 == 0,
+                "Splice at {:#010X} would misalign relocation at {:#010X} by {:#X} bytes",
+                at,
+                address,
+                delta
+            );
+        }
+
+        for (address, split) in self.splits.iter() {
+            for boundary in [address, split.end] {
+                ensure!(
+                    boundary as u64 <= at as u64 || boundary as u64 >= end,
+                    "Splice {:#010X}-{:#010X} removes split boundary {:#010X} of {}",
+                    at,
+                    end,
+                    boundary,
+                    split.unit
+                );
+            }
+        }
+
+        let shift = |address: u32| {
+            if address as u64 >= end {
+                (address as i64 + delta) as u32
+            } else {
+                address
+            }
+        };
+        let offset = (at as u64 - self.address) as usize;
+        self.data.splice(offset..offset + remove, insert.iter().copied());
+        self.size = (self.size as i64 + delta) as u64;
+        let relocations = self
+            .relocations
+            .iter()
+            .map(|(address, reloc)| (shift(address), reloc.clone()))
+            .collect();
+        self.relocations = ObjRelocations::new(relocations)?;
+        let mut splits = ObjSplits::default();
+        for (address, split) in self.splits.iter() {
+            let mut split = split.clone();
+            if split.end != 0 {
+                split.end = shift(split.end);
+            }
+            splits.push(shift(address), split);
+        }
+        self.splits = splits;
+        Ok(())
+    }
+
     pub fn rename(&mut self, name: String) -> Result<()> {
         self.kind = section_kind_for_section(&name)?;
         self.name = name;
@@ -229,3 +460,120 @@ fn section_kind_for_section(section_name: &str) -> Result<ObjSectionKind> {
         name => bail!("Unknown section {name}"),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::test_section;
+
+    #[test]
+    fn test_by_name() {
+        let section = |name: &str, kind| test_section(name, kind, 0x80000000, vec![0; 8]);
+        let mut sections = ObjSections::new(ObjKind::Relocatable, vec![
+            section(".text", ObjSectionKind::Code),
+            section(".data", ObjSectionKind::Data),
+            section(".text", ObjSectionKind::Code),
+            section(".bss", ObjSectionKind::Bss),
+        ]);
+        assert_eq!(sections.by_name(".data").unwrap().map(|(i, _)| i), Some(1));
+        assert!(sections.by_name(".rodata").unwrap().is_none());
+        assert!(sections.by_name(".text").is_err());
+        assert_eq!(sections.first_by_name(".text").map(|(i, _)| i), Some(0));
+        assert_eq!(sections.all_by_name(".text").map(|(i, _)| i).collect_vec(), vec![0, 2]);
+
+        let (_, data) = sections.by_name_mut(".data").unwrap().unwrap();
+        data.align = 32;
+        assert_eq!(sections[1].align, 32);
+        assert!(sections.by_name_mut(".text").is_err());
+
+        assert_eq!(sections.by_kind(ObjSectionKind::Code).map(|(i, _)| i).collect_vec(), vec![
+            0, 2
+        ]);
+        for (_, section) in sections.by_kind_mut(ObjSectionKind::Bss) {
+            section.size = 0x20;
+        }
+        assert_eq!(sections[3].size, 0x20);
+        assert_eq!(sections.by_kind(ObjSectionKind::ReadOnlyData).count(), 0);
+    }
+
+    #[test]
+    fn test_elf_flags() {
+        assert_eq!(ObjSectionKind::Code.elf_flags(), SHF_ALLOC | SHF_EXECINSTR);
+        assert_eq!(ObjSectionKind::Code.elf_type(), SHT_PROGBITS);
+        assert_eq!(ObjSectionKind::Bss.elf_flags(), SHF_ALLOC | SHF_WRITE);
+        assert_eq!(ObjSectionKind::Bss.elf_type(), SHT_NOBITS);
+        assert_eq!(ObjSectionKind::ReadOnlyData.elf_flags(), SHF_ALLOC);
+    }
+
+    #[test]
+    fn test_data_at() {
+        let section = test_section(".data", ObjSectionKind::Data, 0x80000000, vec![
+            0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0,
+        ]);
+        assert_eq!(section.data_at(0x80000002, 2).unwrap(), &[0x56, 0x78]);
+        assert_eq!(section.data_at(0x80000000, 8).unwrap().len(), 8);
+        assert_eq!(section.read_u32(0x80000000).unwrap(), 0x12345678);
+        assert_eq!(section.read_u32(0x80000004).unwrap(), 0x9ABCDEF0);
+
+        assert!(section.data_at(0x7FFFFFFC, 4).is_err());
+        assert!(section.data_at(0x80000006, 4).is_err());
+        assert!(section.read_u32(0x80000008).is_err());
+    }
+
+    #[test]
+    fn test_changed_chunks() {
+        let mut data = vec![0u8; 0x100];
+        data.iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+        let section = test_section(".text", ObjSectionKind::Code, 0x80000000, data.clone());
+        assert_eq!(section.chunk_hashes(0x40).len(), 4);
+        assert_eq!(section.chunk_hashes(0x30).len(), 6);
+
+        let mut other = section.clone();
+        assert!(section.changed_chunks(&other, 0x40).is_empty());
+
+        other.data[0x85] ^= 0xFF;
+        let hashes = section.chunk_hashes(0x40);
+        let other_hashes = other.chunk_hashes(0x40);
+        assert_eq!(hashes.iter().zip(&other_hashes).filter(|(a, b)| a != b).count(), 1);
+        assert_eq!(section.changed_chunks(&other, 0x40), vec![0x80000080..0x800000C0]);
+
+        // Growing the data flags the new trailing chunk
+        other.data = data;
+        other.data.extend_from_slice(&[0; 0x10]);
+        assert_eq!(section.changed_chunks(&other, 0x40), vec![0x80000100..0x80000110]);
+    }
+
+    #[test]
+    fn test_estimate_compressed_size() {
+        let mut repetitive =
+            test_section(".data", ObjSectionKind::Data, 0x80000000, b"abcd".repeat(0x1000));
+        let mut state = 0x12345678u32;
+        let random = (0..0x4000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        let random = test_section(".data", ObjSectionKind::Data, 0x80000000, random);
+        let repetitive_size = repetitive.estimate_compressed_size();
+        assert!(repetitive_size * 10 < random.estimate_compressed_size());
+        assert_eq!(repetitive.estimate_compressed_size(), repetitive_size);
+
+        // Cached while the data is unchanged
+        let hash = xxh3_64(&repetitive.data);
+        *repetitive.compressed_size_cache.0.lock().unwrap() = Some((hash, 1));
+        assert_eq!(repetitive.estimate_compressed_size(), 1);
+        assert_eq!(repetitive.clone().estimate_compressed_size(), 1);
+        repetitive.data[0] ^= 1;
+        assert!(repetitive.estimate_compressed_size() > 1);
+    }
+
+    #[test]
+    fn test_data_at_bss() {
+        let section = test_section(".bss", ObjSectionKind::Bss, 0x80000000, vec![]);
+        assert!(section.data_at(0x80000000, 4).is_err());
+        assert!(section.read_u32(0x80000000).is_err());
+    }
+}
@@ -2,6 +2,7 @@ use std::{cmp::max, collections::BTreeMap, ops::RangeBounds};
 
 use anyhow::{anyhow, Result};
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     obj::{ObjInfo, ObjSection},
@@ -9,18 +10,26 @@ use crate::{
 };
 
 /// Marks a split point within a section.
-#[derive(Debug, Clone, Eq, PartialEq)]
+/// Optional fields are omitted from serialized output when unset.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ObjSplit {
     pub unit: String,
     pub end: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub align: Option<u32>,
-    /// Whether this is a part of common BSS.
+    /// Whether this is a part of common BSS. Only valid in `.bss`, and all following `.bss`
+    /// splits must also be common. Symbols in a common split are emitted as common symbols
+    /// (without a section) in the split object, rather than into its `.bss`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub common: bool,
     /// Generated, replaceable by user.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub autogenerated: bool,
     /// Skip when emitting the split object.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub skip: bool,
     /// Override the section name in the split object. (e.g. `.ctors$10`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub rename: Option<String>,
 }
 
@@ -112,3 +121,31 @@ impl ObjSplits {
 
     pub fn remove(&mut self, address: u32) -> Option<Vec<ObjSplit>> { self.splits.remove(&address) }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_serde() {
+        let split = ObjSplit {
+            unit: "main.c".to_string(),
+            end: 0x80003200,
+            align: None,
+            common: false,
+            autogenerated: true,
+            skip: false,
+            rename: Some(".ctors$10".to_string()),
+        };
+        let json = serde_json::to_string(&split).unwrap();
+        assert_eq!(
+            json,
+            r#"{"unit":"main.c","end":2147496448,"autogenerated":true,"rename":".ctors$10"}"#
+        );
+        assert_eq!(serde_json::from_str::<ObjSplit>(&json).unwrap(), split);
+
+        let split = serde_json::from_str::<ObjSplit>(r#"{"unit":"a.c","end":16}"#).unwrap();
+        assert!(!split.autogenerated && !split.skip && !split.common);
+        assert_eq!((split.align, split.rename), (None, None));
+    }
+}
@@ -1,5 +1,7 @@
 use std::{
-    collections::{BTreeMap, HashMap},
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
     hash::{Hash, Hasher},
     ops::{Index, RangeBounds},
 };
@@ -201,6 +203,18 @@ pub struct ObjSymbol {
 
 pub type SymbolIndex = usize;
 
+/// How [ObjSymbols::add] handles a symbol with the same section address and kind as an
+/// existing symbol.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum SymbolMergePolicy {
+    /// Keep the existing symbol, or overwrite it when `replace` is set.
+    #[default]
+    Replace,
+    /// Merge into the existing symbol, keeping the more specific name and its scope,
+    /// along with the special flags of both symbols.
+    Merge,
+}
+
 #[derive(Debug, Clone)]
 pub struct ObjSymbols {
     obj_kind: ObjKind,
@@ -208,6 +222,7 @@ pub struct ObjSymbols {
     symbols_by_address: BTreeMap<u32, Vec<SymbolIndex>>,
     symbols_by_name: HashMap<String, Vec<SymbolIndex>>,
     symbols_by_section: Vec<BTreeMap<u32, Vec<SymbolIndex>>>,
+    merge_policy: SymbolMergePolicy,
 }
 
 impl ObjSymbols {
@@ -234,9 +249,18 @@ impl ObjSymbols {
                 symbols_by_name.nested_push(symbol.name.clone(), idx);
             }
         }
-        Self { obj_kind, symbols, symbols_by_address, symbols_by_name, symbols_by_section }
+        Self {
+            obj_kind,
+            symbols,
+            symbols_by_address,
+            symbols_by_name,
+            symbols_by_section,
+            merge_policy: SymbolMergePolicy::default(),
+        }
     }
 
+    pub fn set_merge_policy(&mut self, policy: SymbolMergePolicy) { self.merge_policy = policy; }
+
     pub fn add(&mut self, in_symbol: ObjSymbol, replace: bool) -> Result<SymbolIndex> {
         let opt = if in_symbol.flags.is_stripped() {
             // Stripped symbols don't overwrite existing symbols
@@ -253,6 +277,18 @@ impl ObjSymbols {
         } else {
             bail!("ABS symbol in relocatable object: {:?}", in_symbol);
         };
+        if self.merge_policy == SymbolMergePolicy::Merge {
+            if let Some((symbol_idx, existing)) =
+                opt.filter(|(_, existing)| existing.kind == in_symbol.kind)
+            {
+                let merged = merge_symbol(existing, in_symbol);
+                if existing != &merged {
+                    log::debug!("Merging {:?} into {:?}", existing, merged);
+                    self.replace(symbol_idx, merged)?;
+                }
+                return Ok(symbol_idx);
+            }
+        }
         let target_symbol_idx = if let Some((symbol_idx, existing)) = opt {
             let replace = replace
                 // Replace auto symbols with known symbols
@@ -359,6 +395,8 @@ impl ObjSymbols {
 
     pub fn count(&self) -> usize { self.symbols.len() }
 
+    pub fn get(&self, index: SymbolIndex) -> Option<&ObjSymbol> { self.symbols.get(index) }
+
     pub fn at_section_address(
         &self,
         section_idx: usize,
@@ -392,12 +430,22 @@ impl ObjSymbols {
             })
     }
 
-    // Iterate over all in address ascending order, excluding ABS symbols
+    /// Iterate over symbols in canonical order: by section, address, then name.
+    /// Excludes symbols without a section (ABS and common symbols).
+    /// [SymbolIndex] remains the stable identity of a symbol, but depends on insertion order,
+    /// so emitters should use this order rather than iterating by index.
     pub fn iter_ordered(&self) -> impl DoubleEndedIterator<Item = (SymbolIndex, &ObjSymbol)> {
-        self.symbols_by_section
-            .iter()
-            .flat_map(|v| v.iter().map(|(_, v)| v))
-            .flat_map(move |v| v.iter().map(move |u| (*u, &self.symbols[*u])))
+        let cmp = move |&a: &SymbolIndex, &b: &SymbolIndex| -> Ordering {
+            self.symbols[a].name.cmp(&self.symbols[b].name).then(a.cmp(&b))
+        };
+        self.symbols_by_section.iter().flat_map(|v| v.values()).flat_map(move |v| {
+            // Only copy addresses whose symbols aren't already in name order
+            let mut indexes = Cow::Borrowed(v.as_slice());
+            if indexes.windows(2).any(|w| cmp(&w[0], &w[1]) == Ordering::Greater) {
+                indexes.to_mut().sort_by(cmp);
+            }
+            (0..indexes.len()).map(move |i| (indexes[i], &self.symbols[indexes[i]]))
+        })
     }
 
     // Iterate over all ABS symbols
@@ -534,6 +582,34 @@ impl ObjSymbols {
         Ok(())
     }
 
+    /// Moves a symbol to a new section and address, updating the address indexes.
+    pub fn set_address(
+        &mut self,
+        index: SymbolIndex,
+        section: Option<usize>,
+        address: u64,
+    ) -> Result<()> {
+        ensure!(
+            section.is_some() || address == 0 || self.obj_kind == ObjKind::Executable,
+            "ABS symbol in relocatable object"
+        );
+        let symbol = &mut self.symbols[index];
+        self.symbols_by_address.nested_remove(&(symbol.address as u32), &index);
+        if let Some(section_idx) = symbol.section {
+            self.symbols_by_section[section_idx].nested_remove(&(symbol.address as u32), &index);
+        }
+        symbol.section = section;
+        symbol.address = address;
+        self.symbols_by_address.nested_push(address as u32, index);
+        if let Some(section_idx) = section {
+            if section_idx >= self.symbols_by_section.len() {
+                self.symbols_by_section.resize_with(section_idx + 1, BTreeMap::new);
+            }
+            self.symbols_by_section[section_idx].nested_push(address as u32, index);
+        }
+        Ok(())
+    }
+
     // Try to find a previous sized symbol that encompasses the target
     pub fn for_relocation(
         &self,
@@ -568,6 +644,36 @@ impl ObjSymbols {
         Ok(result)
     }
 
+    /// Find pairs of sized symbols of the same kind whose ranges partially overlap within
+    /// a section, which usually indicates a bad size. Zero-sized labels and symbols nested
+    /// within another (including identical ranges) are ignored. Pairs are returned in address
+    /// order.
+    pub fn find_overlaps(&self) -> Vec<(SymbolIndex, SymbolIndex)> {
+        let mut result = vec![];
+        for section_symbols in &self.symbols_by_section {
+            // Sized symbols that haven't ended yet, with their end address
+            let mut active: Vec<(u64, SymbolIndex)> = vec![];
+            for &index in section_symbols.values().flatten() {
+                let symbol = &self.symbols[index];
+                if symbol.size == 0 || symbol.kind == ObjSymbolKind::Section {
+                    continue;
+                }
+                let end = symbol.address + symbol.size;
+                active.retain(|&(prev_end, _)| prev_end > symbol.address);
+                for &(prev_end, prev_index) in &active {
+                    let prev = &self.symbols[prev_index];
+                    // Symbols at the same address always nest
+                    if prev.kind == symbol.kind && prev.address != symbol.address && prev_end < end
+                    {
+                        result.push((prev_index, index));
+                    }
+                }
+                active.push((end, index));
+            }
+        }
+        result
+    }
+
     #[inline]
     pub fn flags(&mut self, idx: SymbolIndex) -> &mut ObjSymbolFlagSet {
         &mut self.symbols[idx].flags
@@ -589,10 +695,7 @@ impl ObjSymbol {
 
         if is_linker_generated_label(&self.name) {
             // Linker generated labels will only be referenced by @ha/@h/@l relocations
-            return matches!(
-                reloc_kind,
-                ObjRelocKind::PpcAddr16Ha | ObjRelocKind::PpcAddr16Hi | ObjRelocKind::PpcAddr16Lo
-            );
+            return reloc_kind.is_halfword();
         }
 
         match self.kind {
@@ -602,14 +705,23 @@ impl ObjSymbol {
                 // !matches!(reloc_kind, ObjRelocKind::PpcRel14 | ObjRelocKind::PpcRel24)
                 true // SADX has bugged relocations that jump from .text to .bss, how awful
             }
-            ObjSymbolKind::Section => {
-                matches!(
-                    reloc_kind,
-                    ObjRelocKind::PpcAddr16Ha
-                        | ObjRelocKind::PpcAddr16Hi
-                        | ObjRelocKind::PpcAddr16Lo
-                )
-            }
+            ObjSymbolKind::Section => reloc_kind.is_halfword(),
+        }
+    }
+
+    /// Explicit alignment if known, otherwise a default based on the data kind.
+    pub fn effective_alignment(&self) -> u32 {
+        if let Some(align) = self.align {
+            return align;
+        }
+        match self.data_kind {
+            ObjDataKind::Byte | ObjDataKind::String | ObjDataKind::StringTable => 1,
+            ObjDataKind::Byte2
+            | ObjDataKind::Short
+            | ObjDataKind::String16
+            | ObjDataKind::String16Table => 2,
+            ObjDataKind::Byte8 | ObjDataKind::Double => 8,
+            ObjDataKind::Unknown | ObjDataKind::Byte4 | ObjDataKind::Float | ObjDataKind::Int => 4,
         }
     }
 }
@@ -657,7 +769,230 @@ pub fn best_match_for_reloc(
         if symbol.size > 0 {
             rank += 1;
         }
+        // Prefer symbols of the kind the relocation expects, e.g. a function
+        // over a data label at the same address for a branch
+        if reloc_kind.expected_target_kind() == Some(symbol.kind) {
+            rank += 2;
+        }
         -rank
     });
     symbols.into_iter().next()
 }
+
+/// Makes demangled names unique within `symbols`. Demangling drops some qualifiers, so distinct
+/// mangled names can demangle to the same string. Within each colliding group, ordered by
+/// mangled name, the first keeps its demangled name and the rest get a numeric suffix, e.g.
+/// `foo(char*) [1]`. Symbols sharing a mangled name share the result. Mangled names, which
+/// remain the authoritative identity, are left untouched.
+pub fn disambiguate_demangled_names(symbols: &mut [ObjSymbol]) {
+    let mut groups = BTreeMap::<String, Vec<usize>>::new();
+    for (index, symbol) in symbols.iter().enumerate() {
+        if let Some(demangled_name) = &symbol.demangled_name {
+            groups.entry(demangled_name.clone()).or_default().push(index);
+        }
+    }
+    let mut taken = groups.keys().cloned().collect::<HashSet<String>>();
+    for (demangled_name, mut indices) in groups {
+        indices.sort_by(|&a, &b| symbols[a].name.cmp(&symbols[b].name));
+        let mut suffix = 0;
+        let mut current = demangled_name.clone();
+        for (i, &index) in indices.iter().enumerate() {
+            if i > 0 && symbols[index].name != symbols[indices[i - 1]].name {
+                loop {
+                    suffix += 1;
+                    current = format!("{} [{}]", demangled_name, suffix);
+                    if taken.insert(current.clone()) {
+                        break;
+                    }
+                }
+            }
+            symbols[index].demangled_name = Some(current.clone());
+        }
+    }
+}
+
+/// Merges two symbols at the same address, keeping the more specific name:
+/// named over unnamed, and known over auto-generated.
+fn merge_symbol(existing: &ObjSymbol, in_symbol: ObjSymbol) -> ObjSymbol {
+    let specificity = |symbol: &ObjSymbol| match symbol.name.as_str() {
+        "" => 0,
+        _ if is_auto_symbol(symbol) => 1,
+        _ => 2,
+    };
+    let (named, other) = if specificity(&in_symbol) > specificity(existing) {
+        (in_symbol, existing.clone())
+    } else {
+        (existing.clone(), in_symbol)
+    };
+    ObjSymbol {
+        demangled_name: named.demangled_name.or(other.demangled_name),
+        size: if named.size_known || !other.size_known { named.size } else { other.size },
+        size_known: named.size_known || other.size_known,
+        flags: ObjSymbolFlagSet(named.flags.0 | other.flags.keep_flags()),
+        align: named.align.or(other.align),
+        data_kind: match named.data_kind {
+            ObjDataKind::Unknown => other.data_kind,
+            kind => kind,
+        },
+        name_hash: named.name_hash.or(other.name_hash),
+        demangled_name_hash: named.demangled_name_hash.or(other.demangled_name_hash),
+        ..named
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disambiguate_demangled_names() {
+        let symbol = |name: &str, demangled_name: &str| ObjSymbol {
+            name: name.to_string(),
+            demangled_name: Some(demangled_name.to_string()),
+            ..Default::default()
+        };
+        let mut symbols = vec![
+            symbol("foo__FPCc", "foo(char*)"),
+            symbol("foo__FPc", "foo(char*)"),
+            symbol("foo__FPc", "foo(char*)"),
+            symbol("bar__Fv", "foo(char*) [1]"),
+            symbol("baz__Fi", "baz(int)"),
+        ];
+        disambiguate_demangled_names(&mut symbols);
+        let names = symbols.iter().map(|s| s.demangled_name.as_deref().unwrap()).collect_vec();
+        assert_eq!(names, vec![
+            "foo(char*)",
+            "foo(char*) [2]",
+            "foo(char*) [2]",
+            "foo(char*) [1]",
+            "baz(int)"
+        ]);
+        assert_eq!(symbols[0].name, "foo__FPCc");
+        assert_eq!(symbols[1].name, "foo__FPc");
+    }
+
+    #[test]
+    fn test_best_match_for_reloc_kind() {
+        let symbol = |name: &str, kind, size| ObjSymbol {
+            name: name.to_string(),
+            address: 0x80003100,
+            section: Some(0),
+            size,
+            size_known: true,
+            kind,
+            ..Default::default()
+        };
+        let data = symbol("data_label", ObjSymbolKind::Object, 8);
+        let func = symbol("fn_80003100", ObjSymbolKind::Function, 0);
+        let candidates = || vec![(0, &data), (1, &func)];
+
+        // Without kind awareness, the sized object would outrank the unsized function
+        let best = |kind| best_match_for_reloc(candidates(), kind).map(|(i, _)| i);
+        assert_eq!(best(ObjRelocKind::PpcRel24), Some(1));
+        assert_eq!(best(ObjRelocKind::PpcRel14), Some(1));
+        assert_eq!(best(ObjRelocKind::PpcEmbSda21), Some(0));
+        assert_eq!(best(ObjRelocKind::Absolute), Some(0));
+
+        // A single candidate is returned regardless of kind
+        let result = best_match_for_reloc(vec![(0, &data)], ObjRelocKind::PpcRel24);
+        assert_eq!(result.map(|(i, _)| i), Some(0));
+        assert_eq!(ObjRelocKind::PpcAddr16Ha.expected_target_kind(), None);
+    }
+
+    #[test]
+    fn test_merge_policy() {
+        let symbol = |name: &str, flags| ObjSymbol {
+            name: name.to_string(),
+            address: 0x80003100,
+            section: Some(0),
+            flags: ObjSymbolFlagSet(flags),
+            kind: ObjSymbolKind::Object,
+            ..Default::default()
+        };
+        let unnamed = symbol("", ObjSymbolFlags::Local | ObjSymbolFlags::NoWrite);
+        let named = ObjSymbol {
+            size: 8,
+            size_known: true,
+            ..symbol("gData", ObjSymbolFlags::Global.into())
+        };
+
+        // Without merging, the existing name is kept
+        let mut symbols = ObjSymbols::new(ObjKind::Executable, vec![]);
+        symbols.add(unnamed.clone(), false).unwrap();
+        symbols.add(named.clone(), false).unwrap();
+        assert_eq!(symbols.iter().next().unwrap().name, "");
+
+        let mut symbols = ObjSymbols::new(ObjKind::Executable, vec![]);
+        symbols.set_merge_policy(SymbolMergePolicy::Merge);
+        let idx = symbols.add(unnamed, false).unwrap();
+        assert_eq!(symbols.add(named, false).unwrap(), idx);
+        assert_eq!(symbols.count(), 1);
+        let merged = &symbols[idx];
+        assert_eq!((merged.name.as_str(), merged.size, merged.size_known), ("gData", 8, true));
+        // The named symbol's scope wins; only special flags are kept from the other
+        assert_eq!(merged.flags.0, ObjSymbolFlags::Global | ObjSymbolFlags::NoWrite);
+        assert_eq!(merged.flags.scope(), ObjSymbolScope::Global);
+        assert_eq!(symbols.by_name("gData").unwrap().map(|(i, _)| i), Some(idx));
+    }
+
+    #[test]
+    fn test_effective_alignment() {
+        let symbol = |data_kind, align| ObjSymbol { data_kind, align, ..Default::default() };
+        assert_eq!(symbol(ObjDataKind::Double, None).effective_alignment(), 8);
+        assert_eq!(symbol(ObjDataKind::Float, None).effective_alignment(), 4);
+        assert_eq!(symbol(ObjDataKind::String, None).effective_alignment(), 1);
+        assert_eq!(symbol(ObjDataKind::Double, Some(32)).effective_alignment(), 32);
+    }
+
+    #[test]
+    fn test_find_overlaps() {
+        let symbol = |name: &str, address, size, kind| ObjSymbol {
+            name: name.to_string(),
+            address,
+            section: Some(0),
+            size,
+            size_known: true,
+            kind,
+            ..Default::default()
+        };
+        let symbols = ObjSymbols::new(ObjKind::Executable, vec![
+            symbol("fn_80003100", 0x80003100, 0x40, ObjSymbolKind::Function),
+            symbol("fn_80003120", 0x80003120, 0x40, ObjSymbolKind::Function),
+            symbol("lbl_80003110", 0x80003110, 0, ObjSymbolKind::Unknown),
+            symbol("jumptable_80003130", 0x80003130, 0x10, ObjSymbolKind::Object),
+            symbol("fn_80003160", 0x80003160, 0x20, ObjSymbolKind::Function),
+            // Nested within fn_80003160, and identical ranges
+            symbol("fn_80003168", 0x80003168, 0x8, ObjSymbolKind::Function),
+            symbol("fn_80003160_2", 0x80003160, 0x10, ObjSymbolKind::Function),
+            symbol("fn_80003180", 0x80003180, 0x10, ObjSymbolKind::Function),
+            symbol("fn_80003180_2", 0x80003180, 0x10, ObjSymbolKind::Function),
+        ]);
+        assert_eq!(symbols.find_overlaps(), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn test_iter_ordered() {
+        let symbol = |name: &str, section, address| ObjSymbol {
+            name: name.to_string(),
+            address,
+            section: Some(section),
+            ..Default::default()
+        };
+        let symbols = vec![
+            symbol("b", 0, 0x80003100),
+            symbol("a", 0, 0x80003100),
+            symbol("d", 1, 0x80004000),
+            symbol("c", 0, 0x80003200),
+        ];
+        let ordered = |symbols: Vec<ObjSymbol>| {
+            ObjSymbols::new(ObjKind::Executable, symbols)
+                .iter_ordered()
+                .map(|(_, s)| s.name.clone())
+                .collect::<Vec<_>>()
+        };
+        let forward = ordered(symbols.clone());
+        let reverse = ordered(symbols.into_iter().rev().collect());
+        assert_eq!(forward, vec!["a", "b", "c", "d"]);
+        assert_eq!(forward, reverse);
+    }
+}
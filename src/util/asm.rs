@@ -10,10 +10,10 @@ use ppc750cl::{Argument, Ins, InsIter, Opcode};
 
 use crate::{
     obj::{
-        ObjDataKind, ObjInfo, ObjReloc, ObjRelocKind, ObjSection, ObjSectionKind, ObjSymbol,
-        ObjSymbolKind,
+        disambiguate_demangled_names, ObjDataKind, ObjInfo, ObjReloc, ObjRelocKind, ObjSection,
+        ObjSectionKind, ObjSymbol, ObjSymbolKind,
     },
-    util::nested::NestedVec,
+    util::{extab, extab::ExTabPointer, nested::NestedVec},
 };
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -45,6 +45,7 @@ where W: Write + ?Sized {
 
     // We'll append generated symbols to the end
     let mut symbols: Vec<ObjSymbol> = obj.symbols.iter().cloned().collect();
+    disambiguate_demangled_names(&mut symbols);
     let mut section_entries: Vec<BTreeMap<u32, Vec<SymbolEntry>>> = vec![];
     let mut section_relocations: Vec<BTreeMap<u32, ObjReloc>> = vec![];
     for (section_idx, section) in obj.sections.iter() {
@@ -122,12 +123,12 @@ where W: Write + ?Sized {
     }
 
     // Generate labels for jump tables & relative data relocations
-    for (_section_index, section) in obj
+    for (section_index, _) in obj
         .sections
         .iter()
         .filter(|(_, s)| matches!(s.kind, ObjSectionKind::Data | ObjSectionKind::ReadOnlyData))
     {
-        for (reloc_address, reloc) in section.relocations.iter() {
+        for (reloc_address, reloc) in obj.relocations_in_order(section_index) {
             if reloc.addend == 0 {
                 continue;
             }
@@ -176,7 +177,7 @@ where W: Write + ?Sized {
             }
             write!(w, ".comm ")?;
             write_symbol_name(w, &symbol.name)?;
-            writeln!(w, ", {:#X}, {}", symbol.size, symbol.align.unwrap_or(4))?;
+            writeln!(w, ", {:#X}, {}", symbol.size, symbol.effective_alignment())?;
         }
     }
 
@@ -406,6 +407,13 @@ where
             if symbol.kind != ObjSymbolKind::Unknown {
                 writeln!(w)?;
             }
+            if symbol.align.is_some() {
+                // Only emitted when already satisfied, so no padding is inserted
+                let align = symbol.effective_alignment();
+                if align > 1 && symbol.address % align as u64 == 0 {
+                    writeln!(w, ".balign {align}")?;
+                }
+            }
             write!(w, "# {}:{:#X}", section.name, symbol.address)?;
             if let Some(section_address) = section.virtual_address {
                 write!(w, " | {:#X}", section_address + symbol.address)?;
@@ -414,6 +422,9 @@ where
             if let Some(name) = &symbol.demangled_name {
                 writeln!(w, "# {name}")?;
             }
+            if section.name == "extab" && symbol.kind == ObjSymbolKind::Object {
+                write_extab_comment(w, symbols, symbol, section)?;
+            }
             write!(w, ".{symbol_kind} ")?;
             write_symbol_name(w, &symbol.name)?;
             writeln!(w, ", {scope}")?;
@@ -435,6 +446,36 @@ where
     Ok(())
 }
 
+/// Describes a decoded extab record. Records that fail to decode are left undescribed.
+fn write_extab_comment<W>(
+    w: &mut W,
+    symbols: &[ObjSymbol],
+    symbol: &ObjSymbol,
+    section: &ObjSection,
+) -> Result<()>
+where
+    W: Write + ?Sized,
+{
+    let info = match section.symbol_data(symbol).and_then(extab::decode) {
+        Ok(info) => info,
+        Err(e) => {
+            log::debug!("Failed to decode extab record {}: {:?}", symbol.name, e);
+            return Ok(());
+        }
+    };
+    let resolve = |pointer: &ExTabPointer| match section
+        .relocations
+        .at(symbol.address as u32 + pointer.offset)
+    {
+        Some(reloc) => symbols[reloc.target_symbol].name.clone(),
+        None => format!("{:#010X}", pointer.value),
+    };
+    for line in extab::render(&info, resolve) {
+        writeln!(w, "# {line}")?;
+    }
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 fn write_data<W>(
     w: &mut W,
@@ -958,3 +999,47 @@ where W: Write + ?Sized {
 fn is_illegal_instruction(code: u32) -> bool {
     matches!(code, 0x43000000 /* bc 24, lt, 0x0 */ | 0xB8030000 /* lmw r0, 0(r3) */)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::{test_section, ObjArchitecture, ObjKind};
+
+    #[test]
+    fn test_write_asm_alignment() {
+        let symbol = |name: &str, section, address, size, kind, data_kind, align| ObjSymbol {
+            name: name.to_string(),
+            address,
+            section: Some(section),
+            size,
+            size_known: true,
+            kind,
+            data_kind,
+            align,
+            ..Default::default()
+        };
+        let obj = ObjInfo::new(
+            ObjKind::Relocatable,
+            ObjArchitecture::PowerPc,
+            "test.c".to_string(),
+            vec![
+                symbol("fn_0", 0, 0, 4, ObjSymbolKind::Function, ObjDataKind::Unknown, Some(16)),
+                symbol("lbl_0", 1, 0, 4, ObjSymbolKind::Object, ObjDataKind::Int, None),
+                symbol("lbl_4", 1, 4, 4, ObjSymbolKind::Object, ObjDataKind::Int, Some(8)),
+                symbol("lbl_8", 1, 8, 8, ObjSymbolKind::Object, ObjDataKind::Double, Some(8)),
+            ],
+            vec![
+                test_section(".text", ObjSectionKind::Code, 0, vec![0x4E, 0x80, 0x00, 0x20]),
+                test_section(".data", ObjSectionKind::Data, 0, vec![0; 0x10]),
+            ],
+        );
+        let mut out = vec![];
+        write_asm(&mut out, &obj).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(".balign 16\n# .text:0x0 |"));
+        assert!(out.contains(".balign 8\n# .data:0x8 |"));
+        // No alignment given, or not satisfied by the symbol's address
+        assert!(out.contains("\n\n# .data:0x0 |"));
+        assert!(out.contains("\n\n# .data:0x4 |"));
+    }
+}
@@ -0,0 +1,214 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use anyhow::{bail, ensure, Context, Result};
+use encoding_rs::SHIFT_JIS;
+
+use crate::{array_ref, util::disc::find_root_file};
+
+/// Banner image width in pixels.
+pub const BANNER_WIDTH: usize = 96;
+/// Banner image height in pixels.
+pub const BANNER_HEIGHT: usize = 32;
+
+const IMAGE_OFFSET: usize = 0x20;
+const IMAGE_SIZE: usize = BANNER_WIDTH * BANNER_HEIGHT * 2;
+const INFO_OFFSET: usize = IMAGE_OFFSET + IMAGE_SIZE;
+const INFO_SIZE: usize = 0x140;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BannerKind {
+    /// Single info block (US/JP)
+    Bnr1,
+    /// Six info blocks: English, German, French, Spanish, Italian, Dutch (EU)
+    Bnr2,
+}
+
+/// Text encoding of banner strings.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BannerEncoding {
+    /// Western releases.
+    Latin1,
+    /// Japanese releases.
+    ShiftJis,
+}
+
+impl BannerEncoding {
+    /// Encoding used by discs of the given region, the last character of the game code
+    /// (e.g. `J` in `GALJ`).
+    pub fn for_region(region: u8) -> Self {
+        if region == b'J' {
+            BannerEncoding::ShiftJis
+        } else {
+            BannerEncoding::Latin1
+        }
+    }
+}
+
+/// Per-language game metadata.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct BannerInfo {
+    pub short_title: String,
+    pub short_maker: String,
+    pub title: String,
+    pub maker: String,
+    pub description: String,
+}
+
+/// Parsed `opening.bnr` file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Banner {
+    pub kind: BannerKind,
+    /// RGBA8 pixels, [BANNER_WIDTH] x [BANNER_HEIGHT], row-major
+    pub image: Vec<u8>,
+    pub info: Vec<BannerInfo>,
+}
+
+/// Parses an `opening.bnr` file, decoding BNR1 strings with `encoding`.
+/// BNR2 (European) strings are always decoded as Latin-1.
+pub fn read_banner(data: &[u8], encoding: BannerEncoding) -> Result<Banner> {
+    ensure!(data.len() >= 4, "Banner too small");
+    let (kind, count) = match array_ref!(data, 0, 4) {
+        b"BNR1" => (BannerKind::Bnr1, 1),
+        b"BNR2" => (BannerKind::Bnr2, 6),
+        magic => bail!("Invalid banner magic {:?}", magic),
+    };
+    let size = INFO_OFFSET + INFO_SIZE * count;
+    ensure!(data.len() >= size, "Banner size {:#X} too small, expected {:#X}", data.len(), size);
+
+    let encoding = if kind == BannerKind::Bnr1 { encoding } else { BannerEncoding::Latin1 };
+    let image = decode_rgb5a3(&data[IMAGE_OFFSET..INFO_OFFSET]);
+    let info = (0..count)
+        .map(|i| {
+            let block = &data[INFO_OFFSET + INFO_SIZE * i..INFO_OFFSET + INFO_SIZE * (i + 1)];
+            let string =
+                |start: usize, len: usize| decode_string(&block[start..start + len], encoding);
+            BannerInfo {
+                short_title: string(0x00, 0x20),
+                short_maker: string(0x20, 0x20),
+                title: string(0x40, 0x40),
+                maker: string(0x80, 0x40),
+                description: string(0xC0, 0x80),
+            }
+        })
+        .collect();
+    Ok(Banner { kind, image, info })
+}
+
+/// Reads and parses `opening.bnr` from an unencrypted GameCube disc image. The string
+/// encoding is chosen by the disc's region.
+pub fn read_disc_banner<R>(reader: &mut R) -> Result<Banner>
+where R: Read + Seek + ?Sized {
+    let mut game_code = [0u8; 4];
+    reader.seek(SeekFrom::Start(0))?;
+    reader.read_exact(&mut game_code).context("Failed to read disc header")?;
+    let Some((offset, size)) = find_root_file(reader, "opening.bnr")? else {
+        bail!("Disc has no opening.bnr");
+    };
+    let mut data = vec![0u8; size as usize];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut data).context("Failed to read opening.bnr")?;
+    read_banner(&data, BannerEncoding::for_region(game_code[3]))
+}
+
+/// Decodes a zero-terminated string from a fixed-size field.
+fn decode_string(bytes: &[u8], encoding: BannerEncoding) -> String {
+    let len = bytes.iter().position(|&c| c == 0).unwrap_or(bytes.len());
+    let bytes = &bytes[..len];
+    match encoding {
+        BannerEncoding::Latin1 => bytes.iter().map(|&c| char::from(c)).collect(),
+        BannerEncoding::ShiftJis => SHIFT_JIS.decode_without_bom_handling(bytes).0.into_owned(),
+    }
+}
+
+/// Decodes tiled (4x4) RGB5A3 pixels into RGBA8.
+fn decode_rgb5a3(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0u8; BANNER_WIDTH * BANNER_HEIGHT * 4];
+    let tiles_x = BANNER_WIDTH / 4;
+    for (i, pixel) in data.chunks_exact(2).enumerate() {
+        let (tile, within) = (i / 16, i % 16);
+        let x = (tile % tiles_x) * 4 + within % 4;
+        let y = (tile / tiles_x) * 4 + within / 4;
+        let value = u16::from_be_bytes([pixel[0], pixel[1]]);
+        let rgba = if value & 0x8000 != 0 {
+            // RGB555
+            let c = |shift: u16| {
+                let v = ((value >> shift) & 0x1F) as u8;
+                (v << 3) | (v >> 2)
+            };
+            [c(10), c(5), c(0), 0xFF]
+        } else {
+            // RGB4A3
+            let c = |shift: u16| ((value >> shift) & 0xF) as u8 * 0x11;
+            let a = ((value >> 12) & 0x7) as u8;
+            [c(8), c(4), c(0), (a << 5) | (a << 2) | (a >> 1)]
+        };
+        let offset = (y * BANNER_WIDTH + x) * 4;
+        out[offset..offset + 4].copy_from_slice(&rgba);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::util::disc::GCN_DISC_MAGIC;
+
+    fn test_banner() -> Vec<u8> {
+        let mut data = vec![0u8; INFO_OFFSET + INFO_SIZE];
+        data[..4].copy_from_slice(b"BNR1");
+        // (1, 0): RGB4A3 red, alpha 3; (4, 0): RGB555 blue
+        data[IMAGE_OFFSET + 2..IMAGE_OFFSET + 4].copy_from_slice(&0x3F00u16.to_be_bytes());
+        data[IMAGE_OFFSET + 32..IMAGE_OFFSET + 34].copy_from_slice(&0x801Fu16.to_be_bytes());
+        let info = &mut data[INFO_OFFSET..];
+        info[..6].copy_from_slice(b"\x83\x65\x83\x58\x83\x67");
+        info[0x20..0x28].copy_from_slice(b"Nintendo");
+        info[0xC0..0xC5].copy_from_slice(b"Demo\n");
+        data
+    }
+
+    #[test]
+    fn test_read_banner() {
+        let banner = read_banner(&test_banner(), BannerEncoding::ShiftJis).unwrap();
+        assert_eq!(banner.kind, BannerKind::Bnr1);
+        assert_eq!(banner.info, vec![BannerInfo {
+            short_title: "テスト".to_string(),
+            short_maker: "Nintendo".to_string(),
+            description: "Demo\n".to_string(),
+            ..Default::default()
+        }]);
+        assert_eq!(banner.image.len(), BANNER_WIDTH * BANNER_HEIGHT * 4);
+        assert_eq!(&banner.image[4..8], &[0xFF, 0x00, 0x00, 0x6D]);
+        assert_eq!(&banner.image[16..20], &[0x00, 0x00, 0xFF, 0xFF]);
+        assert!(read_banner(&test_banner()[..INFO_OFFSET], BannerEncoding::ShiftJis).is_err());
+        let latin1 = read_banner(&test_banner(), BannerEncoding::Latin1).unwrap();
+        assert_eq!(latin1.info[0].short_title, "\u{83}e\u{83}X\u{83}g");
+        // Valid Shift-JIS, but a Latin-1 string
+        assert_eq!(decode_string(b"Caf\xE9\0", BannerEncoding::Latin1), "Café");
+        assert_ne!(decode_string(b"Caf\xE9\0", BannerEncoding::ShiftJis), "Café");
+    }
+
+    #[test]
+    fn test_read_disc_banner() {
+        let banner = test_banner();
+        let mut disc = vec![0u8; 0x500];
+        disc[..4].copy_from_slice(b"GTSJ");
+        disc[0x1C..0x20].copy_from_slice(&GCN_DISC_MAGIC.to_be_bytes());
+        disc[0x424..0x428].copy_from_slice(&0x460u32.to_be_bytes());
+        // Root (2 entries), opening.bnr
+        for (i, word) in [0x01000000, 0, 2, 0, 0x500, banner.len() as u32].into_iter().enumerate() {
+            disc[0x460 + i * 4..0x464 + i * 4].copy_from_slice(&word.to_be_bytes());
+        }
+        disc[0x478..0x484].copy_from_slice(b"OPENING.BNR\0");
+        disc.extend_from_slice(&banner);
+
+        let result = read_disc_banner(&mut Cursor::new(disc.as_slice())).unwrap();
+        assert_eq!(result, read_banner(&banner, BannerEncoding::ShiftJis).unwrap());
+        disc[3] = b'E';
+        let result = read_disc_banner(&mut Cursor::new(disc.as_slice())).unwrap();
+        assert_eq!(result, read_banner(&banner, BannerEncoding::Latin1).unwrap());
+        disc[0x478] = b'X';
+        assert!(read_disc_banner(&mut Cursor::new(disc.as_slice())).is_err());
+    }
+}
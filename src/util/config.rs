@@ -620,100 +620,120 @@ where P: AsRef<Path> {
 }
 
 pub fn apply_splits<R>(r: &mut R, obj: &mut ObjInfo) -> Result<()>
+where R: BufRead + ?Sized {
+    parse_splits(r, obj, false)
+}
+
+/// Reads a splits config, inserting each split with [`ObjInfo::add_split`].
+/// Unlike [`apply_splits`], splits must reference a section by name, and
+/// overlapping or conflicting splits are reported as errors.
+pub fn read_splits<R>(r: &mut R, obj: &mut ObjInfo) -> Result<()>
+where R: BufRead + ?Sized {
+    parse_splits(r, obj, true)
+}
+
+fn parse_splits<R>(r: &mut R, obj: &mut ObjInfo, validate: bool) -> Result<()>
 where R: BufRead + ?Sized {
     let mut state = SplitState::None;
-    for result in r.lines() {
+    for (line_num, result) in r.lines().enumerate() {
         let line = match result {
             Ok(line) => line,
             Err(e) => return Err(e.into()),
         };
-        let split_line = parse_split_line(&line, &state)?;
-        match (&mut state, split_line) {
-            (
-                SplitState::None | SplitState::Unit(_) | SplitState::Sections(_),
-                SplitLine::Unit(SplitUnit { name, comment_version }),
-            ) => {
-                obj.link_order.push(ObjUnit {
-                    name: name.clone(),
-                    autogenerated: false,
-                    comment_version,
-                });
-                state = SplitState::Unit(name);
-            }
-            (SplitState::None, SplitLine::UnitSection(SplitSection { name, .. })) => {
-                bail!("Section {} defined outside of unit", name);
-            }
-            (SplitState::None | SplitState::Unit(_), SplitLine::SectionsStart) => {
-                state = SplitState::Sections(0);
+        apply_split_line(&line, &mut state, obj, validate)
+            .with_context(|| format!("Failed to process splits line {}", line_num + 1))?;
+    }
+    Ok(())
+}
+
+fn apply_split_line(
+    line: &str,
+    state: &mut SplitState,
+    obj: &mut ObjInfo,
+    validate: bool,
+) -> Result<()> {
+    let split_line = parse_split_line(line, state)?;
+    match (&mut *state, split_line) {
+        (
+            SplitState::None | SplitState::Unit(_) | SplitState::Sections(_),
+            SplitLine::Unit(SplitUnit { name, comment_version }),
+        ) => {
+            obj.link_order.push(ObjUnit {
+                name: name.clone(),
+                autogenerated: false,
+                comment_version,
+            });
+            *state = SplitState::Unit(name);
+        }
+        (SplitState::None, SplitLine::UnitSection(SplitSection { name, .. })) => {
+            bail!("Section {} defined outside of unit", name);
+        }
+        (SplitState::None | SplitState::Unit(_), SplitLine::SectionsStart) => {
+            *state = SplitState::Sections(0);
+        }
+        (SplitState::Sections(index), SplitLine::Section(SectionDef { name, kind, align })) => {
+            let Some(obj_section) = obj.sections.get_mut(*index) else {
+                bail!(
+                    "Section out of bounds: {} (index {}), object has {} sections",
+                    name,
+                    index,
+                    obj.sections.len()
+                );
+            };
+            if obj_section.rename(name.clone()).is_err() {
+                // Manual section
+                obj_section.kind =
+                    kind.ok_or_else(|| anyhow!("Section '{}' missing type", name))?;
+                obj_section.name = name;
+                obj_section.section_known = true;
             }
-            (SplitState::Sections(index), SplitLine::Section(SectionDef { name, kind, align })) => {
-                let Some(obj_section) = obj.sections.get_mut(*index) else {
-                    bail!(
-                        "Section out of bounds: {} (index {}), object has {} sections",
-                        name,
-                        index,
-                        obj.sections.len()
-                    );
-                };
-                if obj_section.rename(name.clone()).is_err() {
-                    // Manual section
-                    obj_section.kind =
-                        kind.ok_or_else(|| anyhow!("Section '{}' missing type", name))?;
-                    obj_section.name = name;
-                    obj_section.section_known = true;
-                }
-                if let Some(align) = align {
-                    obj_section.align = align as u64;
-                }
-                *index += 1;
+            if let Some(align) = align {
+                obj_section.align = align as u64;
             }
-            (
-                SplitState::Unit(unit),
-                SplitLine::UnitSection(SplitSection {
-                    name,
-                    start,
-                    end,
-                    align,
-                    common,
-                    rename,
-                    skip,
-                }),
-            ) => {
-                ensure!(end >= start, "Invalid split range {:#X}..{:#X}", start, end);
-                let (section_index, _) = match obj.sections.by_name(&name)? {
-                    Some(v) => Ok(v),
-                    None => {
-                        if obj.kind == ObjKind::Executable {
-                            obj.sections.with_range(start..end)
-                        } else {
-                            Err(anyhow!("Section {} not found", name))
-                        }
+            *index += 1;
+        }
+        (
+            SplitState::Unit(unit),
+            SplitLine::UnitSection(SplitSection { name, start, end, align, common, rename, skip }),
+        ) => {
+            ensure!(end >= start, "Invalid split range {:#X}..{:#X}", start, end);
+            let (section_index, _) = match obj.sections.by_name(&name)? {
+                Some(v) => Ok(v),
+                None => {
+                    if obj.kind == ObjKind::Executable && !validate {
+                        obj.sections.with_range(start..end)
+                    } else {
+                        Err(anyhow!("Section {} not found", name))
                     }
-                }?;
-                let section = obj.sections.get_mut(section_index).unwrap();
-                let section_end = (section.address + section.size) as u32;
-                ensure!(
-                    section.contains_range(start..end)
-                        || (start == section_end && end == section_end),
-                    "Section {} ({:#010X}..{:#010X}) does not contain range {:#010X}..{:#010X}",
-                    name,
-                    section.address,
-                    section.address + section.size,
-                    start,
-                    end
-                );
-                section.splits.push(start, ObjSplit {
-                    unit: unit.clone(),
-                    end,
-                    align,
-                    common,
-                    autogenerated: false,
-                    skip,
-                    rename,
-                });
+                }
+            }?;
+            let section = obj.sections.get_mut(section_index).unwrap();
+            let section_end = (section.address + section.size) as u32;
+            ensure!(
+                section.contains_range(start..end) || (start == section_end && end == section_end),
+                "Section {} ({:#010X}..{:#010X}) does not contain range {:#010X}..{:#010X}",
+                name,
+                section.address,
+                section.address + section.size,
+                start,
+                end
+            );
+            let split = ObjSplit {
+                unit: unit.clone(),
+                end,
+                align,
+                common,
+                autogenerated: false,
+                skip,
+                rename,
+            };
+            if validate {
+                obj.add_split(section_index, start, split)?;
+            } else {
+                section.splits.push(start, split);
             }
-            _ => {}
         }
+        _ => {}
     }
     Ok(())
 }
@@ -17,8 +17,9 @@ use objdiff_core::{
     },
 };
 use object::RelocationFlags;
+use serde::{Deserialize, Serialize};
 
-use crate::obj::{ObjInfo, ObjReloc, ObjSection, ObjSymbol};
+use crate::obj::{NamedReloc, ObjInfo, ObjReloc, ObjSection, ObjSymbol};
 
 /// Processes code for a PPC function using objdiff-core.
 /// Returns [ProcessCodeResult] for other objdiff-core functions to accept.
@@ -252,11 +253,41 @@ fn print_line(ins_diff: &ObjInsDiff, base_addr: u64) -> Vec<Span> {
     line
 }
 
+/// A relocation in the shape objdiff expects, for exporting to objdiff tooling.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ExportedReloc {
+    pub address: u32,
+    /// objdiff-core relocations have no equivalent to `module`, so it's only conveyed here.
+    #[serde(flatten)]
+    pub reloc: NamedReloc,
+}
+
+/// Exports the relocations of a section, ordered by address.
+pub fn export_relocations(obj: &ObjInfo, section: &ObjSection) -> Vec<ExportedReloc> {
+    section
+        .relocations
+        .iter()
+        .map(|(address, reloc)| ExportedReloc { address, reloc: reloc.to_named(&obj.symbols) })
+        .collect()
+}
+
+/// Converts the relocations of a section to [objdiff_core::obj::ObjReloc]s, ordered by address.
+pub fn objdiff_relocations(
+    obj: &ObjInfo,
+    section: &ObjSection,
+) -> Vec<objdiff_core::obj::ObjReloc> {
+    section
+        .relocations
+        .iter()
+        .map(|(address, reloc)| to_objdiff_reloc(obj, address, reloc))
+        .collect()
+}
+
 /// Converts an [ObjReloc] to an [objdiff_core::obj::ObjReloc].
 fn to_objdiff_reloc(obj: &ObjInfo, address: u32, reloc: &ObjReloc) -> objdiff_core::obj::ObjReloc {
     let target_symbol = &obj.symbols[reloc.target_symbol];
     let target_section = target_symbol.section.map(|i| &obj.sections[i]);
-    let (r_offset, r_type) = reloc.to_elf(address);
+    let (r_offset, r_type) = obj.architecture.arch().reloc_to_elf(reloc, address);
     objdiff_core::obj::ObjReloc {
         flags: RelocationFlags::Elf { r_type },
         address: r_offset,
@@ -303,6 +334,7 @@ fn to_objdiff_symbol(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::obj::test_section;
 
     macro_rules! ins_diff {
         ($kind:expr) => {
@@ -381,4 +413,64 @@ mod tests {
         ];
         assert_eq!(calc_diff_ranges(&diff, &diff, 3), vec![0..7, 9..15]);
     }
+
+    #[test]
+    fn test_export_relocations() {
+        use crate::obj::{
+            ObjArchitecture, ObjKind, ObjRelocKind, ObjRelocations, ObjSectionKind, ObjSymbolKind,
+        };
+
+        let symbol = |name: &str, address: u64, section: Option<usize>| ObjSymbol {
+            name: name.to_string(),
+            address,
+            section,
+            kind: ObjSymbolKind::Function,
+            ..Default::default()
+        };
+        let mut obj = ObjInfo::new(
+            ObjKind::Relocatable,
+            ObjArchitecture::PowerPc,
+            "test".to_string(),
+            vec![symbol("fn_a", 0, Some(0)), symbol("fn_ext", 0, None)],
+            vec![ObjSection {
+                elf_index: 1,
+                ..test_section(".text", ObjSectionKind::Code, 0, vec![0x60; 0x10])
+            }],
+        );
+        obj.sections[0].relocations = ObjRelocations::new(vec![
+            (4, ObjReloc {
+                kind: ObjRelocKind::PpcAddr16Ha,
+                target_symbol: 0,
+                addend: 8,
+                module: None,
+            }),
+            (8, ObjReloc {
+                kind: ObjRelocKind::PpcRel24,
+                target_symbol: 1,
+                addend: 0,
+                module: Some(2),
+            }),
+        ])
+        .unwrap();
+
+        let section = &obj.sections[0];
+        let exported = export_relocations(&obj, section);
+        let json = serde_json::to_string(&exported).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"address":4,"kind":"ha","target":"fn_a","addend":8},{"address":8,"kind":"rel24","target":"fn_ext","addend":0,"module":2}]"#
+        );
+        assert_eq!(serde_json::from_str::<Vec<ExportedReloc>>(&json).unwrap(), exported);
+
+        let objdiff = objdiff_relocations(&obj, section);
+        assert_eq!(objdiff.len(), exported.len());
+        for (exported, objdiff) in exported.iter().zip(&objdiff) {
+            let reloc = exported.reloc.resolve(|_| Some(0)).unwrap();
+            let (r_offset, r_type) = obj.architecture.arch().reloc_to_elf(&reloc, exported.address);
+            assert_eq!(objdiff.address, r_offset);
+            assert_eq!(objdiff.flags, RelocationFlags::Elf { r_type });
+            assert_eq!(objdiff.target.name, exported.reloc.target);
+            assert_eq!(objdiff.target.addend, exported.reloc.addend);
+        }
+    }
 }
@@ -0,0 +1,167 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use anyhow::{ensure, Context, Result};
+
+use crate::util::{
+    file::read_c_bytes,
+    reader::{Endian, FromReader, ToWriter},
+};
+
+/// Offset of the apploader within a GameCube/Wii disc image.
+pub const APPLOADER_OFFSET: u64 = 0x2440;
+
+/// Magic word at offset 0x1C of a GameCube disc image.
+pub const GCN_DISC_MAGIC: u32 = 0xC2339F3D;
+
+const FST_OFFSET_OFFSET: u64 = 0x424;
+const FST_ENTRY_SIZE: u64 = 12;
+
+const APPLOADER_HEADER_SIZE: usize = 0x20;
+const APPLOADER_DATE_SIZE: usize = 0x10;
+
+/// Disc apploader: the loader the IPL runs to load the main DOL.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Apploader {
+    /// Build date, e.g. `2001/10/18`.
+    pub date: String,
+    /// Entry point address.
+    pub entry: u32,
+    /// Size of the apploader code.
+    pub size: u32,
+    /// Size of the trailer following the code.
+    pub trailer_size: u32,
+    /// Apploader code followed by the trailer, excluding the header.
+    pub data: Vec<u8>,
+}
+
+/// Reads an apploader from the current position. For a full disc image, seek to
+/// [APPLOADER_OFFSET] first.
+pub fn read_apploader<R>(reader: &mut R) -> Result<Apploader>
+where R: Read + Seek + ?Sized {
+    let start = reader.stream_position()?;
+    let end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(start))?;
+
+    let mut date = [0u8; APPLOADER_DATE_SIZE];
+    reader.read_exact(&mut date).context("Failed to read apploader header")?;
+    let entry = u32::from_reader(reader, Endian::Big)?;
+    let size = u32::from_reader(reader, Endian::Big)?;
+    let trailer_size = u32::from_reader(reader, Endian::Big)?;
+    let _padding = u32::from_reader(reader, Endian::Big)?;
+
+    let data_size = size as u64 + trailer_size as u64;
+    let available = end.saturating_sub(start + APPLOADER_HEADER_SIZE as u64);
+    ensure!(
+        data_size <= available,
+        "Apploader size {:#X} + trailer {:#X} exceeds available data {:#X}",
+        size,
+        trailer_size,
+        available
+    );
+    let mut data = vec![0u8; data_size as usize];
+    reader.read_exact(&mut data).context("Failed to read apploader data")?;
+
+    let date_len = date.iter().position(|&c| c == 0).unwrap_or(date.len());
+    let date = String::from_utf8_lossy(&date[..date_len]).into_owned();
+    Ok(Apploader { date, entry, size, trailer_size, data })
+}
+
+/// Writes an apploader header followed by its code and trailer.
+pub fn write_apploader<W>(w: &mut W, apploader: &Apploader) -> Result<()>
+where W: Write + ?Sized {
+    ensure!(
+        apploader.data.len() as u64 == apploader.size as u64 + apploader.trailer_size as u64,
+        "Apploader data size {:#X} doesn't match size {:#X} + trailer {:#X}",
+        apploader.data.len(),
+        apploader.size,
+        apploader.trailer_size
+    );
+    ensure!(
+        apploader.date.len() < APPLOADER_DATE_SIZE,
+        "Apploader date '{}' too long",
+        apploader.date
+    );
+    let mut date = [0u8; APPLOADER_DATE_SIZE];
+    date[..apploader.date.len()].copy_from_slice(apploader.date.as_bytes());
+    w.write_all(&date)?;
+    apploader.entry.to_writer(w, Endian::Big)?;
+    apploader.size.to_writer(w, Endian::Big)?;
+    apploader.trailer_size.to_writer(w, Endian::Big)?;
+    0u32.to_writer(w, Endian::Big)?;
+    w.write_all(&apploader.data)?;
+    Ok(())
+}
+
+/// Locates a file in the root directory of an unencrypted GameCube disc image by name
+/// (case-insensitive), returning its data offset and size.
+pub fn find_root_file<R>(reader: &mut R, name: &str) -> Result<Option<(u64, u32)>>
+where R: Read + Seek + ?Sized {
+    reader.seek(SeekFrom::Start(0x1C))?;
+    let magic = u32::from_reader(reader, Endian::Big)?;
+    ensure!(magic == GCN_DISC_MAGIC, "Not a GameCube disc image (magic {:#010X})", magic);
+    reader.seek(SeekFrom::Start(FST_OFFSET_OFFSET))?;
+    let fst_offset = u32::from_reader(reader, Endian::Big)? as u64;
+
+    let read_entry = |reader: &mut R, index: u32| -> Result<(u32, u32, u32)> {
+        reader.seek(SeekFrom::Start(fst_offset + index as u64 * FST_ENTRY_SIZE))?;
+        let kind_name = u32::from_reader(reader, Endian::Big)?;
+        let offset = u32::from_reader(reader, Endian::Big)?;
+        let length = u32::from_reader(reader, Endian::Big)?;
+        Ok((kind_name, offset, length))
+    };
+    let (_, _, count) = read_entry(reader, 0).context("Failed to read FST root")?;
+    let string_table = fst_offset + count as u64 * FST_ENTRY_SIZE;
+
+    let mut index = 1;
+    while index < count {
+        let (kind_name, offset, length) = read_entry(reader, index)?;
+        if kind_name >> 24 != 0 {
+            // Directory: skip to the entry following its contents
+            ensure!(length > index, "Invalid FST directory entry {}", index);
+            index = length;
+            continue;
+        }
+        let entry_name = read_c_bytes(reader, string_table + (kind_name & 0xFFFFFF) as u64)?;
+        if entry_name.eq_ignore_ascii_case(name.as_bytes()) {
+            return Ok(Some((offset as u64, length)));
+        }
+        index += 1;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_apploader() {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"2001/10/18\0\0\0\0\0\0");
+        data.extend_from_slice(&0x81200150u32.to_be_bytes());
+        data.extend_from_slice(&0x10u32.to_be_bytes());
+        data.extend_from_slice(&8u32.to_be_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend((0..0x18).map(|i| i as u8));
+
+        let apploader = read_apploader(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(apploader.date, "2001/10/18");
+        assert_eq!(
+            (apploader.entry, apploader.size, apploader.trailer_size),
+            (0x81200150, 0x10, 8)
+        );
+        assert_eq!(apploader.data, &data[APPLOADER_HEADER_SIZE..]);
+
+        let mut out = Vec::new();
+        write_apploader(&mut out, &apploader).unwrap();
+        assert_eq!(out, data);
+
+        // Declared size exceeds the available bytes
+        let mut reader = Cursor::new(&data[..data.len() - 1]);
+        assert!(read_apploader(&mut reader).is_err());
+        let bad = Apploader { trailer_size: 0, ..apploader };
+        assert!(write_apploader(&mut Vec::new(), &bad).is_err());
+    }
+}
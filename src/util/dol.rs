@@ -435,6 +435,7 @@ pub fn process_dol(buf: &[u8], name: &str) -> Result<ObjInfo> {
             file_offset: dol_section.file_offset as u64,
             section_known: known,
             splits: Default::default(),
+            compressed_size_cache: Default::default(),
         });
     }
 
@@ -465,6 +466,7 @@ pub fn process_dol(buf: &[u8], name: &str) -> Result<ObjInfo> {
                 file_offset: 0,
                 section_known: false,
                 splits: Default::default(),
+                compressed_size_cache: Default::default(),
             });
         }
 
@@ -485,6 +487,7 @@ pub fn process_dol(buf: &[u8], name: &str) -> Result<ObjInfo> {
                 file_offset: 0,
                 section_known: false,
                 splits: Default::default(),
+                compressed_size_cache: Default::default(),
             });
             let mut obj = ObjInfo::new(
                 ObjKind::Executable,
@@ -512,6 +515,7 @@ pub fn process_dol(buf: &[u8], name: &str) -> Result<ObjInfo> {
                         file_offset: 0,
                         section_known: false,
                         splits: Default::default(),
+                        compressed_size_cache: Default::default(),
                     });
                     sections.push(ObjSection {
                         name: ".sbss".to_string(),
@@ -526,6 +530,7 @@ pub fn process_dol(buf: &[u8], name: &str) -> Result<ObjInfo> {
                         file_offset: 0,
                         section_known: false,
                         splits: Default::default(),
+                        compressed_size_cache: Default::default(),
                     });
                 }
                 n => bail!("Invalid number of BSS sections: {}", n),
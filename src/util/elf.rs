@@ -1,18 +1,21 @@
 use std::{
     collections::{hash_map, HashMap},
-    io::Cursor,
+    ffi::OsStr,
+    io::{Cursor, Read, Write},
     num::NonZeroU64,
     path::Path,
+    str::FromStr,
 };
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
+use argp::FromArgValue;
 use cwdemangle::demangle;
 use flagset::Flags;
 use indexmap::IndexMap;
 use objdiff_core::obj::split_meta::{SplitMeta, SHT_SPLITMETA, SPLITMETA_SECTION};
 use object::{
     elf,
-    elf::{SHF_ALLOC, SHF_EXECINSTR, SHF_WRITE, SHT_NOBITS, SHT_PROGBITS},
+    elf::{SHT_NOBITS, SHT_PROGBITS},
     write::{
         elf::{ProgramHeader, Rel, SectionHeader, SectionIndex, SymbolIndex, Writer},
         StringId,
@@ -46,7 +49,20 @@ enum BoundaryState {
 pub fn process_elf<P>(path: P) -> Result<ObjInfo>
 where P: AsRef<Path> {
     let file = map_file(path)?;
-    let obj_file = object::read::File::parse(file.as_slice())?;
+    process_elf_buf(file.as_slice())
+}
+
+/// Reads an ELF object from a reader. See [process_elf].
+pub fn read_elf<R>(reader: &mut R) -> Result<ObjInfo>
+where R: Read + ?Sized {
+    let mut buf = vec![];
+    reader.read_to_end(&mut buf)?;
+    process_elf_buf(&buf)
+}
+
+/// Reads an ELF object from memory. See [process_elf].
+pub fn process_elf_buf(buf: &[u8]) -> Result<ObjInfo> {
+    let obj_file = object::read::File::parse(buf)?;
     let architecture = match obj_file.architecture() {
         Architecture::PowerPc => ObjArchitecture::PowerPc,
         arch => bail!("Unexpected architecture: {arch:?}"),
@@ -100,6 +116,7 @@ where P: AsRef<Path> {
             file_offset: section.file_range().map(|(v, _)| v).unwrap_or_default(),
             section_known: true,
             splits: Default::default(),
+            compressed_size_cache: Default::default(),
         });
     }
 
@@ -345,10 +362,13 @@ where P: AsRef<Path> {
         // TODO rebuild common symbols
     }
 
+    let mut relocations = vec![];
     for (section_idx, section) in obj_file.sections().enumerate() {
-        let out_section = match section_indexes[section_idx].and_then(|idx| sections.get_mut(idx)) {
-            Some(s) => s,
-            None => continue,
+        let Some(out_section_idx) = section_indexes[section_idx] else {
+            continue;
+        };
+        let Some(out_section) = sections.get(out_section_idx) else {
+            continue;
         };
         // Generate relocations
         for (address, reloc) in section.relocations() {
@@ -357,11 +377,14 @@ where P: AsRef<Path> {
             else {
                 continue;
             };
-            out_section.relocations.insert(address as u32, reloc)?;
+            relocations.push((out_section_idx, address as u32, reloc));
         }
     }
 
     let mut obj = ObjInfo::new(kind, architecture, obj_name, symbols, sections);
+    for (section_index, address, reloc) in relocations {
+        obj.insert_relocation(section_index, address, reloc)?;
+    }
     obj.entry = NonZeroU64::new(obj_file.entry()).map(|n| n.get());
     obj.mw_comment = mw_comment.map(|(header, _)| header);
     obj.split_meta = split_meta;
@@ -376,7 +399,85 @@ where P: AsRef<Path> {
     Ok(obj)
 }
 
+/// Writes a big-endian PowerPC ELF object to `w`. See [write_elf].
+/// Fill used for the padding after each section, up to the next 32-byte boundary.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PaddingPolicy {
+    /// `0x00`
+    Zero,
+    /// `0xFF`
+    Ones,
+    /// PowerPC `nop` (`0x60000000`), aligned to the section start.
+    Nop,
+}
+
+impl PaddingPolicy {
+    /// The padding byte at the given offset from the start of the section.
+    pub fn byte_at(self, offset: usize) -> u8 {
+        match self {
+            PaddingPolicy::Zero => 0,
+            PaddingPolicy::Ones => 0xFF,
+            PaddingPolicy::Nop => 0x60000000u32.to_be_bytes()[offset % 4],
+        }
+    }
+}
+
+impl FromStr for PaddingPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "zero" => Self::Zero,
+            "ff" => Self::Ones,
+            "nop" => Self::Nop,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl FromArgValue for PaddingPolicy {
+    fn from_arg_value(value: &OsStr) -> Result<Self, String> {
+        String::from_arg_value(value)
+            .and_then(|s| Self::from_str(&s).map_err(|_| "Invalid padding policy".to_string()))
+    }
+}
+
+/// Padding policies by section kind.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SectionPadding {
+    pub code: PaddingPolicy,
+    pub data: PaddingPolicy,
+}
+
+impl Default for SectionPadding {
+    fn default() -> Self { Self { code: PaddingPolicy::Nop, data: PaddingPolicy::Zero } }
+}
+
+impl SectionPadding {
+    pub fn for_kind(self, kind: ObjSectionKind) -> PaddingPolicy {
+        match kind {
+            ObjSectionKind::Code => self.code,
+            _ => self.data,
+        }
+    }
+}
+
+pub fn write_elf_to<W>(w: &mut W, obj: &ObjInfo, export_all: bool) -> Result<()>
+where W: Write + ?Sized {
+    w.write_all(&write_elf(obj, export_all)?)?;
+    Ok(())
+}
+
 pub fn write_elf(obj: &ObjInfo, export_all: bool) -> Result<Vec<u8>> {
+    write_elf_with_padding(obj, export_all, SectionPadding::default())
+}
+
+/// Same as [write_elf], filling the gaps between section data with `padding`.
+pub fn write_elf_with_padding(
+    obj: &ObjInfo,
+    export_all: bool,
+    padding: SectionPadding,
+) -> Result<Vec<u8>> {
     let mut out_data = Vec::new();
     let mut writer = Writer::new(Endianness::Big, false, &mut out_data);
 
@@ -634,7 +735,7 @@ pub fn write_elf(obj: &ObjInfo, export_all: bool) -> Result<Vec<u8>> {
     }
 
     for ((_, section), out_section) in obj.sections.iter().zip(&mut out_sections) {
-        if section.kind == ObjSectionKind::Bss {
+        if section.kind.elf_type() == SHT_NOBITS {
             continue;
         }
         ensure!(section.data.len() as u64 == section.size);
@@ -708,27 +809,34 @@ pub fn write_elf(obj: &ObjInfo, export_all: bool) -> Result<Vec<u8>> {
         }
     }
 
+    // Policy and file offset of the last section written, for the gap that follows it
+    let mut gap_padding = None;
     for ((_, section), out_section) in obj.sections.iter().zip(&out_sections) {
-        if section.kind == ObjSectionKind::Bss {
+        if section.kind.elf_type() == SHT_NOBITS {
             continue;
         }
-        writer.write_align(32);
+        let (policy, start) = gap_padding.unwrap_or((PaddingPolicy::Zero, writer.len()));
+        let fill = (writer.len()..out_section.offset)
+            .map(|offset| policy.byte_at(offset - start))
+            .collect::<Vec<_>>();
+        writer.write(&fill);
         ensure!(writer.len() == out_section.offset);
         if obj.kind == ObjKind::Relocatable {
             write_relocatable_section_data(&mut writer, section)?;
         } else {
             writer.write(&section.data);
         }
+        gap_padding = Some((padding.for_kind(section.kind), out_section.offset));
     }
 
-    for ((_, section), out_section) in obj.sections.iter().zip(&out_sections) {
+    for ((section_index, section), out_section) in obj.sections.iter().zip(&out_sections) {
         if section.relocations.is_empty() {
             continue;
         }
         writer.write_align_relocation();
         ensure!(writer.len() == out_section.rela_offset);
-        for (addr, reloc) in section.relocations.iter() {
-            let (r_offset, r_type) = reloc.to_elf(addr);
+        for (addr, reloc) in obj.relocations_in_order(section_index) {
+            let (r_offset, r_type) = obj.architecture.arch().reloc_to_elf(reloc, addr);
             let r_sym = symbol_map[reloc.target_symbol]
                 .ok_or_else(|| anyhow!("Relocation against stripped symbol"))?;
             writer.write_relocation(true, &Rel { r_offset, r_sym, r_type, r_addend: reloc.addend });
@@ -766,17 +874,8 @@ pub fn write_elf(obj: &ObjInfo, export_all: bool) -> Result<Vec<u8>> {
     for ((_, section), out_section) in obj.sections.iter().zip(&out_sections) {
         writer.write_section_header(&SectionHeader {
             name: Some(out_section.name),
-            sh_type: match section.kind {
-                ObjSectionKind::Code | ObjSectionKind::Data | ObjSectionKind::ReadOnlyData => {
-                    SHT_PROGBITS
-                }
-                ObjSectionKind::Bss => SHT_NOBITS,
-            },
-            sh_flags: match section.kind {
-                ObjSectionKind::Code => SHF_ALLOC | SHF_EXECINSTR,
-                ObjSectionKind::Data | ObjSectionKind::Bss => SHF_ALLOC | SHF_WRITE,
-                ObjSectionKind::ReadOnlyData => SHF_ALLOC,
-            } as u64,
+            sh_type: section.kind.elf_type(),
+            sh_flags: section.kind.elf_flags() as u64,
             sh_addr: section.address,
             sh_offset: out_section.offset as u64,
             sh_size: section.size,
@@ -899,15 +998,9 @@ fn to_obj_symbol(
 
 pub fn to_obj_reloc_kind(flags: RelocationFlags) -> Result<ObjRelocKind> {
     Ok(match flags {
-        RelocationFlags::Elf { r_type } => match r_type {
-            elf::R_PPC_ADDR32 | elf::R_PPC_UADDR32 => ObjRelocKind::Absolute,
-            elf::R_PPC_ADDR16_LO => ObjRelocKind::PpcAddr16Lo,
-            elf::R_PPC_ADDR16_HI => ObjRelocKind::PpcAddr16Hi,
-            elf::R_PPC_ADDR16_HA => ObjRelocKind::PpcAddr16Ha,
-            elf::R_PPC_REL24 => ObjRelocKind::PpcRel24,
-            elf::R_PPC_REL14 => ObjRelocKind::PpcRel14,
-            elf::R_PPC_EMB_SDA21 => ObjRelocKind::PpcEmbSda21,
-            kind => bail!("Unhandled ELF relocation type: {kind}"),
+        RelocationFlags::Elf { r_type } => match ObjRelocKind::from_elf(r_type) {
+            Some(kind) => kind,
+            None => bail!("Unhandled ELF relocation type: {r_type}"),
         },
         flags => bail!("Unhandled relocation type: {:?}", flags),
     })
@@ -941,9 +1034,10 @@ fn to_obj_reloc(
         }
         SymbolKind::Section => {
             let addend = if reloc.has_implicit_addend() {
-                let addend = u32::from_be_bytes(
-                    section_data[address as usize..address as usize + 4].try_into()?,
-                ) as i64;
+                let data = section_data
+                    .get(address as usize..address as usize + 4)
+                    .ok_or_else(|| anyhow!("Relocation out of bounds at {:#010X}", address))?;
+                let addend = u32::from_be_bytes(data.try_into()?) as i64;
                 match reloc_kind {
                     ObjRelocKind::Absolute => addend,
                     _ => bail!("Unsupported implicit relocation type {reloc_kind:?}"),
@@ -990,3 +1084,195 @@ fn write_relocatable_section_data(w: &mut Writer, section: &ObjSection) -> Resul
     w.write(&section.data[current_address..]);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::{test_section, ObjRelocations, ObjSymbolScope};
+
+    #[test]
+    fn test_write_elf_round_trip() {
+        let section = |name: &str, kind, data: Vec<u8>, size| ObjSection {
+            size,
+            ..test_section(name, kind, 0, data)
+        };
+        let symbol = |name: &str, section, scope, kind| {
+            let mut flags = ObjSymbolFlagSet::default();
+            flags.set_scope(scope);
+            ObjSymbol {
+                name: name.to_string(),
+                section: Some(section),
+                size: 8,
+                size_known: true,
+                flags,
+                kind,
+                ..Default::default()
+            }
+        };
+        let mut obj = ObjInfo::new(
+            ObjKind::Relocatable,
+            ObjArchitecture::PowerPc,
+            "test.o".to_string(),
+            vec![
+                symbol("fn_global", 0, ObjSymbolScope::Global, ObjSymbolKind::Function),
+                symbol("gLocal", 1, ObjSymbolScope::Local, ObjSymbolKind::Object),
+            ],
+            vec![
+                section(".text", ObjSectionKind::Code, vec![0x48, 0, 0, 1, 0x4E, 0x80, 0, 0x20], 8),
+                section(".bss", ObjSectionKind::Bss, vec![], 8),
+            ],
+        );
+        obj.sections[0].relocations = ObjRelocations::new(vec![(0, ObjReloc {
+            kind: ObjRelocKind::PpcRel24,
+            target_symbol: 0,
+            addend: 0,
+            module: None,
+        })])
+        .unwrap();
+
+        let mut out = vec![];
+        write_elf_to(&mut out, &obj, false).unwrap();
+        let file = object::read::File::parse(out.as_slice()).unwrap();
+        assert_eq!(file.kind(), ObjectKind::Relocatable);
+        assert_eq!(file.section_by_name(".bss").unwrap().kind(), SectionKind::UninitializedData);
+        assert_eq!(file.section_by_name(".text").unwrap().kind(), SectionKind::Text);
+
+        let read = process_elf_buf(&out).unwrap();
+        let (text_index, text) = read.sections.by_name(".text").unwrap().unwrap();
+        assert_eq!(text.kind, ObjSectionKind::Code);
+        assert_eq!(text.data, obj.sections[0].data);
+        let (_, bss) = read.sections.by_name(".bss").unwrap().unwrap();
+        assert_eq!((bss.kind, bss.size), (ObjSectionKind::Bss, 8));
+
+        let (fn_index, fn_global) = read.symbols.by_name("fn_global").unwrap().unwrap();
+        assert_eq!(fn_global.section, Some(text_index));
+        assert_eq!(fn_global.flags.scope(), ObjSymbolScope::Global);
+        let (_, local) = read.symbols.by_name("gLocal").unwrap().unwrap();
+        assert_eq!(local.flags.scope(), ObjSymbolScope::Local);
+
+        let relocations = text.relocations.iter().collect::<Vec<_>>();
+        assert_eq!(relocations.len(), 1);
+        assert_eq!(relocations[0].0, 0);
+        assert_eq!(relocations[0].1.kind, ObjRelocKind::PpcRel24);
+        assert_eq!(relocations[0].1.target_symbol, fn_index);
+    }
+
+    #[test]
+    fn test_write_elf_padding() {
+        let mut obj = ObjInfo::new(
+            ObjKind::Executable,
+            ObjArchitecture::PowerPc,
+            "test".to_string(),
+            vec![],
+            vec![
+                test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0x4E, 0x80, 0, 0x20]),
+                test_section(".data", ObjSectionKind::Data, 0x80003120, vec![1, 2, 3]),
+                test_section(".rodata", ObjSectionKind::ReadOnlyData, 0x80003140, vec![4; 4]),
+            ],
+        );
+        obj.entry = Some(0x80003100);
+        let gaps = |padding| {
+            let out = write_elf_with_padding(&obj, false, padding).unwrap();
+            let file = object::read::File::parse(out.as_slice()).unwrap();
+            let range = |name| {
+                let (offset, size) = file.section_by_name(name).unwrap().file_range().unwrap();
+                offset as usize..(offset + size) as usize
+            };
+            let (text, data, rodata) = (range(".text"), range(".data"), range(".rodata"));
+            (out[text.end..data.start].to_vec(), out[data.end..rodata.start].to_vec())
+        };
+
+        // Code gaps default to nops, data gaps to zeros
+        let (code_gap, data_gap) = gaps(SectionPadding::default());
+        assert_eq!(code_gap.len(), 28);
+        assert!(code_gap.chunks(4).all(|c| c == [0x60, 0, 0, 0]));
+        assert_eq!(data_gap, vec![0; 29]);
+
+        let ones = SectionPadding { code: PaddingPolicy::Zero, data: PaddingPolicy::Ones };
+        let (code_gap, data_gap) = gaps(ones);
+        assert_eq!((code_gap, data_gap), (vec![0; 28], vec![0xFF; 29]));
+    }
+
+    /// Builds a small relocatable object, as emitted by mwcc.
+    fn fixture_elf(rel24_type: u32) -> Vec<u8> {
+        use object::write;
+
+        let mut out =
+            write::Object::new(object::BinaryFormat::Elf, Architecture::PowerPc, Endianness::Big);
+        out.add_file_symbol(b"fixture.c".to_vec());
+        let text = out.add_section(vec![], b".text".to_vec(), SectionKind::Text);
+        out.append_section_data(
+            text,
+            &[
+                0x3C, 0x60, 0x00, 0x00, // lis r3, gData@ha
+                0x38, 0x63, 0x00, 0x00, // addi r3, r3, gData@l
+                0x48, 0x00, 0x00, 0x01, // bl helper
+                0x4E, 0x80, 0x00, 0x20, // blr
+            ],
+            4,
+        );
+        let data = out.add_section(vec![], b".data".to_vec(), SectionKind::Data);
+        out.append_section_data(data, &[0; 8], 4);
+        let mut add_symbol = |name: &str, value, section, kind, scope| {
+            out.add_symbol(write::Symbol {
+                name: name.as_bytes().to_vec(),
+                value,
+                size: 4,
+                kind,
+                scope,
+                weak: false,
+                section: write::SymbolSection::Section(section),
+                flags: object::SymbolFlags::None,
+            })
+        };
+        add_symbol("main", 0, text, SymbolKind::Text, SymbolScope::Dynamic);
+        let helper = add_symbol("helper", 0xC, text, SymbolKind::Text, SymbolScope::Compilation);
+        let data_symbol = add_symbol("gData", 0, data, SymbolKind::Data, SymbolScope::Dynamic);
+        for (offset, symbol, r_type) in [
+            (2, data_symbol, elf::R_PPC_ADDR16_HA),
+            (6, data_symbol, elf::R_PPC_ADDR16_LO),
+            (8, helper, rel24_type),
+        ] {
+            out.add_relocation(text, write::Relocation {
+                offset,
+                symbol,
+                addend: 0,
+                flags: RelocationFlags::Elf { r_type },
+            })
+            .unwrap();
+        }
+        out.write().unwrap()
+    }
+
+    #[test]
+    fn test_read_elf() {
+        let obj = read_elf(&mut fixture_elf(elf::R_PPC_REL24).as_slice()).unwrap();
+        assert_eq!(obj.kind, ObjKind::Relocatable);
+        let (text_index, text) = obj.sections.by_name(".text").unwrap().unwrap();
+        assert_eq!(text.kind, ObjSectionKind::Code);
+        let (data_index, _) = obj.sections.by_name(".data").unwrap().unwrap();
+
+        let (_, main) = obj.symbols.by_name("main").unwrap().unwrap();
+        assert_eq!((main.section, main.kind), (Some(text_index), ObjSymbolKind::Function));
+        assert_eq!(main.flags.scope(), ObjSymbolScope::Global);
+        let (helper_index, helper) = obj.symbols.by_name("helper").unwrap().unwrap();
+        assert_eq!((helper.address, helper.flags.scope()), (0xC, ObjSymbolScope::Local));
+        let (data_symbol, g_data) = obj.symbols.by_name("gData").unwrap().unwrap();
+        assert_eq!((g_data.section, g_data.kind), (Some(data_index), ObjSymbolKind::Object));
+
+        let relocations = text
+            .relocations
+            .iter()
+            .map(|(address, reloc)| (address, reloc.kind, reloc.target_symbol))
+            .collect::<Vec<_>>();
+        assert_eq!(relocations, vec![
+            (0, ObjRelocKind::PpcAddr16Ha, data_symbol),
+            (4, ObjRelocKind::PpcAddr16Lo, data_symbol),
+            (8, ObjRelocKind::PpcRel24, helper_index),
+        ]);
+
+        // Unsupported relocation types are reported
+        let err = read_elf(&mut fixture_elf(elf::R_PPC_ADDR24).as_slice()).unwrap_err();
+        assert!(format!("{err:?}").contains("Unhandled ELF relocation type"));
+    }
+}
@@ -0,0 +1,392 @@
+//! Decoding of MWCC exception table (`extab`) records.
+//!
+//! A record starts with a 4-byte header, followed by a list of PC ranges terminated by a
+//! zero word. Each range refers to a chain of actions, stored after the range list, that
+//! are run when unwinding through it. Function and type pointers within actions are
+//! relocated, so they're reported with their offset in the record for resolution.
+use std::{collections::BTreeMap, fmt::Write, io::Cursor};
+
+use anyhow::{bail, Context, Result};
+
+use crate::util::reader::{Endian, FromReader};
+
+/// Decoded extab record. See [decode].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ExTabInfo {
+    pub saved_gprs: u8,
+    pub saved_fprs: u8,
+    pub saved_cr: bool,
+    pub has_frame_pointer: bool,
+    pub large_frame: bool,
+    pub has_elf_vector: bool,
+    pub frame_size: u16,
+    pub ranges: Vec<ExTabRange>,
+    /// Action chains by offset within the record.
+    pub actions: BTreeMap<u32, Vec<ExTabAction>>,
+}
+
+/// Function-relative PC range and the offset of its action chain.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ExTabRange {
+    pub start: u32,
+    pub size: u16,
+    pub action_offset: u16,
+}
+
+/// A relocated pointer within a record.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ExTabPointer {
+    /// Offset within the record.
+    pub offset: u32,
+    /// Unrelocated value.
+    pub value: u32,
+}
+
+/// Unwinding action. Offsets are relative to the stack or frame pointer,
+/// or to the object for member actions.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ExTabAction {
+    DestroyLocal {
+        local: i16,
+        dtor: ExTabPointer,
+    },
+    DestroyLocalCond {
+        cond: i16,
+        local: i16,
+        dtor: ExTabPointer,
+    },
+    DestroyLocalPointer {
+        pointer: i16,
+        dtor: ExTabPointer,
+    },
+    DestroyLocalArray {
+        array: i16,
+        elements: u16,
+        element_size: u16,
+        dtor: ExTabPointer,
+    },
+    DestroyBase {
+        object: i16,
+        offset: i32,
+        dtor: ExTabPointer,
+    },
+    DestroyMember {
+        object: i16,
+        offset: i32,
+        dtor: ExTabPointer,
+    },
+    DestroyMemberCond {
+        cond: i16,
+        object: i16,
+        offset: i32,
+        dtor: ExTabPointer,
+    },
+    DestroyMemberArray {
+        object: i16,
+        offset: i32,
+        elements: u32,
+        element_size: u32,
+        dtor: ExTabPointer,
+    },
+    DeletePointer {
+        pointer: i16,
+        delete: ExTabPointer,
+    },
+    DeletePointerCond {
+        cond: i16,
+        pointer: i16,
+        delete: ExTabPointer,
+    },
+    CatchBlock {
+        catch_type: ExTabPointer,
+        catch_pc: u32,
+        cinfo: i16,
+    },
+    ActiveCatchBlock {
+        cinfo: i16,
+    },
+    Terminate,
+    Specification {
+        types: Vec<ExTabPointer>,
+        pc: u32,
+        cinfo: i32,
+    },
+}
+
+struct RecordReader<'a> {
+    cursor: Cursor<&'a [u8]>,
+}
+
+impl RecordReader<'_> {
+    fn offset(&self) -> u32 { self.cursor.position() as u32 }
+
+    fn read<T>(&mut self) -> Result<T>
+    where T: FromReader<Args = ()> {
+        let offset = self.offset();
+        T::from_reader(&mut self.cursor, Endian::Big)
+            .with_context(|| format!("Truncated extab record at offset {:#X}", offset))
+    }
+
+    fn pointer(&mut self) -> Result<ExTabPointer> {
+        let offset = self.offset();
+        Ok(ExTabPointer { offset, value: self.read()? })
+    }
+
+    fn action(&mut self, kind: u8) -> Result<ExTabAction> {
+        Ok(match kind {
+            1 => ExTabAction::DestroyLocal { local: self.read()?, dtor: self.pointer()? },
+            2 => ExTabAction::DestroyLocalCond {
+                cond: self.read()?,
+                local: self.read()?,
+                dtor: self.pointer()?,
+            },
+            3 => ExTabAction::DestroyLocalPointer { pointer: self.read()?, dtor: self.pointer()? },
+            4 => ExTabAction::DestroyLocalArray {
+                array: self.read()?,
+                elements: self.read()?,
+                element_size: self.read()?,
+                dtor: self.pointer()?,
+            },
+            5 => ExTabAction::DestroyBase {
+                object: self.read()?,
+                offset: self.read()?,
+                dtor: self.pointer()?,
+            },
+            6 => ExTabAction::DestroyMember {
+                object: self.read()?,
+                offset: self.read()?,
+                dtor: self.pointer()?,
+            },
+            7 => ExTabAction::DestroyMemberCond {
+                cond: self.read()?,
+                object: self.read()?,
+                offset: self.read()?,
+                dtor: self.pointer()?,
+            },
+            8 => ExTabAction::DestroyMemberArray {
+                object: self.read()?,
+                offset: self.read()?,
+                elements: self.read()?,
+                element_size: self.read()?,
+                dtor: self.pointer()?,
+            },
+            9 => ExTabAction::DeletePointer { pointer: self.read()?, delete: self.pointer()? },
+            10 => ExTabAction::DeletePointerCond {
+                cond: self.read()?,
+                pointer: self.read()?,
+                delete: self.pointer()?,
+            },
+            11 => {
+                let catch_type = self.pointer()?;
+                let catch_pc = self.read::<u16>()? as u32;
+                ExTabAction::CatchBlock { catch_type, catch_pc, cinfo: self.read()? }
+            }
+            12 => ExTabAction::ActiveCatchBlock { cinfo: self.read()? },
+            13 => ExTabAction::Terminate,
+            14 => {
+                let count = self.read::<u16>()?;
+                let pc = self.read()?;
+                let cinfo = self.read()?;
+                let types = (0..count).map(|_| self.pointer()).collect::<Result<_>>()?;
+                ExTabAction::Specification { types, pc, cinfo }
+            }
+            16 => {
+                let catch_type = self.pointer()?;
+                let catch_pc = self.read()?;
+                ExTabAction::CatchBlock { catch_type, catch_pc, cinfo: self.read::<i32>()? as i16 }
+            }
+            _ => bail!("Unknown extab action type {} at offset {:#X}", kind, self.offset() - 2),
+        })
+    }
+}
+
+/// Decodes an extab record.
+pub fn decode(record: &[u8]) -> Result<ExTabInfo> {
+    let mut reader = RecordReader { cursor: Cursor::new(record) };
+    let flags = reader.read::<u16>()?;
+    let mut info = ExTabInfo {
+        saved_gprs: (flags >> 11) as u8,
+        saved_fprs: ((flags >> 6) & 0x1F) as u8,
+        saved_cr: flags & 0x20 != 0,
+        has_frame_pointer: flags & 0x10 != 0,
+        large_frame: flags & 0x8 != 0,
+        has_elf_vector: flags & 0x2 != 0,
+        frame_size: reader.read()?,
+        ..Default::default()
+    };
+
+    loop {
+        let start = reader.read::<u32>()?;
+        if start == 0 {
+            break;
+        }
+        let size = reader.read()?;
+        let action_offset = reader.read()?;
+        info.ranges.push(ExTabRange { start, size, action_offset });
+    }
+
+    for range in &info.ranges {
+        let offset = range.action_offset as u32;
+        if offset == 0 || info.actions.contains_key(&offset) {
+            continue;
+        }
+        reader.cursor.set_position(offset as u64);
+        let mut actions = vec![];
+        loop {
+            let kind = reader.read::<u8>()?;
+            let _flags = reader.read::<u8>()?;
+            actions.push(reader.action(kind & 0x7F)?);
+            if kind & 0x80 != 0 {
+                break;
+            }
+        }
+        info.actions.insert(offset, actions);
+    }
+    Ok(info)
+}
+
+/// Renders a decoded record as comment lines (without the comment prefix).
+/// `resolve` names a relocated pointer, e.g. by looking up its relocation.
+pub fn render<F>(info: &ExTabInfo, resolve: F) -> Vec<String>
+where F: Fn(&ExTabPointer) -> String {
+    let mut lines = vec![];
+    let mut header = format!(
+        "extab: GPRs {}, FPRs {}, frame size {:#X}",
+        info.saved_gprs, info.saved_fprs, info.frame_size
+    );
+    for (set, name) in [
+        (info.saved_cr, "CR"),
+        (info.has_frame_pointer, "frame pointer"),
+        (info.large_frame, "large frame"),
+        (info.has_elf_vector, "ELF vector"),
+    ] {
+        if set {
+            write!(header, ", {name}").unwrap();
+        }
+    }
+    lines.push(header);
+    for range in &info.ranges {
+        lines.push(format!(
+            "  {:#X}..{:#X}: actions @ {:#X}",
+            range.start,
+            range.start + range.size as u32,
+            range.action_offset
+        ));
+    }
+    for (offset, actions) in &info.actions {
+        lines.push(format!("  actions @ {offset:#X}:"));
+        for action in actions {
+            lines.push(format!("    {}", render_action(action, &resolve)));
+        }
+    }
+    lines
+}
+
+fn render_action<F>(action: &ExTabAction, resolve: &F) -> String
+where F: Fn(&ExTabPointer) -> String {
+    match action {
+        ExTabAction::DestroyLocal { local, dtor } => {
+            format!("destroy local {:#X} with {}", local, resolve(dtor))
+        }
+        ExTabAction::DestroyLocalCond { cond, local, dtor } => {
+            format!("destroy local {:#X} if {:#X} with {}", local, cond, resolve(dtor))
+        }
+        ExTabAction::DestroyLocalPointer { pointer, dtor } => {
+            format!("destroy *local {:#X} with {}", pointer, resolve(dtor))
+        }
+        ExTabAction::DestroyLocalArray { array, elements, element_size, dtor } => format!(
+            "destroy local array {:#X}[{}] (size {:#X}) with {}",
+            array,
+            elements,
+            element_size,
+            resolve(dtor)
+        ),
+        ExTabAction::DestroyBase { object, offset, dtor } => {
+            format!("destroy base {:#X}+{:#X} with {}", object, offset, resolve(dtor))
+        }
+        ExTabAction::DestroyMember { object, offset, dtor } => {
+            format!("destroy member {:#X}+{:#X} with {}", object, offset, resolve(dtor))
+        }
+        ExTabAction::DestroyMemberCond { cond, object, offset, dtor } => format!(
+            "destroy member {:#X}+{:#X} if {:#X} with {}",
+            object,
+            offset,
+            cond,
+            resolve(dtor)
+        ),
+        ExTabAction::DestroyMemberArray { object, offset, elements, element_size, dtor } => {
+            format!(
+                "destroy member array {:#X}+{:#X}[{}] (size {:#X}) with {}",
+                object,
+                offset,
+                elements,
+                element_size,
+                resolve(dtor)
+            )
+        }
+        ExTabAction::DeletePointer { pointer, delete } => {
+            format!("delete local {:#X} with {}", pointer, resolve(delete))
+        }
+        ExTabAction::DeletePointerCond { cond, pointer, delete } => {
+            format!("delete local {:#X} if {:#X} with {}", pointer, cond, resolve(delete))
+        }
+        ExTabAction::CatchBlock { catch_type, catch_pc, cinfo } => {
+            format!("catch {} @ {:#X}, cinfo {:#X}", resolve(catch_type), catch_pc, cinfo)
+        }
+        ExTabAction::ActiveCatchBlock { cinfo } => format!("active catch, cinfo {cinfo:#X}"),
+        ExTabAction::Terminate => "terminate".to_string(),
+        ExTabAction::Specification { types, pc, cinfo } => format!(
+            "specification ({}) @ {:#X}, cinfo {:#X}",
+            types.iter().map(resolve).collect::<Vec<_>>().join(", "),
+            pc,
+            cinfo
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        #[rustfmt::skip]
+        let record = [
+            // Header: 3 GPRs, CR saved, frame size 0x20
+            0x18, 0x20, 0x00, 0x20,
+            // Range 0x10..0x30 -> 0x10
+            0x00, 0x00, 0x00, 0x10, 0x00, 0x20, 0x00, 0x10,
+            0x00, 0x00, 0x00, 0x00,
+            // Destroy local 0x8, dtor at 0x14
+            0x01, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00,
+            // Catch block (last), type at 0x1A, catch pc 0x40, cinfo 0x10
+            0x8B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x10,
+        ];
+        let info = decode(&record).unwrap();
+        assert_eq!((info.saved_gprs, info.saved_fprs, info.frame_size), (3, 0, 0x20));
+        assert!(info.saved_cr && !info.has_frame_pointer && !info.large_frame);
+        assert_eq!(info.ranges, vec![ExTabRange { start: 0x10, size: 0x20, action_offset: 0x10 }]);
+        assert_eq!(info.actions[&0x10], vec![
+            ExTabAction::DestroyLocal { local: 0x8, dtor: ExTabPointer { offset: 0x14, value: 0 } },
+            ExTabAction::CatchBlock {
+                catch_type: ExTabPointer { offset: 0x1A, value: 0 },
+                catch_pc: 0x40,
+                cinfo: 0x10,
+            },
+        ]);
+
+        let names = |ptr: &ExTabPointer| match ptr.offset {
+            0x14 => "__dt__3FooFv".to_string(),
+            _ => "@1234".to_string(),
+        };
+        assert_eq!(render(&info, names), vec![
+            "extab: GPRs 3, FPRs 0, frame size 0x20, CR",
+            "  0x10..0x30: actions @ 0x10",
+            "  actions @ 0x10:",
+            "    destroy local 0x8 with __dt__3FooFv",
+            "    catch @1234 @ 0x40, cinfo 0x10",
+        ]);
+
+        assert!(decode(&record[..0x1E]).is_err());
+    }
+}
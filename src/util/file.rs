@@ -1,11 +1,17 @@
 use std::{
+    collections::HashSet,
+    env,
     ffi::OsStr,
+    fs,
     fs::{DirBuilder, File, OpenOptions},
-    io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom},
+    io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write},
     path::{Component, Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use encoding_rs::SHIFT_JIS;
 use filetime::{set_file_mtime, FileTime};
 use memmap2::{Mmap, MmapOptions};
 use path_slash::PathBufExt;
@@ -16,9 +22,13 @@ use xxhash_rust::xxh3::xxh3_64;
 use crate::{
     array_ref,
     util::{
-        ncompress::{decompress_yay0, decompress_yaz0, YAY0_MAGIC, YAZ0_MAGIC},
+        ncompress::{
+            decompress_yay0, decompress_yay0_into, decompress_yaz0, decompress_yaz0_into,
+            decompress_yaz0_limited, decompressed_size, YAY0_MAGIC, YAZ0_MAGIC,
+        },
         rarc,
-        rarc::{Node, RARC_MAGIC},
+        rarc::RARC_MAGIC,
+        reader::Endian,
         take_seek::{TakeSeek, TakeSeekExt},
         u8_arc::{U8View, U8_MAGIC},
         Bytes,
@@ -68,8 +78,42 @@ where P: AsRef<Path> {
     Ok((base_path, sub_path))
 }
 
+/// Mapping offsets must be aligned to the page size (or allocation granularity on Windows).
+/// 64 KiB is a multiple of both on all supported platforms.
+const MMAP_ALIGN: u64 = 0x10000;
+
+/// Memory maps only `len` bytes of a file starting at `offset`, rather than the whole file.
+/// The offset doesn't need to be page-aligned.
+pub fn map_file_range<P>(path: P, offset: u64, len: u64) -> Result<MappedFile>
+where P: AsRef<Path> {
+    let path = path.as_ref();
+    let file =
+        File::open(path).with_context(|| format!("Failed to open file '{}'", path.display()))?;
+    let metadata = file.metadata()?;
+    ensure!(
+        offset.checked_add(len).is_some_and(|end| end <= metadata.len()),
+        "Range {:#X}..{:#X} out of bounds for '{}' ({:#X} bytes)",
+        offset,
+        offset.saturating_add(len),
+        path.display(),
+        metadata.len()
+    );
+    let mtime = FileTime::from_last_modification_time(&metadata);
+    let map_offset = offset & !(MMAP_ALIGN - 1);
+    let map_len = (offset - map_offset + len) as usize;
+    let mmap = unsafe { MmapOptions::new().offset(map_offset).len(map_len).map(&file) }
+        .with_context(|| format!("Failed to mmap file: '{}'", path.display()))?;
+    Ok(MappedFile { mmap, mtime, offset: offset - map_offset, len })
+}
+
 /// Opens a memory mapped file, and decompresses it if needed.
 pub fn map_file<P>(path: P) -> Result<FileEntry>
+where P: AsRef<Path> {
+    map_file_limited(path, usize::MAX)
+}
+
+/// Like [map_file], but rejects Yaz0 files declaring more than `max_output` decompressed bytes.
+pub fn map_file_limited<P>(path: P, max_output: usize) -> Result<FileEntry>
 where P: AsRef<Path> {
     let (base_path, sub_path) = split_path(path.as_ref())?;
     let file = File::open(&base_path)
@@ -93,7 +137,7 @@ where P: AsRef<Path> {
             ));
         } else if sub_path.as_os_str() == OsStr::new("yaz0") {
             return Ok(FileEntry::Buffer(
-                decompress_yaz0(mmap.as_ref()).with_context(|| {
+                decompress_yaz0_limited(mmap.as_ref(), max_output).with_context(|| {
                     format!("Failed to decompress '{}' with Yaz0", path.as_ref().display())
                 })?,
                 mtime,
@@ -139,7 +183,7 @@ where P: AsRef<Path> {
         match *array_ref!(buf, 0, 4) {
             YAZ0_MAGIC => {
                 return Ok(FileEntry::Buffer(
-                    decompress_yaz0(buf).with_context(|| {
+                    decompress_yaz0_limited(buf, max_output).with_context(|| {
                         format!("Failed to decompress '{}' with Yaz0", path.as_ref().display())
                     })?,
                     mtime,
@@ -229,23 +273,100 @@ where R: Read + Seek + ?Sized {
 
 /// Reads a zero-terminated string at the specified offset.
 pub fn read_c_string<R>(reader: &mut R, off: u64) -> Result<String>
+where R: Read + Seek + ?Sized {
+    Ok(read_c_bytes(reader, off)?.into_iter().map(char::from).collect())
+}
+
+/// Reads a zero-terminated Shift-JIS string at the specified offset.
+/// Returns the decoded string along with the raw bytes.
+pub fn read_c_string_sjis<R>(reader: &mut R, off: u64) -> Result<(String, Vec<u8>)>
+where R: Read + Seek + ?Sized {
+    let raw = read_c_bytes(reader, off)?;
+    let (decoded, _) = SHIFT_JIS.decode_without_bom_handling(&raw);
+    Ok((decoded.into_owned(), raw))
+}
+
+/// Reads a zero-terminated byte string at the specified offset, restoring the stream position.
+pub fn read_c_bytes<R>(reader: &mut R, off: u64) -> Result<Vec<u8>>
 where R: Read + Seek + ?Sized {
     let pos = reader.stream_position()?;
     reader.seek(SeekFrom::Start(off))?;
-    let mut s = String::new();
+    let mut out = vec![];
     let mut buf = [0u8; 1];
     loop {
         reader.read_exact(&mut buf)?;
         if buf[0] == 0 {
             break;
         }
-        s.push(buf[0] as char);
+        out.push(buf[0]);
     }
     reader.seek(SeekFrom::Start(pos))?;
-    Ok(s)
+    Ok(out)
+}
+
+/// Reads `N` bytes at the specified offset, restoring the stream position.
+fn read_bytes_at<const N: usize, R>(reader: &mut R, off: u64) -> Result<[u8; N]>
+where R: Read + Seek + ?Sized {
+    let mut data = [0u8; N];
+    let pos = reader.stream_position()?;
+    reader.seek(SeekFrom::Start(off))?;
+    reader.read_exact(&mut data)?;
+    reader.seek(SeekFrom::Start(pos))?;
+    Ok(data)
+}
+
+/// Reads a `u16` at the specified offset in the given byte order,
+/// e.g. [Architecture::endian](crate::obj::Architecture::endian).
+pub fn read_u16_at<R>(reader: &mut R, off: u64, e: Endian) -> Result<u16>
+where R: Read + Seek + ?Sized {
+    let bytes = read_bytes_at(reader, off)?;
+    Ok(match e {
+        Endian::Big => u16::from_be_bytes(bytes),
+        Endian::Little => u16::from_le_bytes(bytes),
+    })
+}
+
+/// Reads a `u32` at the specified offset in the given byte order,
+/// e.g. [Architecture::endian](crate::obj::Architecture::endian).
+pub fn read_u32_at<R>(reader: &mut R, off: u64, e: Endian) -> Result<u32>
+where R: Read + Seek + ?Sized {
+    let bytes = read_bytes_at(reader, off)?;
+    Ok(match e {
+        Endian::Big => u32::from_be_bytes(bytes),
+        Endian::Little => u32::from_le_bytes(bytes),
+    })
+}
+
+/// Reads a big-endian `u16` at the specified offset.
+#[inline]
+pub fn read_u16_be<R>(reader: &mut R, off: u64) -> Result<u16>
+where R: Read + Seek + ?Sized {
+    read_u16_at(reader, off, Endian::Big)
+}
+
+/// Reads a big-endian `u32` at the specified offset.
+#[inline]
+pub fn read_u32_be<R>(reader: &mut R, off: u64) -> Result<u32>
+where R: Read + Seek + ?Sized {
+    read_u32_at(reader, off, Endian::Big)
+}
+
+/// Reads a big-endian `i32` at the specified offset.
+#[inline]
+pub fn read_i32_be<R>(reader: &mut R, off: u64) -> Result<i32>
+where R: Read + Seek + ?Sized {
+    Ok(read_u32_be(reader, off)? as i32)
 }
 
-/// Process response files (starting with '@') and glob patterns (*).
+/// Reads a big-endian `f32` at the specified offset.
+#[inline]
+pub fn read_f32_be<R>(reader: &mut R, off: u64) -> Result<f32>
+where R: Read + Seek + ?Sized {
+    Ok(f32::from_bits(read_u32_be(reader, off)?))
+}
+
+/// Process response files (starting with '@') and glob patterns (*). Patterns containing
+/// a wildcard may also use `{a,b}` alternations; paths without one are taken literally.
 pub fn process_rsp(files: &[PathBuf]) -> Result<Vec<PathBuf>> {
     let mut out = Vec::with_capacity(files.len());
     for path in files {
@@ -260,8 +381,14 @@ pub fn process_rsp(files: &[PathBuf]) -> Result<Vec<PathBuf>> {
                 }
             }
         } else if path_str.contains('*') {
-            for entry in glob::glob(path_str)? {
-                out.push(entry?);
+            for pattern in expand_braces(path_str) {
+                if pattern.contains('*') {
+                    for entry in glob::glob(&pattern)? {
+                        out.push(entry?);
+                    }
+                } else {
+                    out.push(PathBuf::from(pattern));
+                }
             }
         } else {
             out.push(path.clone());
@@ -270,46 +397,143 @@ pub fn process_rsp(files: &[PathBuf]) -> Result<Vec<PathBuf>> {
     Ok(out)
 }
 
+/// Like [process_rsp], but normalizes the resulting paths and removes duplicates,
+/// preserving the order in which they were first seen.
+pub fn process_rsp_dedup(files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut seen = HashSet::new();
+    let mut out = vec![];
+    for path in process_rsp(files)? {
+        let path = normalize_path(&path);
+        if seen.insert(path.clone()) {
+            out.push(path);
+        }
+    }
+    Ok(out)
+}
+
+/// Lexically resolves `.` and `..` components, without touching the filesystem.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match out.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    out.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => out.push(".."),
+            },
+            c => out.push(c),
+        }
+    }
+    if out.as_os_str().is_empty() {
+        out.push(".");
+    }
+    out
+}
+
+/// Whether `\` escapes characters in patterns. On Windows, it's the path separator instead.
+const PATTERN_ESCAPES: bool = !cfg!(windows);
+
+/// Expands `{a,b}` alternations into multiple patterns, since `glob` doesn't support them.
+/// Alternations may be nested. Except on Windows, `\{`, `\}` and `\,` are treated as
+/// literal characters.
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let bytes = pattern.as_bytes();
+    let mut open = None;
+    let mut commas = vec![];
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if PATTERN_ESCAPES && matches!(bytes.get(i + 1), Some(b'{' | b'}' | b',')) => {
+                i += 1
+            }
+            b'{' => {
+                if depth == 0 {
+                    open = Some(i);
+                }
+                depth += 1;
+            }
+            b',' if depth == 1 => commas.push(i),
+            b'}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let open = open.unwrap();
+                    let (prefix, suffix) = (&pattern[..open], &pattern[i + 1..]);
+                    let mut out = vec![];
+                    let mut start = open + 1;
+                    for end in commas.iter().copied().chain([i]) {
+                        let alternative = &pattern[start..end];
+                        out.extend(expand_braces(&format!("{prefix}{alternative}{suffix}")));
+                        start = end + 1;
+                    }
+                    return out;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    // No (complete) alternation left
+    if !PATTERN_ESCAPES {
+        return vec![pattern.to_string()];
+    }
+    let mut out = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(&next @ ('{' | '}' | ',')) = chars.peek() {
+                out.push(next);
+                chars.next();
+                continue;
+            }
+        }
+        out.push(c);
+    }
+    vec![out]
+}
+
 /// Iterator over files in a RARC archive.
 struct RarcIterator {
     file: MappedFile,
     base_path: PathBuf,
     paths: Vec<(PathBuf, u64, u32)>,
     index: usize,
+    memory_limit: Option<usize>,
+    decompress_limit: usize,
 }
 
 impl RarcIterator {
-    pub fn new(file: MappedFile, base_path: &Path) -> Result<Self> {
+    pub fn new(
+        file: MappedFile,
+        base_path: &Path,
+        memory_limit: Option<usize>,
+        decompress_limit: usize,
+    ) -> Result<Self> {
         let reader = RarcReader::new(&mut file.as_reader())?;
         let paths = Self::collect_paths(&reader, base_path);
-        Ok(Self { file, base_path: base_path.to_owned(), paths, index: 0 })
+        Ok(Self {
+            file,
+            base_path: base_path.to_owned(),
+            paths,
+            index: 0,
+            memory_limit,
+            decompress_limit,
+        })
     }
 
     fn collect_paths(reader: &RarcReader, base_path: &Path) -> Vec<(PathBuf, u64, u32)> {
-        let mut current_path = PathBuf::new();
-        let mut paths = vec![];
-        for node in reader.nodes() {
-            match node {
-                Node::DirectoryBegin { name } => {
-                    current_path.push(name.name);
-                }
-                Node::DirectoryEnd { name: _ } => {
-                    current_path.pop();
-                }
-                Node::File { name, offset, size } => {
-                    let path = base_path.join(&current_path).join(name.name);
-                    paths.push((path, offset, size));
-                }
-                Node::CurrentDirectory => {}
-                Node::ParentDirectory => {}
-            }
-        }
-        paths
+        reader
+            .entries()
+            .map(|entry| (base_path.join(entry.path), entry.offset, entry.size))
+            .collect()
     }
 }
 
 impl Iterator for RarcIterator {
-    type Item = Result<(PathBuf, Box<[u8]>)>;
+    type Item = Result<(PathBuf, FileEntry)>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.index >= self.paths.len() {
@@ -320,10 +544,9 @@ impl Iterator for RarcIterator {
         self.index += 1;
 
         let slice = &self.file.as_slice()[off as usize..off as usize + size as usize];
-        match decompress_if_needed(slice) {
-            Ok(buf) => Some(Ok((path, buf.into_owned()))),
-            Err(e) => Some(Err(e)),
-        }
+        let entry =
+            decompress_entry(slice, self.file.mtime, self.memory_limit, self.decompress_limit);
+        Some(entry.map(|entry| (path, entry)))
     }
 }
 
@@ -369,6 +592,18 @@ impl FileEntry {
             Self::Buffer(_, mtime) => *mtime,
         }
     }
+
+    /// Copies the file contents into an owned buffer.
+    pub fn to_vec(&self) -> Vec<u8> { self.as_slice().to_vec() }
+
+    /// Converts the entry into an owned buffer. Buffered entries are moved out without
+    /// copying; mapped files are copied.
+    pub fn into_vec(self) -> Vec<u8> {
+        match self {
+            Self::MappedFile(file) => file.as_slice().to_vec(),
+            Self::Buffer(slice, _) => slice.into_vec(),
+        }
+    }
 }
 
 /// Information about a file when it was read.
@@ -387,6 +622,9 @@ impl FileReadInfo {
     }
 }
 
+/// Default cap on the declared size of Yaz0 files decompressed by [FileIterator].
+pub const DEFAULT_DECOMPRESS_LIMIT: usize = 512 * 1024 * 1024;
+
 /// Iterate over file paths, expanding response files (@) and glob patterns (*).
 /// If a file is a RARC archive, iterate over its contents.
 /// If a file is a Yaz0 compressed file, decompress it.
@@ -394,21 +632,47 @@ pub struct FileIterator {
     paths: Vec<PathBuf>,
     index: usize,
     rarc: Option<RarcIterator>,
+    memory_limit: Option<usize>,
+    decompress_limit: usize,
 }
 
 impl FileIterator {
     pub fn new(paths: &[PathBuf]) -> Result<Self> {
-        Ok(Self { paths: process_rsp(paths)?, index: 0, rarc: None })
+        Ok(Self {
+            paths: process_rsp(paths)?,
+            index: 0,
+            rarc: None,
+            memory_limit: None,
+            decompress_limit: DEFAULT_DECOMPRESS_LIMIT,
+        })
+    }
+
+    /// Yaz0 files declaring more than `limit` decompressed bytes are rejected.
+    /// Defaults to [DEFAULT_DECOMPRESS_LIMIT].
+    pub fn with_decompress_limit(mut self, limit: usize) -> Self {
+        self.decompress_limit = limit;
+        self
+    }
+
+    /// Decompressed files larger than `limit` bytes are written to a temporary file
+    /// and memory mapped, rather than being held in memory.
+    pub fn with_memory_limit(mut self, limit: usize) -> Self {
+        self.memory_limit = Some(limit);
+        self
     }
 
+    /// Logs and skips entries that fail to load, rather than yielding errors.
+    /// The skipped errors can be retrieved with [SkipErrors::errors].
+    pub fn skip_errors(self) -> SkipErrors { SkipErrors { inner: self, errors: vec![] } }
+
     fn next_rarc(&mut self) -> Option<Result<(PathBuf, FileEntry)>> {
         if let Some(rarc) = &mut self.rarc {
             match rarc.next() {
-                Some(Ok((path, buf))) => {
+                Some(Ok((path, entry))) => {
                     let mut path_str = rarc.base_path.as_os_str().to_os_string();
                     path_str.push(OsStr::new(":"));
                     path_str.push(path.as_os_str());
-                    return Some(Ok((path, FileEntry::Buffer(buf, rarc.file.mtime))));
+                    return Some(Ok((path, entry)));
                 }
                 Some(Err(err)) => return Some(Err(err)),
                 None => self.rarc = None,
@@ -424,9 +688,11 @@ impl FileIterator {
 
         let path = self.paths[self.index].clone();
         self.index += 1;
-        match map_file(&path) {
+        match map_file_limited(&path, self.decompress_limit) {
             Ok(FileEntry::MappedFile(map)) => self.handle_file(map, path),
-            Ok(FileEntry::Buffer(_, _)) => todo!(),
+            Ok(FileEntry::Buffer(buf, mtime)) => {
+                Some(buffer_entry(buf, mtime, self.memory_limit).map(|entry| (path, entry)))
+            }
             Err(err) => Some(Err(err)),
         }
     }
@@ -442,33 +708,20 @@ impl FileIterator {
         }
 
         match *array_ref!(buf, 0, 4) {
-            YAZ0_MAGIC => self.handle_yaz0(file, path),
-            YAY0_MAGIC => self.handle_yay0(file, path),
+            YAZ0_MAGIC | YAY0_MAGIC => self.handle_compressed(file, path),
             RARC_MAGIC => self.handle_rarc(file, path),
             _ => Some(Ok((path, FileEntry::MappedFile(file)))),
         }
     }
 
-    fn handle_yaz0(
-        &mut self,
-        file: MappedFile,
-        path: PathBuf,
-    ) -> Option<Result<(PathBuf, FileEntry)>> {
-        Some(match decompress_yaz0(file.as_slice()) {
-            Ok(buf) => Ok((path, FileEntry::Buffer(buf, file.mtime))),
-            Err(e) => Err(e),
-        })
-    }
-
-    fn handle_yay0(
+    fn handle_compressed(
         &mut self,
         file: MappedFile,
         path: PathBuf,
     ) -> Option<Result<(PathBuf, FileEntry)>> {
-        Some(match decompress_yay0(file.as_slice()) {
-            Ok(buf) => Ok((path, FileEntry::Buffer(buf, file.mtime))),
-            Err(e) => Err(e),
-        })
+        let entry =
+            decompress_entry(file.as_slice(), file.mtime, self.memory_limit, self.decompress_limit);
+        Some(entry.map(|entry| (path, entry)))
     }
 
     fn handle_rarc(
@@ -476,7 +729,7 @@ impl FileIterator {
         file: MappedFile,
         path: PathBuf,
     ) -> Option<Result<(PathBuf, FileEntry)>> {
-        self.rarc = match RarcIterator::new(file, &path) {
+        self.rarc = match RarcIterator::new(file, &path, self.memory_limit, self.decompress_limit) {
             Ok(iter) => Some(iter),
             Err(e) => return Some(Err(e)),
         };
@@ -490,6 +743,116 @@ impl Iterator for FileIterator {
     fn next(&mut self) -> Option<Self::Item> { self.next_rarc().or_else(|| self.next_path()) }
 }
 
+/// Iterator adapter returned by [FileIterator::skip_errors].
+pub struct SkipErrors {
+    inner: FileIterator,
+    errors: Vec<anyhow::Error>,
+}
+
+impl SkipErrors {
+    /// Errors encountered so far.
+    pub fn errors(&self) -> &[anyhow::Error] { &self.errors }
+
+    pub fn into_errors(self) -> Vec<anyhow::Error> { self.errors }
+}
+
+impl Iterator for SkipErrors {
+    type Item = (PathBuf, FileEntry);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.inner.next()? {
+                Ok(entry) => return Some(entry),
+                Err(e) => {
+                    log::warn!("Skipping file: {:?}", e);
+                    self.errors.push(e);
+                }
+            }
+        }
+    }
+}
+
+/// Creates a file entry for a decompressed buffer. If the buffer exceeds `memory_limit`,
+/// it's written to a temporary file and memory mapped instead.
+fn buffer_entry(buf: Box<[u8]>, mtime: FileTime, memory_limit: Option<usize>) -> Result<FileEntry> {
+    match memory_limit {
+        Some(limit) if buf.len() > limit => spill_to_file(buf.len(), mtime, |out| {
+            out.copy_from_slice(&buf);
+            Ok(())
+        }),
+        _ => Ok(FileEntry::Buffer(buf, mtime)),
+    }
+}
+
+/// Creates a file entry for data that may be Yaz0 or Yay0 compressed. If the (decompressed)
+/// size exceeds `memory_limit`, it's decompressed straight into a memory mapped temporary
+/// file, so it's never held in memory in full. Yaz0 data declaring more than
+/// `decompress_limit` bytes is rejected.
+fn decompress_entry(
+    data: &[u8],
+    mtime: FileTime,
+    memory_limit: Option<usize>,
+    decompress_limit: usize,
+) -> Result<FileEntry> {
+    let magic = data.get(0..4).and_then(|magic| <[u8; 4]>::try_from(magic).ok());
+    let size = if matches!(magic, Some(YAZ0_MAGIC | YAY0_MAGIC)) {
+        let size = decompressed_size(data).ok_or_else(|| anyhow!("Invalid compression header"))?;
+        ensure!(
+            magic != Some(YAZ0_MAGIC) || size <= decompress_limit,
+            "Yaz0 declared size {:#X} exceeds limit {:#X}",
+            size,
+            decompress_limit
+        );
+        size
+    } else {
+        data.len()
+    };
+    let fill = |out: &mut [u8]| match magic {
+        Some(YAZ0_MAGIC) => decompress_yaz0_into(data, out),
+        Some(YAY0_MAGIC) => decompress_yay0_into(data, out),
+        _ => {
+            out.copy_from_slice(data);
+            Ok(())
+        }
+    };
+    match memory_limit {
+        Some(limit) if size > limit => spill_to_file(size, mtime, fill),
+        _ => {
+            let mut buf = vec![0u8; size].into_boxed_slice();
+            fill(&mut buf[..])?;
+            Ok(FileEntry::Buffer(buf, mtime))
+        }
+    }
+}
+
+/// Creates a temporary file of `len` bytes, memory maps it, and writes its contents with `fill`.
+fn spill_to_file<F>(len: usize, mtime: FileTime, fill: F) -> Result<FileEntry>
+where F: FnOnce(&mut [u8]) -> Result<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let path = env::temp_dir().join(format!(
+        "dtk-{}-{}.tmp",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .with_context(|| format!("Failed to create temporary file '{}'", path.display()))?;
+    let result = file.set_len(len as u64).map_err(anyhow::Error::from).and_then(|_| {
+        let mut mmap = unsafe { MmapOptions::new().map_mut(&file) }
+            .with_context(|| format!("Failed to mmap file: '{}'", path.display()))?;
+        fill(&mut mmap[..])?;
+        Ok(mmap.make_read_only()?)
+    });
+    // The mapping remains valid after removal on most platforms; on Windows,
+    // removal fails while mapped and the file is left behind in the temp directory.
+    let _ = fs::remove_file(&path);
+    let mmap = result?;
+    Ok(FileEntry::MappedFile(MappedFile { mmap, mtime, offset: 0, len: len as u64 }))
+}
+
 pub fn touch<P>(path: P) -> std::io::Result<()>
 where P: AsRef<Path> {
     if path.as_ref().exists() {
@@ -530,3 +893,171 @@ pub fn verify_hash(buf: &[u8], expected_str: &str) -> Result<()> {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::ncompress::{compress_yay0, compress_yaz0};
+
+    #[test]
+    fn test_read_be() {
+        let data = [0x12, 0x34, 0xFF, 0xFF, 0xFF, 0xFE, 0x3F, 0x80, 0x00, 0x00];
+        let mut reader = Cursor::new(data.as_slice());
+        reader.seek(SeekFrom::Start(1)).unwrap();
+        assert_eq!(read_u16_be(&mut reader, 0).unwrap(), 0x1234);
+        assert_eq!(read_u32_be(&mut reader, 2).unwrap(), 0xFFFFFFFE);
+        assert_eq!(read_i32_be(&mut reader, 2).unwrap(), -2);
+        assert_eq!(read_f32_be(&mut reader, 6).unwrap(), 1.0);
+        assert_eq!(reader.stream_position().unwrap(), 1);
+        assert!(read_u32_be(&mut reader, 8).is_err());
+        assert_eq!(read_u16_at(&mut reader, 0, Endian::Little).unwrap(), 0x3412);
+        assert_eq!(read_u32_at(&mut reader, 6, Endian::Little).unwrap(), 0x0000803F);
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let data = vec![0xAB; 0x100000];
+        for (ext, compressed) in [("szs", compress_yaz0(&data)), ("yay0", compress_yay0(&data))] {
+            let path = env::temp_dir().join(format!("dtk-test-{}.{}", process::id(), ext));
+            fs::write(&path, compressed).unwrap();
+            let mut limited = FileIterator::new(&[path.clone()]).unwrap().with_memory_limit(0x1000);
+            let (_, spilled) = limited.next().unwrap().unwrap();
+            let (_, buffered) =
+                FileIterator::new(&[path.clone()]).unwrap().next().unwrap().unwrap();
+            fs::remove_file(&path).unwrap();
+            assert!(matches!(spilled, FileEntry::MappedFile(_)));
+            assert!(matches!(buffered, FileEntry::Buffer(..)));
+            assert_eq!(spilled.as_slice(), data.as_slice());
+            assert_eq!(buffered.as_slice(), data.as_slice());
+            assert!(limited.next().is_none());
+        }
+    }
+
+    #[test]
+    fn test_spill_to_file() {
+        // Written straight into the mapping, without an intermediate buffer
+        let mut fill_ptr = std::ptr::null();
+        let entry = spill_to_file(0x1000, FileTime::now(), |out| {
+            fill_ptr = out.as_ptr();
+            out.fill(0xAB);
+            Ok(())
+        })
+        .unwrap();
+        assert!(matches!(entry, FileEntry::MappedFile(_)));
+        assert_eq!(entry.as_slice().as_ptr(), fill_ptr);
+        assert_eq!(entry.as_slice(), &[0xAB; 0x1000]);
+        assert!(spill_to_file(0x10, FileTime::now(), |_| bail!("fill failed")).is_err());
+    }
+
+    #[test]
+    fn test_decompress_limit() {
+        let data = vec![0xAB; 0x1000];
+        let compressed = compress_yaz0(&data);
+        assert!(decompress_yaz0_limited(&compressed, 0x1000).is_ok());
+        assert!(decompress_yaz0_limited(&compressed, 0xFFF).is_err());
+
+        // Header declaring 4 GB of output
+        let mut bomb = compressed.to_vec();
+        bomb[4..8].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(decompress_yaz0_limited(&bomb, DEFAULT_DECOMPRESS_LIMIT).is_err());
+
+        let path = env::temp_dir().join(format!("dtk-test-limit-{}.szs", process::id()));
+        fs::write(&path, &bomb).unwrap();
+        let mut iter = FileIterator::new(&[path.clone()]).unwrap();
+        let result = iter.next().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_into_vec() {
+        let data = (0..0x1000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let path = env::temp_dir().join(format!("dtk-test-vec-{}.bin", process::id()));
+        fs::write(&path, &data).unwrap();
+        let mapped = map_file_basic(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(mapped, FileEntry::MappedFile(_)));
+        assert_eq!(mapped.to_vec(), data);
+        assert_eq!(mapped.into_vec(), data);
+
+        let buffer = FileEntry::Buffer(data.clone().into_boxed_slice(), FileTime::now());
+        assert_eq!(buffer.to_vec(), data);
+        let ptr = buffer.as_slice().as_ptr();
+        let vec = buffer.into_vec();
+        assert_eq!(vec, data);
+        assert_eq!(vec.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_expand_braces() {
+        assert_eq!(expand_braces("assets/*.dat"), vec!["assets/*.dat"]);
+        assert_eq!(expand_braces("assets/{a,b}/*.dat"), vec!["assets/a/*.dat", "assets/b/*.dat"]);
+        assert_eq!(expand_braces("{a,b{c,d}}/{e,f}"), vec![
+            "a/e", "a/f", "bc/e", "bc/f", "bd/e", "bd/f"
+        ]);
+        assert_eq!(expand_braces("x{,y}"), vec!["x", "xy"]);
+        if PATTERN_ESCAPES {
+            assert_eq!(expand_braces(r"a\{b,c\}/{d\,e,f}"), vec![r"a{b,c}/d,e", r"a{b,c}/f"]);
+        } else {
+            assert_eq!(expand_braces(r"a\{b,c}\d"), vec![r"a\b\d", r"a\c\d"]);
+        }
+        assert_eq!(expand_braces("a{b"), vec!["a{b"]);
+        // Braces alone don't make a path a pattern
+        assert_eq!(process_rsp(&[PathBuf::from("a{b,c}.dat")]).unwrap(), vec![PathBuf::from(
+            "a{b,c}.dat"
+        )]);
+    }
+
+    #[test]
+    fn test_process_rsp_dedup() {
+        let dir = env::temp_dir().join(format!("dtk-test-rsp-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.dat"), []).unwrap();
+        fs::write(dir.join("b.dat"), []).unwrap();
+        let dir_str = dir.to_str().unwrap();
+        let patterns = [
+            PathBuf::from(format!("{dir_str}/a.*")),
+            PathBuf::from(format!("{dir_str}/*.dat")),
+            PathBuf::from(format!("{dir_str}/./b.dat")),
+        ];
+        let result = process_rsp_dedup(&patterns);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(result.unwrap(), vec![dir.join("a.dat"), dir.join("b.dat")]);
+        assert_eq!(process_rsp(&patterns[..2]).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path(Path::new("a/./b/../c")), PathBuf::from("a/c"));
+        assert_eq!(normalize_path(Path::new("../a/..")), PathBuf::from(".."));
+        assert_eq!(normalize_path(Path::new("/../a")), PathBuf::from("/a"));
+    }
+
+    #[test]
+    fn test_map_file_range() {
+        let data = (0..0x30000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let path = env::temp_dir().join(format!("dtk-test-range-{}.bin", process::id()));
+        fs::write(&path, &data).unwrap();
+        let file = map_file_range(&path, 0x12345, 0x10000).unwrap();
+        let out_of_bounds = map_file_range(&path, 0x2FFFF, 2);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(file.as_slice(), &data[0x12345..0x22345]);
+        assert!(out_of_bounds.is_err());
+    }
+
+    #[test]
+    fn test_skip_errors() {
+        let dir = env::temp_dir().join(format!("dtk-test-skip-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let corrupt = dir.join("corrupt.arc");
+        let valid = dir.join("valid.bin");
+        fs::write(&corrupt, b"RARC\0\0\0\0").unwrap();
+        fs::write(&valid, b"valid data").unwrap();
+        let mut iter = FileIterator::new(&[corrupt.clone(), valid.clone()]).unwrap().skip_errors();
+        let entries =
+            iter.by_ref().map(|(path, entry)| (path, entry.into_vec())).collect::<Vec<_>>();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(entries, vec![(valid, b"valid data".to_vec())]);
+        assert_eq!(iter.errors().len(), 1);
+    }
+}
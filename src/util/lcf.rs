@@ -1,10 +1,10 @@
-use std::path::PathBuf;
+use std::{io::Write, path::PathBuf};
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
 use itertools::Itertools;
 use path_slash::PathBufExt;
 
-use crate::obj::{ObjInfo, ObjKind};
+use crate::obj::{ObjInfo, ObjKind, ObjSectionKind, ObjSymbolKind};
 
 const LCF_TEMPLATE: &str = include_str!("../../assets/ldscript.lcf");
 const LCF_PARTIAL_TEMPLATE: &str = include_str!("../../assets/ldscript_partial.lcf");
@@ -37,7 +37,8 @@ pub fn generate_ldscript(
     }
 
     let mut force_active = force_active.to_vec();
-    for symbol in obj.symbols.iter() {
+    let symbols = obj.symbols.iter_ordered().map(|(_, s)| s);
+    for symbol in symbols.chain(obj.symbols.iter().filter(|s| s.section.is_none())) {
         if symbol.flags.is_exported() && symbol.flags.is_global() && !symbol.flags.is_no_write() {
             force_active.push(symbol.name.clone());
         }
@@ -86,7 +87,8 @@ pub fn generate_ldscript_partial(
     }
 
     let mut force_active = force_active.to_vec();
-    for symbol in obj.symbols.iter() {
+    let symbols = obj.symbols.iter_ordered().map(|(_, s)| s);
+    for symbol in symbols.chain(obj.symbols.iter().filter(|s| s.section.is_none())) {
         if symbol.flags.is_exported() && symbol.flags.is_global() && !symbol.flags.is_no_write() {
             force_active.push(symbol.name.clone());
         }
@@ -99,6 +101,109 @@ pub fn generate_ldscript_partial(
     Ok(out)
 }
 
+/// Writes a GNU ld linker script placing each section at its address, in address order,
+/// with linker generated symbols (`_SDA_BASE_`, `__ArenaLo`, etc.) provided at their
+/// known values. Only valid for executable objects.
+pub fn write_gnu_ldscript<W>(w: &mut W, obj: &ObjInfo) -> Result<()>
+where W: Write + ?Sized {
+    ensure!(obj.kind == ObjKind::Executable, "GNU ld scripts require an executable object");
+
+    let entry = obj.entry.map(|entry| {
+        let symbol = obj.sections.at_address(entry as u32).ok().and_then(|(section_index, _)| {
+            obj.symbols
+                .kind_at_section_address(section_index, entry as u32, ObjSymbolKind::Function)
+                .ok()
+                .flatten()
+        });
+        (entry, symbol.map(|(_, s)| s.name.clone()))
+    });
+    match &entry {
+        Some((_, Some(name))) => writeln!(w, "ENTRY({})", name)?,
+        Some((_, None)) => writeln!(w, "ENTRY(__entry)")?,
+        None => {}
+    }
+    writeln!(w)?;
+
+    writeln!(w, "SECTIONS")?;
+    writeln!(w, "{{")?;
+    for (_, section) in obj.sections.iter().sorted_by_key(|(_, s)| s.address) {
+        let ty = if section.kind == ObjSectionKind::Bss { " (NOLOAD)" } else { "" };
+        writeln!(
+            w,
+            "    {} {:#010X}{} : ALIGN({:#X}) {{ *({}) }}",
+            section.name,
+            section.address,
+            ty,
+            section.align.max(1),
+            section.name
+        )?;
+    }
+    writeln!(w, "}}")?;
+    writeln!(w)?;
+
+    if let Some((entry, None)) = entry {
+        writeln!(w, "PROVIDE(__entry = {:#010X});", entry)?;
+    }
+    for (name, value) in [
+        ("_SDA_BASE_", obj.sda_base),
+        ("_SDA2_BASE_", obj.sda2_base),
+        ("_stack_addr", obj.stack_address),
+        ("_stack_end", obj.stack_end),
+        ("_db_stack_addr", obj.db_stack_addr),
+        ("__ArenaLo", obj.arena_lo),
+        ("__ArenaHi", obj.arena_hi),
+    ] {
+        if let Some(value) = value {
+            writeln!(w, "PROVIDE({} = {:#010X});", name, value)?;
+        }
+    }
+    Ok(())
+}
+
 pub fn obj_path_for_unit(unit: &str) -> PathBuf { PathBuf::from_slash(unit).with_extension("o") }
 
 pub fn asm_path_for_unit(unit: &str) -> PathBuf { PathBuf::from_slash(unit).with_extension("s") }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::{test_section, ObjArchitecture, ObjSection, ObjSymbol};
+
+    #[test]
+    fn test_write_gnu_ldscript() {
+        let section = |name: &str, kind: ObjSectionKind, address, size: u64| {
+            let data = if kind == ObjSectionKind::Bss { vec![] } else { vec![0; size as usize] };
+            ObjSection { size, align: 0x20, ..test_section(name, kind, address, data) }
+        };
+        let mut obj = ObjInfo::new(
+            ObjKind::Executable,
+            ObjArchitecture::PowerPc,
+            "test".to_string(),
+            vec![ObjSymbol {
+                name: "__start".to_string(),
+                address: 0x80003100,
+                section: Some(1),
+                kind: ObjSymbolKind::Function,
+                ..Default::default()
+            }],
+            vec![
+                section(".bss", ObjSectionKind::Bss, 0x80004000, 0x100),
+                section(".text", ObjSectionKind::Code, 0x80003100, 0x100),
+            ],
+        );
+        obj.entry = Some(0x80003100);
+        obj.sda_base = Some(0x80008000);
+        obj.arena_lo = Some(0x80010000);
+
+        let mut out = vec![];
+        obj.write_linker_script(&mut out).unwrap();
+        let script = String::from_utf8(out).unwrap();
+        assert!(script.starts_with("ENTRY(__start)\n"));
+        assert!(script.contains("PROVIDE(_SDA_BASE_ = 0x80008000);"));
+        assert!(script.contains("PROVIDE(__ArenaLo = 0x80010000);"));
+        assert!(!script.contains("_SDA2_BASE_"));
+        let text = script.find(".text 0x80003100 : ALIGN(0x20) { *(.text) }").unwrap();
+        let bss = script.find(".bss 0x80004000 (NOLOAD) : ALIGN(0x20) { *(.bss) }").unwrap();
+        assert!(text < bss);
+    }
+}
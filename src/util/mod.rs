@@ -2,14 +2,17 @@ use std::{borrow::Cow, ops::Deref};
 
 pub mod alf;
 pub mod asm;
+pub mod banner;
 pub mod bin2c;
 pub mod comment;
 pub mod config;
 pub mod dep;
 pub mod diff;
+pub mod disc;
 pub mod dol;
 pub mod dwarf;
 pub mod elf;
+pub mod extab;
 pub mod file;
 pub mod lcf;
 pub mod map;
@@ -21,6 +24,7 @@ pub mod rel;
 pub mod rso;
 pub mod signatures;
 pub mod split;
+pub mod symbols;
 pub mod take_seek;
 pub mod u8_arc;
 
@@ -1,5 +1,6 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, ensure, Result};
 use orthrus_ncompress::{yay0::Yay0, yaz0::Yaz0};
+use rayon::prelude::*;
 
 pub const YAZ0_MAGIC: [u8; 4] = *b"Yaz0";
 pub const YAY0_MAGIC: [u8; 4] = *b"Yay0";
@@ -18,6 +19,186 @@ pub fn decompress_yaz0(input: &[u8]) -> Result<Box<[u8]>> {
     Yaz0::decompress_from(input).map_err(|e| anyhow!(e))
 }
 
+/// Decompresses Yaz0 data, erroring before allocating if the header's declared size exceeds
+/// `max_output` bytes. The output buffer is sized by the header, so decompression can't grow
+/// past the declared size.
+pub fn decompress_yaz0_limited(input: &[u8], max_output: usize) -> Result<Box<[u8]>> {
+    ensure!(input.len() >= 16 && input[0..4] == YAZ0_MAGIC, "Invalid Yaz0 header");
+    let size = u32::from_be_bytes(input[4..8].try_into().unwrap()) as usize;
+    ensure!(size <= max_output, "Yaz0 declared size {:#X} exceeds limit {:#X}", size, max_output);
+    decompress_yaz0(input)
+}
+
+/// Size declared by a Yaz0 or Yay0 header, or `None` if the data isn't compressed.
+pub fn decompressed_size(input: &[u8]) -> Option<usize> {
+    if input.len() < 16 || (input[0..4] != YAZ0_MAGIC && input[0..4] != YAY0_MAGIC) {
+        return None;
+    }
+    Some(u32::from_be_bytes(input[4..8].try_into().unwrap()) as usize)
+}
+
+/// Decompresses Yaz0 data into `output`, which must be exactly the size declared by the
+/// header. Allows decompressing into memory that isn't heap allocated, e.g. a mapped file.
+pub fn decompress_yaz0_into(input: &[u8], output: &mut [u8]) -> Result<()> {
+    ensure!(input.len() >= 16 && input[0..4] == YAZ0_MAGIC, "Invalid Yaz0 header");
+    let size = u32::from_be_bytes(input[4..8].try_into().unwrap()) as usize;
+    ensure!(size == output.len(), "Yaz0 size {:#X} doesn't match output", size);
+    let mut src = input[16..].iter().copied();
+    let mut next = || src.next().ok_or_else(|| anyhow!("Unexpected end of Yaz0 data"));
+    let (mut pos, mut code, mut bits) = (0, 0u8, 0);
+    while pos < size {
+        if bits == 0 {
+            code = next()?;
+            bits = 8;
+        }
+        if code & 0x80 != 0 {
+            output[pos] = next()?;
+            pos += 1;
+        } else {
+            let (b1, b2) = (next()?, next()?);
+            let distance = (((b1 as usize & 0xF) << 8) | b2 as usize) + 1;
+            let length = match b1 >> 4 {
+                0 => next()? as usize + 0x12,
+                n => n as usize + 2,
+            };
+            ensure!(distance <= pos && pos + length <= size, "Invalid Yaz0 back-reference");
+            for _ in 0..length {
+                output[pos] = output[pos - distance];
+                pos += 1;
+            }
+        }
+        code <<= 1;
+        bits -= 1;
+    }
+    Ok(())
+}
+
+/// Default window size for [compress_yaz0_parallel].
+pub const YAZ0_PARALLEL_WINDOW: usize = 0x40000;
+
+const YAZ0_MAX_DISTANCE: usize = 0x1000;
+const YAZ0_MIN_MATCH: usize = 3;
+const YAZ0_MAX_MATCH: usize = 0x111;
+const YAZ0_HASH_BITS: u32 = 15;
+const YAZ0_MAX_CHAIN: usize = 256;
+
+#[derive(Debug, Copy, Clone)]
+enum Yaz0Token {
+    Literal(u8),
+    Match { distance: u16, length: u16 },
+}
+
+/// Compresses the data using Yaz0 compression on multiple threads.
+///
+/// The input is split into windows of `window_size` bytes which are encoded independently.
+/// Back-references may reach up to 0x1000 bytes into the preceding window, so windows don't
+/// compress worse at their boundaries; matches never extend past the end of a window. The
+/// encoded windows are then packed into a single group-flag stream.
+pub fn compress_yaz0_parallel(input: &[u8], window_size: usize) -> Box<[u8]> {
+    let window_size = window_size.max(1);
+    let windows = (0..input.len())
+        .step_by(window_size)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|start| yaz0_encode_window(input, start, (start + window_size).min(input.len())))
+        .collect::<Vec<_>>();
+    yaz0_pack(input.len(), windows.into_iter().flatten())
+}
+
+#[inline]
+fn yaz0_hash(data: &[u8]) -> usize {
+    let value = ((data[0] as u32) << 16) | ((data[1] as u32) << 8) | data[2] as u32;
+    (value.wrapping_mul(0x9E3779B1) >> (32 - YAZ0_HASH_BITS)) as usize
+}
+
+/// Greedily encodes `input[start..end]`, searching for matches with a hash chain.
+fn yaz0_encode_window(input: &[u8], start: usize, end: usize) -> Vec<Yaz0Token> {
+    let dict_start = start.saturating_sub(YAZ0_MAX_DISTANCE);
+    let mut head = vec![usize::MAX; 1 << YAZ0_HASH_BITS];
+    let mut prev = vec![usize::MAX; end - dict_start];
+    let insert = |head: &mut [usize], prev: &mut [usize], pos: usize| {
+        if pos + YAZ0_MIN_MATCH <= input.len() {
+            let hash = yaz0_hash(&input[pos..]);
+            prev[pos - dict_start] = head[hash];
+            head[hash] = pos;
+        }
+    };
+    for pos in dict_start..start {
+        insert(&mut head, &mut prev, pos);
+    }
+
+    let mut tokens = Vec::with_capacity(end - start);
+    let mut pos = start;
+    while pos < end {
+        let max_length = YAZ0_MAX_MATCH.min(end - pos);
+        let mut best = (0, 0);
+        if max_length >= YAZ0_MIN_MATCH {
+            let mut candidate = head[yaz0_hash(&input[pos..])];
+            let mut steps = 0;
+            while candidate != usize::MAX && pos - candidate <= YAZ0_MAX_DISTANCE {
+                let length =
+                    (0..max_length).take_while(|&i| input[candidate + i] == input[pos + i]).count();
+                if length > best.1 {
+                    best = (pos - candidate, length);
+                    if length == max_length {
+                        break;
+                    }
+                }
+                steps += 1;
+                if steps >= YAZ0_MAX_CHAIN {
+                    break;
+                }
+                candidate = prev[candidate - dict_start];
+            }
+        }
+        let (distance, length) = best;
+        if length >= YAZ0_MIN_MATCH {
+            tokens.push(Yaz0Token::Match { distance: distance as u16, length: length as u16 });
+        } else {
+            tokens.push(Yaz0Token::Literal(input[pos]));
+        }
+        for i in pos..pos + length.max(1) {
+            insert(&mut head, &mut prev, i);
+        }
+        pos += length.max(1);
+    }
+    tokens
+}
+
+/// Writes a Yaz0 header and packs tokens into groups of 8 with a leading flag byte.
+fn yaz0_pack(size: usize, tokens: impl Iterator<Item = Yaz0Token>) -> Box<[u8]> {
+    let mut out = Vec::with_capacity(16 + size + size / 8 + 1);
+    out.extend_from_slice(&YAZ0_MAGIC);
+    out.extend_from_slice(&(size as u32).to_be_bytes());
+    out.extend_from_slice(&[0u8; 8]);
+    let mut flag_pos = 0;
+    for (i, token) in tokens.enumerate() {
+        let bit = i % 8;
+        if bit == 0 {
+            flag_pos = out.len();
+            out.push(0);
+        }
+        match token {
+            Yaz0Token::Literal(byte) => {
+                out[flag_pos] |= 0x80 >> bit;
+                out.push(byte);
+            }
+            Yaz0Token::Match { distance, length } => {
+                let distance = distance - 1;
+                if length < 0x12 {
+                    out.push(((length - 2) << 4) as u8 | (distance >> 8) as u8);
+                    out.push(distance as u8);
+                } else {
+                    out.push((distance >> 8) as u8);
+                    out.push(distance as u8);
+                    out.push((length - 0x12) as u8);
+                }
+            }
+        }
+    }
+    out.into_boxed_slice()
+}
+
 /// Compresses the data into a new allocated buffer using Yay0 compression.
 pub fn compress_yay0(input: &[u8]) -> Box<[u8]> {
     let mut output = vec![0u8; Yay0::worst_possible_size(input.len())];
@@ -31,3 +212,138 @@ pub fn compress_yay0(input: &[u8]) -> Box<[u8]> {
 pub fn decompress_yay0(input: &[u8]) -> Result<Box<[u8]>> {
     Yay0::decompress_from(input).map_err(|e| anyhow!(e))
 }
+
+/// Decompresses Yay0 data into `output`, which must be exactly the size declared by the
+/// header. See [decompress_yaz0_into].
+pub fn decompress_yay0_into(input: &[u8], output: &mut [u8]) -> Result<()> {
+    ensure!(input.len() >= 16 && input[0..4] == YAY0_MAGIC, "Invalid Yay0 header");
+    let word = |offset: usize| u32::from_be_bytes(input[offset..offset + 4].try_into().unwrap());
+    let (size, link_offset, chunk_offset) = (word(4) as usize, word(8) as usize, word(12) as usize);
+    ensure!(size == output.len(), "Yay0 size {:#X} doesn't match output", size);
+    ensure!(
+        link_offset <= input.len() && chunk_offset <= input.len(),
+        "Invalid Yay0 table offsets"
+    );
+    let (mut mask_pos, mut link_pos, mut chunk_pos) = (16, link_offset, chunk_offset);
+    let (mut pos, mut mask, mut bits) = (0, 0u32, 0);
+    while pos < size {
+        if bits == 0 {
+            ensure!(mask_pos + 4 <= input.len(), "Unexpected end of Yay0 data");
+            mask = word(mask_pos);
+            mask_pos += 4;
+            bits = 32;
+        }
+        if mask & 0x80000000 != 0 {
+            output[pos] =
+                *input.get(chunk_pos).ok_or_else(|| anyhow!("Unexpected end of Yay0 data"))?;
+            chunk_pos += 1;
+            pos += 1;
+        } else {
+            ensure!(link_pos + 2 <= input.len(), "Unexpected end of Yay0 data");
+            let link = u16::from_be_bytes([input[link_pos], input[link_pos + 1]]) as usize;
+            link_pos += 2;
+            let distance = (link & 0xFFF) + 1;
+            let length = match link >> 12 {
+                0 => {
+                    let length = *input
+                        .get(chunk_pos)
+                        .ok_or_else(|| anyhow!("Unexpected end of Yay0 data"))?;
+                    chunk_pos += 1;
+                    length as usize + 0x12
+                }
+                n => n + 2,
+            };
+            ensure!(distance <= pos && pos + length <= size, "Invalid Yay0 back-reference");
+            for _ in 0..length {
+                output[pos] = output[pos - distance];
+                pos += 1;
+            }
+        }
+        mask <<= 1;
+        bits -= 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    fn test_data(len: usize) -> Vec<u8> {
+        // Repetitive but not trivially compressible
+        let mut state = 0x12345678u32;
+        (0..len)
+            .map(|i| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                if i % 0x300 < 0x180 {
+                    (i % 0x40) as u8
+                } else {
+                    (state >> 24) as u8 & 0xF
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_compress_yaz0_parallel() {
+        let data = test_data(0x12345);
+        let single = compress_yaz0(&data);
+        assert_eq!(decompress_yaz0(&single).unwrap().as_ref(), data.as_slice());
+        for window_size in [0x100, 0x1000, 0x4000, YAZ0_PARALLEL_WINDOW] {
+            let parallel = compress_yaz0_parallel(&data, window_size);
+            assert_eq!(decompress_yaz0(&parallel).unwrap().as_ref(), data.as_slice());
+            assert!(parallel.len() < data.len());
+        }
+        // Single window
+        let whole = compress_yaz0_parallel(&data, data.len());
+        assert_eq!(decompress_yaz0(&whole).unwrap().as_ref(), data.as_slice());
+
+        for data in [vec![], vec![0xAB], vec![0; 0x200]] {
+            let compressed = compress_yaz0_parallel(&data, 0x80);
+            assert_eq!(decompress_yaz0(&compressed).unwrap().as_ref(), data.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_decompress_into() {
+        for data in [vec![0xAB], test_data(0x12345)] {
+            let yaz0 = compress_yaz0(&data);
+            let mut out = vec![0u8; decompressed_size(&yaz0).unwrap()];
+            decompress_yaz0_into(&yaz0, &mut out).unwrap();
+            assert_eq!(out, data);
+            let yay0 = compress_yay0(&data);
+            let mut out = vec![0u8; decompressed_size(&yay0).unwrap()];
+            decompress_yay0_into(&yay0, &mut out).unwrap();
+            assert_eq!(out, data);
+        }
+        let yaz0 = compress_yaz0(&test_data(0x100));
+        assert!(decompress_yaz0_into(&yaz0, &mut [0u8; 0xFF]).is_err());
+        assert!(decompress_yaz0_into(&yaz0[..yaz0.len() - 4], &mut [0u8; 0x100]).is_err());
+        assert_eq!(decompressed_size(&test_data(0x100)), None);
+    }
+
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_compress_yaz0_parallel() {
+        let data = test_data(0x1000000);
+        let start = Instant::now();
+        let single = compress_yaz0(&data);
+        let single_time = start.elapsed();
+        let start = Instant::now();
+        let parallel = compress_yaz0_parallel(&data, YAZ0_PARALLEL_WINDOW);
+        let parallel_time = start.elapsed();
+        println!(
+            "single: {:?} ({:#X} bytes), parallel: {:?} ({:#X} bytes)",
+            single_time,
+            single.len(),
+            parallel_time,
+            parallel.len()
+        );
+        assert_eq!(decompress_yaz0(&parallel).unwrap().as_ref(), data.as_slice());
+        if rayon::current_num_threads() > 1 {
+            assert!(parallel_time < single_time);
+        }
+    }
+}
@@ -4,22 +4,26 @@
 use std::{
     collections::HashMap,
     fmt::Display,
+    fs,
     hash::{Hash, Hasher},
     io,
     io::{Read, Seek, SeekFrom},
     path::{Component, Path, PathBuf},
 };
 
-use anyhow::{anyhow, bail, ensure, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 
 use crate::util::{
-    file::read_c_string,
+    file::{decompress_if_needed, read_c_string_sjis},
     reader::{struct_size, Endian, FromReader},
 };
 
 #[derive(Debug, Clone)]
 pub struct NamedHash {
+    /// Name, decoded from Shift-JIS.
     pub name: String,
+    /// Raw bytes of the name.
+    pub raw_name: Vec<u8>,
     pub hash: u16,
 }
 
@@ -251,14 +255,14 @@ impl RarcReader {
             reader.seek(SeekFrom::Start(directory_base + 20 * i as u64))?;
             let node = RarcFileNode::from_reader(reader, Endian::Big)?;
 
-            let name = {
+            let (name, raw_name) = {
                 let offset = header.string_table_offset as u64;
                 let offset = offset + node.name_offset as u64;
                 ensure!(
                     (node.name_offset as u32) < header.string_table_length,
                     "invalid string table offset"
                 );
-                read_c_string(reader, base + offset)
+                read_c_string_sjis(reader, base + offset)
             }?;
 
             if node.index == 0xFFFF {
@@ -268,12 +272,12 @@ impl RarcReader {
                     directories.push(RarcDirectory::ParentFolder);
                 } else {
                     directories.push(RarcDirectory::Folder {
-                        name: NamedHash { name, hash: node.name_hash },
+                        name: NamedHash { name, raw_name, hash: node.name_hash },
                     });
                 }
             } else {
                 directories.push(RarcDirectory::File {
-                    name: NamedHash { name, hash: node.name_hash },
+                    name: NamedHash { name, raw_name, hash: node.name_hash },
                     offset: data_base + node.data_offset as u64,
                     size: node.data_length,
                 });
@@ -295,23 +299,24 @@ impl RarcReader {
                 "last directory index out of bounds"
             );
 
-            let name = {
+            let (name, raw_name) = {
                 let offset = header.string_table_offset as u64;
                 let offset = offset + node.name_offset as u64;
                 ensure!(
                     node.name_offset < header.string_table_length,
                     "invalid string table offset"
                 );
-                read_c_string(reader, base + offset)
+                read_c_string_sjis(reader, base + offset)
             }?;
 
+            let name = NamedHash { name, raw_name, hash: node.name_hash };
+
             // FIXME: this assumes that the root node is the first node in the list
             if root_node.is_none() {
-                root_node = Some(NamedHash { name: name.clone(), hash: node.name_hash });
+                root_node = Some(name.clone());
             }
 
-            let name = NamedHash { name, hash: node.name_hash };
-            nodes.insert(name.clone(), RarcNode { index: node.index, count: node.count as u32 });
+            nodes.insert(name, RarcNode { index: node.index, count: node.count as u32 });
         }
 
         if let Some(root_node) = root_node {
@@ -339,25 +344,166 @@ impl RarcReader {
             }
         }
 
-        let mut current_path = PathBuf::new();
+        Ok(self
+            .entries()
+            .find(|entry| {
+                let path = entry.path.components().map(|c| c.as_os_str().to_ascii_lowercase());
+                path.collect::<PathBuf>() == cmp_path
+            })
+            .map(|entry| (entry.offset, entry.size)))
+    }
+
+    /// Find a file in the RARC file by its virtual path. (case-insensitive)
+    pub fn find(&self, path: &str) -> Option<(u64, u32)> { self.find_file(path).ok().flatten() }
+
+    /// Builds the directory tree of the RARC file, starting at the root directory.
+    /// The `.` and `..` entries are omitted from the tree.
+    pub fn tree(&self) -> RarcDir {
+        let mut stack: Vec<RarcDir> = vec![];
         for node in self.nodes() {
             match node {
                 Node::DirectoryBegin { name } => {
-                    current_path.push(name.name.to_ascii_lowercase());
+                    stack.push(RarcDir { name: name.name, ..Default::default() });
                 }
                 Node::DirectoryEnd { name: _ } => {
-                    current_path.pop();
+                    let Some(dir) = stack.pop() else { break };
+                    match stack.last_mut() {
+                        Some(parent) => parent.dirs.push(dir),
+                        None => return dir,
+                    }
                 }
                 Node::File { name, offset, size } => {
-                    if current_path.join(name.name.to_ascii_lowercase()) == cmp_path {
-                        return Ok(Some((offset, size)));
+                    if let Some(dir) = stack.last_mut() {
+                        dir.files.push(RarcFile { name: name.name, offset, size });
+                    }
+                }
+                Node::CurrentDirectory | Node::ParentDirectory => {}
+            }
+        }
+        // Unterminated tree; close any open directories
+        let mut dir = stack.pop().unwrap_or_default();
+        while let Some(mut parent) = stack.pop() {
+            parent.dirs.push(dir);
+            dir = parent;
+        }
+        dir
+    }
+
+    /// Get an iterator over the files in the RARC file, with their full paths.
+    pub fn entries(&self) -> impl Iterator<Item = RarcEntry> + '_ {
+        let mut current_path = PathBuf::new();
+        self.nodes().filter_map(move |node| match node {
+            Node::DirectoryBegin { name } => {
+                current_path.push(name.name);
+                None
+            }
+            Node::DirectoryEnd { name: _ } => {
+                current_path.pop();
+                None
+            }
+            Node::File { name, offset, size } => {
+                Some(RarcEntry { path: current_path.join(name.name), offset, size })
+            }
+            Node::CurrentDirectory => None,
+            Node::ParentDirectory => None,
+        })
+    }
+}
+
+/// Extracts every file in a RARC archive to `out_dir`, under its virtual path.
+/// Yaz0/Yay0 compressed files are decompressed. Returns the list of written paths.
+pub fn extract_to<R>(reader: &mut R, out_dir: &Path) -> Result<Vec<PathBuf>>
+where R: Read + Seek + ?Sized {
+    let rarc = RarcReader::new(reader)?;
+    let mut written = vec![];
+    for entry in rarc.entries() {
+        ensure!(
+            entry.path.components().all(|c| matches!(c, Component::Normal(_))),
+            "Invalid path in RARC: {}",
+            entry.path.display()
+        );
+        let mut data = vec![0u8; entry.size as usize];
+        reader.seek(SeekFrom::Start(entry.offset))?;
+        reader
+            .read_exact(&mut data)
+            .with_context(|| format!("Failed to read '{}'", entry.path.display()))?;
+        let data = decompress_if_needed(&data)?;
+        let out_path = out_dir.join(&entry.path);
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&out_path, data)
+            .with_context(|| format!("Failed to write file '{}'", out_path.display()))?;
+        written.push(out_path);
+    }
+    Ok(written)
+}
+
+/// A file in an RARC file, as returned by [RarcReader::entries].
+#[derive(Debug, Clone)]
+pub struct RarcEntry {
+    /// Path of the file, including the root directory.
+    pub path: PathBuf,
+    /// Offset of the file data, relative to the start of the RARC file.
+    pub offset: u64,
+    /// Size of the file data.
+    pub size: u32,
+}
+
+/// A directory in an RARC file, as returned by [RarcReader::tree].
+#[derive(Debug, Clone, Default)]
+pub struct RarcDir {
+    /// Name of the directory.
+    pub name: String,
+    /// Subdirectories, in archive order.
+    pub dirs: Vec<RarcDir>,
+    /// Files directly within this directory, in archive order.
+    pub files: Vec<RarcFile>,
+}
+
+/// A file in an RARC directory tree.
+#[derive(Debug, Clone)]
+pub struct RarcFile {
+    /// Name of the file.
+    pub name: String,
+    /// Offset of the file data, relative to the start of the RARC file.
+    pub offset: u64,
+    /// Size of the file data.
+    pub size: u32,
+}
+
+impl RarcDir {
+    /// Find a subdirectory by its path components relative to this directory.
+    /// (case-insensitive) `.` and `..` components are resolved, but can't leave this
+    /// directory.
+    pub fn dir<'a, I>(&self, components: I) -> Option<&RarcDir>
+    where I: IntoIterator<Item = &'a str> {
+        let mut stack = vec![self];
+        for component in components {
+            match component {
+                "" | "." => {}
+                ".." => {
+                    if stack.len() == 1 {
+                        return None;
                     }
+                    stack.pop();
+                }
+                name => {
+                    let dir =
+                        stack.last()?.dirs.iter().find(|d| d.name.eq_ignore_ascii_case(name))?;
+                    stack.push(dir);
                 }
-                Node::CurrentDirectory => {}
-                Node::ParentDirectory => {}
             }
         }
-        Ok(None)
+        stack.last().copied()
+    }
+
+    /// Find a file by its path components relative to this directory. (case-insensitive)
+    pub fn file<'a, I>(&self, components: I) -> Option<&RarcFile>
+    where I: IntoIterator<Item = &'a str> {
+        let mut components = components.into_iter().collect::<Vec<_>>();
+        let name = components.pop()?;
+        self.dir(components)?.files.iter().find(|f| f.name.eq_ignore_ascii_case(name))
     }
 }
 
@@ -423,3 +569,126 @@ impl<'parent> Iterator for Nodes<'parent> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{env, io::Cursor, process};
+
+    use super::*;
+
+    fn push_u16(out: &mut Vec<u8>, v: u16) { out.extend_from_slice(&v.to_be_bytes()); }
+
+    fn push_u32(out: &mut Vec<u8>, v: u32) { out.extend_from_slice(&v.to_be_bytes()); }
+
+    /// root/a.txt, root/sub/テスト.bin (Shift-JIS)
+    fn test_rarc() -> Vec<u8> {
+        let strings = b"root\0sub\0a.txt\0.\0..\0\x83\x65\x83\x58\x83\x67.bin\0";
+        // (index, name_offset, data_offset, data_length)
+        let directories: [(u16, u16, u32, u32); 7] = [
+            (0, 9, 0, 3),
+            (0xFFFF, 5, 0, 0),
+            (0xFFFF, 15, 0, 0),
+            (0xFFFF, 17, 0, 0),
+            (1, 20, 4, 2),
+            (0xFFFF, 15, 0, 0),
+            (0xFFFF, 17, 0, 0),
+        ];
+        let string_table_offset = 0x40 + 20 * directories.len() as u32;
+        let file_offset = string_table_offset + strings.len() as u32;
+
+        let mut out = vec![];
+        out.extend_from_slice(&RARC_MAGIC);
+        push_u32(&mut out, 0); // file_length
+        push_u32(&mut out, 0x20); // header_length
+        push_u32(&mut out, file_offset);
+        out.resize(0x20, 0);
+        push_u32(&mut out, 2); // node_count
+        push_u32(&mut out, 0x20); // node_offset
+        push_u32(&mut out, directories.len() as u32);
+        push_u32(&mut out, 0x40); // directory_offset
+        push_u32(&mut out, strings.len() as u32);
+        push_u32(&mut out, string_table_offset);
+        out.resize(0x40, 0);
+        // (name_offset, count, index)
+        for (name_offset, count, index) in [(0u32, 4u16, 0u32), (5, 3, 4)] {
+            push_u32(&mut out, 0); // identifier
+            push_u32(&mut out, name_offset);
+            push_u16(&mut out, 0); // name_hash
+            push_u16(&mut out, count);
+            push_u32(&mut out, index);
+        }
+        for (index, name_offset, data_offset, data_length) in directories {
+            push_u16(&mut out, index);
+            push_u16(&mut out, 0); // name_hash
+            push_u16(&mut out, 0);
+            push_u16(&mut out, name_offset);
+            push_u32(&mut out, data_offset);
+            push_u32(&mut out, data_length);
+            push_u32(&mut out, 0);
+        }
+        out.extend_from_slice(strings);
+        out.extend_from_slice(b"abc\0de");
+        out
+    }
+
+    #[test]
+    fn test_find() {
+        let data = test_rarc();
+        let reader = RarcReader::new(&mut Cursor::new(data.as_slice())).unwrap();
+        let (offset, size) = reader.find("root/sub/テスト.bin").unwrap();
+        assert_eq!(&data[offset as usize..offset as usize + size as usize], b"de");
+        assert_eq!(reader.find("ROOT/A.TXT").map(|(_, size)| size), Some(3));
+        assert_eq!(reader.find("root/テスト.bin"), None);
+        let paths = reader.entries().map(|e| e.path).collect::<Vec<_>>();
+        assert_eq!(paths, vec![PathBuf::from("root/a.txt"), PathBuf::from("root/sub/テスト.bin")]);
+    }
+
+    #[test]
+    fn test_tree() {
+        let data = test_rarc();
+        let reader = RarcReader::new(&mut Cursor::new(data.as_slice())).unwrap();
+        let root = reader.tree();
+        assert_eq!(root.name, "root");
+        assert_eq!(root.files.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec!["a.txt"]);
+        assert_eq!(root.dirs.len(), 1);
+        let sub = root.dir(["SUB"]).unwrap();
+        assert_eq!(sub.name, "sub");
+        assert!(sub.dirs.is_empty());
+        assert_eq!(sub.files.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec![
+            "テスト.bin"
+        ]);
+
+        let file = root.file(["sub", "..", "sub", ".", "テスト.bin"]).unwrap();
+        assert_eq!(&data[file.offset as usize..file.offset as usize + file.size as usize], b"de");
+        assert_eq!(root.file(["a.txt"]).map(|f| f.size), Some(3));
+        assert!(root.file(["sub", "a.txt"]).is_none());
+        assert!(root.dir([".."]).is_none());
+    }
+
+    #[test]
+    fn test_extract_to() {
+        let data = test_rarc();
+        let dir = env::temp_dir().join(format!("dtk-test-rarc-{}", process::id()));
+        let written = extract_to(&mut Cursor::new(data.as_slice()), &dir).unwrap();
+        let contents = written.iter().map(|p| fs::read(p).unwrap()).collect::<Vec<_>>();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(written, vec![dir.join("root/a.txt"), dir.join("root/sub/テスト.bin")]);
+        assert_eq!(contents, vec![b"abc".to_vec(), b"de".to_vec()]);
+    }
+
+    #[test]
+    fn test_sjis_name() {
+        let data = test_rarc();
+        let reader = RarcReader::new(&mut Cursor::new(data.as_slice())).unwrap();
+        let name = reader
+            .nodes()
+            .filter_map(|node| match node {
+                Node::File { name, .. } => Some(name),
+                _ => None,
+            })
+            .last()
+            .unwrap();
+        assert_eq!(name.name, "テスト.bin");
+        assert_eq!(name.raw_name, b"\x83\x65\x83\x58\x83\x67.bin");
+    }
+}
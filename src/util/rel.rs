@@ -23,6 +23,13 @@ use crate::{
     },
 };
 
+/// RELs are only produced for PowerPC targets.
+const REL_ARCH: ObjArchitecture = ObjArchitecture::PowerPc;
+
+/// Byte order of REL structures and relocated code.
+#[inline]
+fn rel_endian() -> Endian { REL_ARCH.arch().endian() }
+
 /// Do not relocate anything, but accumulate the offset field for the next relocation offset calculation.
 /// These types are used for referring to relocations that are more than 0xffff apart from each other.
 pub const R_DOLPHIN_NOP: u32 = 201;
@@ -350,7 +357,7 @@ impl ToWriter for RelRelocRaw {
 
 pub fn process_rel_header<R>(reader: &mut R) -> Result<RelHeader>
 where R: Read + Seek + ?Sized {
-    RelHeader::from_reader(reader, Endian::Big).context("Failed to read REL header")
+    RelHeader::from_reader(reader, rel_endian()).context("Failed to read REL header")
 }
 
 pub fn process_rel_sections<R>(
@@ -363,7 +370,7 @@ where
     let mut sections = Vec::with_capacity(header.num_sections as usize);
     reader.seek(SeekFrom::Start(header.section_info_offset as u64))?;
     for idx in 0..header.num_sections {
-        let section = RelSectionHeader::from_reader(reader, Endian::Big)
+        let section = RelSectionHeader::from_reader(reader, rel_endian())
             .with_context(|| format!("Failed to read REL section header {}", idx))?;
         sections.push(section);
     }
@@ -424,6 +431,7 @@ where R: Read + Seek + ?Sized {
             file_offset: offset as u64,
             section_known,
             splits: Default::default(),
+            compressed_size_cache: Default::default(),
         });
     }
     ensure!(
@@ -467,7 +475,7 @@ where R: Read + Seek + ?Sized {
     let imp_end = (header.imp_offset + header.imp_size) as u64;
     reader.seek(SeekFrom::Start(header.imp_offset as u64))?;
     while reader.stream_position()? < imp_end {
-        let import = RelImport::from_reader(reader, Endian::Big)?;
+        let import = RelImport::from_reader(reader, rel_endian())?;
 
         if imp_idx == 0 {
             ensure!(
@@ -495,7 +503,7 @@ where R: Read + Seek + ?Sized {
         let mut address = 0u32;
         let mut section = u8::MAX;
         loop {
-            let reloc = RelRelocRaw::from_reader(reader, Endian::Big)?;
+            let reloc = RelRelocRaw::from_reader(reader, rel_endian())?;
             let kind = match reloc.kind as u32 {
                 elf::R_PPC_NONE => continue,
                 elf::R_PPC_ADDR32 | elf::R_PPC_UADDR32 => ObjRelocKind::Absolute,
@@ -558,7 +566,7 @@ where R: Read + Seek + ?Sized {
     let imp_end = (header.imp_offset + header.imp_size) as u64;
     reader.seek(SeekFrom::Start(header.imp_offset as u64))?;
     while reader.stream_position()? < imp_end {
-        let import = RelImport::from_reader(reader, Endian::Big)?;
+        let import = RelImport::from_reader(reader, rel_endian())?;
         println!("Module {} (file offset {:#X}):", import.module_id, import.offset);
 
         let position = reader.stream_position()?;
@@ -566,7 +574,7 @@ where R: Read + Seek + ?Sized {
         let mut address = 0u32;
         let mut section = u8::MAX;
         loop {
-            let reloc = RelRelocRaw::from_reader(reader, Endian::Big)?;
+            let reloc = RelRelocRaw::from_reader(reader, rel_endian())?;
             let kind = match reloc.kind as u32 {
                 elf::R_PPC_NONE => continue,
                 elf::R_PPC_ADDR32 | elf::R_PPC_UADDR32 => ObjRelocKind::Absolute,
@@ -624,14 +632,14 @@ pub struct RelReloc {
 
 #[inline]
 fn reloc_can_be_applied(_module_id: u32, rel_reloc: &RelReloc) -> bool {
-    matches!(rel_reloc.kind, ObjRelocKind::PpcRel24 | ObjRelocKind::PpcRel14)
+    rel_reloc.kind.is_pc_relative()
 }
 
 #[inline]
 fn skip_reloc(module_id: u32, rel_reloc: &RelReloc) -> bool {
     rel_reloc.module_id == module_id
         && rel_reloc.section == rel_reloc.target_section
-        && matches!(rel_reloc.kind, ObjRelocKind::PpcRel24 | ObjRelocKind::PpcRel14)
+        && rel_reloc.kind.is_pc_relative()
 }
 
 fn apply_relocation(
@@ -648,8 +656,9 @@ fn apply_relocation(
     } else {
         return Ok(());
     };
+    let arch = REL_ARCH.arch();
     let ins_ref = array_ref_mut!(data, rel_reloc.address as usize, 4);
-    let mut ins = u32::from_be_bytes(*ins_ref);
+    let mut ins = arch.read_u32(*ins_ref);
     match rel_reloc.kind {
         ObjRelocKind::PpcRel24 => {
             ensure!((-0x2000000..0x2000000).contains(&diff), "R_PPC_REL24 relocation out of range");
@@ -661,7 +670,7 @@ fn apply_relocation(
         }
         kind => bail!("Unsupported relocation kind {:?}", kind),
     }
-    *ins_ref = ins.to_be_bytes();
+    *ins_ref = arch.write_u32(ins);
     Ok(())
 }
 
@@ -992,14 +1001,14 @@ where
         }
     }
 
-    header.to_writer(w, Endian::Big)?;
+    header.to_writer(w, rel_endian())?;
     ensure!(w.stream_position()? as u32 == header.section_info_offset);
     let mut current_data_offset = section_data_offset;
     let mut permitted_section_idx = 0;
     for section_index in 0..num_sections {
         let Ok(section) = file.section_by_index(object::SectionIndex(section_index as usize))
         else {
-            RelSectionHeader::new(0, 0, false).to_writer(w, Endian::Big)?;
+            RelSectionHeader::new(0, 0, false).to_writer(w, rel_endian())?;
             continue;
         };
         if is_permitted_section(&section) {
@@ -1015,10 +1024,11 @@ where
                 .as_ref()
                 .and_then(|m| m.get(section_index as usize).copied())
                 .unwrap_or(section.kind() == object::SectionKind::Text);
-            RelSectionHeader::new(offset, section.size() as u32, exec).to_writer(w, Endian::Big)?;
+            RelSectionHeader::new(offset, section.size() as u32, exec)
+                .to_writer(w, rel_endian())?;
             permitted_section_idx += 1;
         } else {
-            RelSectionHeader::new(0, 0, false).to_writer(w, Endian::Big)?;
+            RelSectionHeader::new(0, 0, false).to_writer(w, rel_endian())?;
         }
     }
     ensure!(w.stream_position()? as u32 == section_data_offset);
@@ -1057,18 +1067,18 @@ where
             // Version 1 and 2 RELs write relocations before the import table.
             ensure!(w.stream_position()? as u32 == header.rel_offset);
             for reloc in &raw_relocations {
-                reloc.to_writer(w, Endian::Big)?;
+                reloc.to_writer(w, rel_endian())?;
             }
         }
         ensure!(w.stream_position()? as u32 == header.imp_offset);
         for entry in &imp_entries {
-            entry.to_writer(w, Endian::Big)?;
+            entry.to_writer(w, rel_endian())?;
         }
         if info.version >= 3 {
             // Version 3 RELs write relocations after the import table. See above.
             ensure!(w.stream_position()? as u32 == header.rel_offset);
             for reloc in &raw_relocations {
-                reloc.to_writer(w, Endian::Big)?;
+                reloc.to_writer(w, rel_endian())?;
             }
         }
     }
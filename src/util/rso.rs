@@ -414,6 +414,7 @@ where R: Read + Seek + ?Sized {
             file_offset: offset as u64,
             section_known: false,
             splits: Default::default(),
+            compressed_size_cache: Default::default(),
         });
         if offset == 0 {
             total_bss_size += size;
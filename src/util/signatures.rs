@@ -195,7 +195,7 @@ pub fn apply_signature(
             module: None,
         };
         // log::info!("Applying relocation {:#010X?}", obj_reloc);
-        obj.sections[addr.section].relocations.insert(reloc_addr.address, obj_reloc)?;
+        obj.insert_relocation(addr.section, reloc_addr.address, obj_reloc)?;
     }
     for reloc in &signature.relocations {
         let addr = addr + reloc.offset;
@@ -13,9 +13,9 @@ use tracing_attributes::instrument;
 use crate::{
     analysis::{cfa::SectionAddress, read_address, read_u32},
     obj::{
-        ObjArchitecture, ObjInfo, ObjKind, ObjReloc, ObjRelocations, ObjSection, ObjSectionKind,
-        ObjSplit, ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind, ObjSymbolScope,
-        ObjUnit,
+        AutoUnitNaming, ObjArchitecture, ObjInfo, ObjKind, ObjReloc, ObjRelocations, ObjSection,
+        ObjSectionKind, ObjSplit, ObjSymbol, ObjSymbolFlagSet, ObjSymbolFlags, ObjSymbolKind,
+        ObjSymbolScope, ObjUnit,
     },
     util::{align_up, comment::MWComment},
 };
@@ -105,9 +105,7 @@ fn split_ctors_dtors(obj: &mut ObjInfo, start: SectionAddress, end: SectionAddre
         current_address += 4;
     }
 
-    for (addr, split) in new_splits {
-        obj.add_split(addr.section, addr.address, split)?;
-    }
+    obj.add_splits(new_splits)?;
 
     // Hack to avoid deadstripping
     for symbol_idx in referenced_symbols {
@@ -298,9 +296,7 @@ fn split_extabindex(obj: &mut ObjInfo, start: SectionAddress) -> Result<()> {
         current_address += 12;
     }
 
-    for (addr, split) in new_splits {
-        obj.add_split(addr.section, addr.address, split)?;
-    }
+    obj.add_splits(new_splits)?;
 
     Ok(())
 }
@@ -435,9 +431,7 @@ fn create_gap_splits(obj: &mut ObjInfo) -> Result<()> {
     }
 
     // Add new splits
-    for (addr, split) in new_splits {
-        obj.add_split(addr.section, addr.address, split)?;
-    }
+    obj.add_splits(new_splits)?;
 
     Ok(())
 }
@@ -1111,6 +1105,7 @@ pub fn split_obj(obj: &ObjInfo, module_name: Option<&str>) -> Result<Vec<ObjInfo
                         + (current_address.address as u64 - section.address),
                     section_known: true,
                     splits: Default::default(),
+                    compressed_size_cache: Default::default(),
                 });
             }
 
@@ -1361,8 +1356,8 @@ pub fn end_for_section(obj: &ObjInfo, section_index: usize) -> Result<SectionAdd
     Ok(SectionAddress::new(section_index, section_end))
 }
 
-/// Generates a unit name for an autogenerated split.
-/// The name is based on the symbol name and section name.
+/// Generates a unit name for an autogenerated split, per [ObjInfo::auto_unit_naming]:
+/// based on the symbol name and section name, or on the symbol address.
 /// If the name is not unique, a number is appended to the end.
 fn auto_unit_name(
     obj: &ObjInfo,
@@ -1373,13 +1368,23 @@ fn auto_unit_name(
         .section
         .and_then(|idx| obj.sections.get(idx).map(|s| s.name.clone()))
         .unwrap_or_else(|| "unknown".to_string());
-    let name = sanitize_with_options(&symbol.name, &sanitise_file_name::Options {
-        length_limit: 20,
-        ..Default::default()
-    })
-    // Also replace $ to avoid issues with build.ninja
-    .replace('$', "_");
-    let mut unit_name = format!("auto_{}_{}", name, section_name.trim_start_matches('.'));
+    let mut unit_name = match obj.auto_unit_naming {
+        AutoUnitNaming::Symbol => {
+            let name = sanitize_with_options(&symbol.name, &sanitise_file_name::Options {
+                length_limit: 20,
+                ..Default::default()
+            })
+            // Also replace $ to avoid issues with build.ninja
+            .replace('$', "_");
+            format!("auto_{}_{}", name, section_name.trim_start_matches('.'))
+        }
+        AutoUnitNaming::Address => format!(
+            "auto_{:02}_{:08X}_{}",
+            symbol.section.unwrap_or_default(),
+            symbol.address,
+            section_name.trim_start_matches('.')
+        ),
+    };
     // Ensure the name is unique
     if unit_exists(&unit_name, obj, new_splits) {
         let mut i = 1;
@@ -1409,3 +1414,43 @@ fn unit_exists(
             .any(|(_, split)| split.unit.eq_ignore_ascii_case(unit_name))
         || new_splits.values().any(|split| split.unit.eq_ignore_ascii_case(unit_name))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj::test_section;
+
+    #[test]
+    fn test_auto_unit_name() {
+        let build = |naming| {
+            let mut obj = ObjInfo::new(
+                ObjKind::Executable,
+                ObjArchitecture::PowerPc,
+                "test".to_string(),
+                vec![],
+                vec![test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x10])],
+            );
+            obj.auto_unit_naming = naming;
+            obj.link_order.push(ObjUnit {
+                name: "auto_fn_80003100_text".to_string(),
+                autogenerated: true,
+                comment_version: None,
+            });
+            obj
+        };
+        let symbol = ObjSymbol {
+            name: "fn_80003100".to_string(),
+            address: 0x80003100,
+            section: Some(0),
+            kind: ObjSymbolKind::Function,
+            ..Default::default()
+        };
+        let name = |naming| auto_unit_name(&build(naming), &symbol, &BTreeMap::new()).unwrap();
+
+        // Identical input yields identical names
+        assert_eq!(name(AutoUnitNaming::Symbol), name(AutoUnitNaming::Symbol));
+        assert_eq!(name(AutoUnitNaming::Address), name(AutoUnitNaming::Address));
+        assert_eq!(name(AutoUnitNaming::Symbol), "auto_fn_80003100_text_1");
+        assert_eq!(name(AutoUnitNaming::Address), "auto_00_80003100_text");
+    }
+}
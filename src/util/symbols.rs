@@ -0,0 +1,135 @@
+use std::io::BufRead;
+
+use anyhow::{anyhow, Context, Result};
+use cwdemangle::{demangle, DemangleOptions};
+
+use crate::{
+    obj::{ObjInfo, ObjKind, ObjSymbol, ObjSymbolKind},
+    util::config::parse_u32,
+};
+
+/// A symbol parsed by [read_symbol_list]: name, address and optional kind hint.
+pub type SymbolListEntry = (String, u32, Option<ObjSymbolKind>);
+
+/// Parses a simple symbol list, one `name = address` per line, e.g.
+///
+/// ```text
+/// # Labels from the debugger
+/// FooBar = 0x80001234 # func
+/// gData = 0x80400000 # object
+/// ```
+///
+/// Anything after `#` is a comment. A comment consisting of `func`/`function`,
+/// `object`/`data` or `label` is taken as the symbol kind.
+pub fn read_symbol_list<R>(reader: &mut R) -> Result<Vec<SymbolListEntry>>
+where R: BufRead + ?Sized {
+    let mut out = vec![];
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let (line, comment) = match line.split_once('#') {
+            Some((line, comment)) => (line.trim(), Some(comment.trim())),
+            None => (line.trim(), None),
+        };
+        if line.is_empty() {
+            continue;
+        }
+        let (name, address) = line
+            .split_once('=')
+            .map(|(name, address)| (name.trim(), address.trim()))
+            .filter(|(name, _)| !name.is_empty())
+            .ok_or_else(|| anyhow!("Invalid symbol on line {}: '{}'", line_number + 1, line))?;
+        let address = parse_u32(address).with_context(|| {
+            format!("Invalid address on line {}: '{}'", line_number + 1, address)
+        })?;
+        let kind = comment.and_then(|comment| match comment {
+            "func" | "function" => Some(ObjSymbolKind::Function),
+            "object" | "data" => Some(ObjSymbolKind::Object),
+            "label" => Some(ObjSymbolKind::Unknown),
+            _ => None,
+        });
+        out.push((name.to_string(), address, kind));
+    }
+    Ok(out)
+}
+
+/// Adds symbols from [read_symbol_list] to an executable object. Symbols whose name
+/// already exists are skipped. Returns the number of symbols added.
+pub fn apply_symbol_list(obj: &mut ObjInfo, symbols: Vec<SymbolListEntry>) -> Result<usize> {
+    let mut count = 0;
+    for (name, address, kind) in symbols {
+        if obj.symbols.by_name(&name)?.is_some() {
+            log::debug!("Skipping existing symbol {}", name);
+            continue;
+        }
+        let section = match obj.kind {
+            ObjKind::Executable => obj.sections.at_address(address).ok().map(|(index, _)| index),
+            ObjKind::Relocatable => None,
+        };
+        let demangled_name = demangle(&name, &DemangleOptions::default());
+        obj.add_symbol(
+            ObjSymbol {
+                name,
+                demangled_name,
+                address: address as u64,
+                section,
+                kind: kind.unwrap_or_default(),
+                ..Default::default()
+            },
+            false,
+        )?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::obj::{test_section, ObjArchitecture, ObjSectionKind};
+
+    const LIST: &str = "
+# Labels from the debugger
+FooBar = 0x80003100 # func
+gData=0x80003180   # object
+lbl_80003140 = 0x80003140 # TODO
+  OSReport = 2147496960
+";
+
+    #[test]
+    fn test_read_symbol_list() {
+        let symbols = read_symbol_list(&mut Cursor::new(LIST)).unwrap();
+        assert_eq!(symbols, vec![
+            ("FooBar".to_string(), 0x80003100, Some(ObjSymbolKind::Function)),
+            ("gData".to_string(), 0x80003180, Some(ObjSymbolKind::Object)),
+            ("lbl_80003140".to_string(), 0x80003140, None),
+            ("OSReport".to_string(), 0x80003400, None),
+        ]);
+        assert!(read_symbol_list(&mut Cursor::new("FooBar 0x80003100")).is_err());
+        assert!(read_symbol_list(&mut Cursor::new("FooBar = bar")).is_err());
+    }
+
+    #[test]
+    fn test_apply_symbol_list() {
+        let section = test_section(".text", ObjSectionKind::Code, 0x80003100, vec![0; 0x100]);
+        let mut obj = ObjInfo::new(
+            ObjKind::Executable,
+            ObjArchitecture::PowerPc,
+            "test".to_string(),
+            vec![],
+            vec![section],
+        );
+        let mut symbols = read_symbol_list(&mut Cursor::new(LIST)).unwrap();
+        symbols.push(("FooBar".to_string(), 0x80003104, None));
+        assert_eq!(apply_symbol_list(&mut obj, symbols).unwrap(), 4);
+
+        let (_, symbol) = obj.symbols.by_name("FooBar").unwrap().unwrap();
+        assert_eq!(
+            (symbol.address, symbol.section, symbol.kind),
+            (0x80003100, Some(0), ObjSymbolKind::Function)
+        );
+        let (_, symbol) = obj.symbols.by_name("OSReport").unwrap().unwrap();
+        assert_eq!(symbol.section, None);
+    }
+}